@@ -6,6 +6,10 @@ use thorn::math::*;
 use thorn::prelude::*;
 
 
+// Lets the runner load this plugin from its plugins directory when built as a shared library
+thorn::export_plugin!(SamplePlugin);
+
+
 pub struct SamplePlugin;
 impl Plugin<LayerEvent> for SamplePlugin
 {
@@ -16,81 +20,74 @@ impl Plugin<LayerEvent> for SamplePlugin
             .dep::<GobjectManager>()
             .dep::<Core>()
             .dep::<Tasks>()
+            .optional_dep::<Renderer>()
     }
 
     fn load(
         &mut self,
-        reg: &LayerReg<LayerEvent>,
+        ctx: &PluginContext<LayerEvent>,
     ) -> Result<AnyLayer<LayerEvent>, Box<dyn std::error::Error>>
     {
-        let tasks = reg
-            .get()
-            .ok_or(ThError::Error("Failed to fetch engine task layer".into()))?;
-
-        let gobj_manager = reg
-            .get()
-            .ok_or(ThError::Error("Failed to fetch engine gobj manager".into()))?;
-
-        let platform_events = reg.get().ok_or(ThError::Error(
-            "Failed to fetch platform event receiver".into(),
-        ))?;
-
-        let core = reg
+        let core = ctx
             .get()
             .ok_or(ThError::Error("Failed to fetch core layer".into()))?;
 
-        Ok(AnyLayer::new(Sample {
-            tasks,
-            gobj_manager,
-            core,
-            platform_events,
-        }))
+        Ok(AnyLayer::new(Sample { core }))
     }
 
-    fn notify_loaded(&mut self, reg: &LayerReg<LayerEvent>)
+    fn notify_loaded(&mut self, ctx: &PluginContext<LayerEvent>)
     {
-        let me = reg.get::<Sample>().unwrap();
+        let me = ctx.get::<Sample>().unwrap();
         let me_cloned = me.clone();
-        me.read().unwrap().init(me_cloned);
+
+        if let Err(e) = me.read().unwrap().init(ctx, me_cloned)
+        {
+            log::error!("Failed to initialize the sample: {e}");
+        }
     }
 }
 
 
 pub struct Sample
 {
-    platform_events: Layer<EventReceiver<PlatformEvent>>,
     core: Layer<Core>,
-    tasks: Layer<Tasks>,
-    gobj_manager: Layer<GobjectManager>,
 }
 
 
 impl Sample
 {
-    fn init(&self, me: Layer<Sample>)
+    // Everything registered through the context is cleaned up when the plugin unloads.
+    fn init(&self, ctx: &PluginContext<LayerEvent>, me: Layer<Sample>) -> ThResult<()>
     {
         // subscribe to platform events, in order to handle window close events...
-        self.platform_events.write().unwrap().subscribe(me);
+        ctx.subscribe::<PlatformEvent>(me)?;
 
         // Schedule a task that prints the fps every second
-        self.tasks.write().unwrap().repeating(
-            Duration::from_secs(1),
-            move |FrameInfo { delta }| {
-                println!("FPS: {:.2}", 1.0 / delta.as_secs_f32());
-                Ok(())
-            },
-        );
+        ctx.repeating(Duration::from_secs(1), move |FrameInfo { delta }| {
+            println!("FPS: {:.2}", 1.0 / delta.as_secs_f32());
+            Ok(())
+        })?;
+
+        // Animate the main window's clear color, unless running headless
+        if let Some(renderer) = ctx.get::<Renderer>()
+        {
+            ctx.hook(ClearColorCycle {
+                renderer,
+                green: 0.0,
+            })?;
+        }
 
         // Test out two dummy game objects
-        let mut gobj_manager = self.gobj_manager.write().unwrap();
-        gobj_manager.add_gobj(GobjA);
-        gobj_manager.add_gobj(GobjB);
+        ctx.add_gobj(GobjA)?;
+        ctx.add_gobj(GobjB)?;
 
         // Test out some math
         let a = Mat2::from([Vec2::new(1.0, 2.0), Vec2::new(3.0, 4.0)]);
         let b = Mat2::from([Vec2::new(5.0, 6.0), Vec2::new(7.0, 8.0)]);
         let c = a * b;
         println!("{c}");
+
+        Ok(())
     }
 }
 
@@ -105,9 +102,31 @@ impl EventSubscriber<PlatformEvent> for Sample
 {
     fn receive_event(&mut self, event: &PlatformEvent)
     {
-        if let PlatformEvent::WindowClose = event
+        if let PlatformEvent::WindowClose(WindowId::MAIN) = event
         {
             self.core.read().unwrap().terminate();
         }
     }
 }
+
+
+struct ClearColorCycle
+{
+    renderer: Layer<Renderer>,
+    green: f32,
+}
+
+
+impl CoreHook for ClearColorCycle
+{
+    fn tick(&mut self, _frame_info: &FrameInfo)
+    {
+        self.green = (self.green + 0.001) % 1.0;
+
+        let color = Vec4::new(0.47, self.green, 0.16, 1.0);
+        self.renderer
+            .write()
+            .unwrap()
+            .set_clear_color(WindowId::MAIN, color);
+    }
+}
@@ -74,6 +74,11 @@ impl ShaderBundle
         self.shaders.get(name)
     }
 
+    pub fn iter(&self) -> impl Iterator<Item = &ShaderObj>
+    {
+        self.shaders.values()
+    }
+
     pub fn save(&self, path: impl AsRef<Path>) -> Result<()>
     {
         let mut file = File::create(path)?;
@@ -0,0 +1,99 @@
+use crate::logger;
+use clap::Parser;
+use log::LevelFilter;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+
+/// Runs a thorn project: discovers, configures and loads all plugins and starts the engine.
+#[derive(Debug, Parser)]
+#[command(version, about)]
+pub struct Args
+{
+    /// Run without a window and renderer, on a platform that only simulates windows
+    #[arg(long)]
+    pub headless: bool,
+
+    /// Platform events to play back in headless mode
+    #[arg(long, value_name = "FILE", requires = "headless")]
+    pub script: Option<PathBuf>,
+
+    /// Window size, like 1280x720
+    #[arg(long, value_name = "WxH", value_parser = parse_size)]
+    pub window: Option<(u32, u32)>,
+
+    /// Open the window in borderless fullscreen
+    #[arg(long)]
+    pub fullscreen: bool,
+
+    /// Maximum frames per second
+    #[arg(long, value_name = "FPS", value_parser = clap::value_parser!(u32).range(1..))]
+    pub fps_cap: Option<u32>,
+
+    /// trace, debug, info, warn, error or off. Takes precedence over THORN_LOG
+    #[arg(long, value_name = "LEVEL", value_parser = parse_level)]
+    pub log_level: Option<LevelFilter>,
+
+    /// Directory to load plugin libraries from. Takes precedence over THORN_PLUGIN_DIR
+    #[arg(long, value_name = "DIR")]
+    pub plugin_dir: Option<PathBuf>,
+
+    /// Project manifest to use. Takes precedence over THORN_MANIFEST
+    #[arg(long, value_name = "FILE")]
+    pub config: Option<PathBuf>,
+
+    /// Exit after N frames
+    #[arg(long, value_name = "N")]
+    pub frames: Option<u64>,
+
+    /// List all discovered plugins and exit
+    #[arg(long)]
+    pub list_plugins: bool,
+
+    /// Print the order the plugins would be loaded in and exit
+    #[arg(long)]
+    pub print_load_order: bool,
+}
+
+
+/// Exit codes of the runner. Invalid arguments exit with 2, as reported by clap.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Exit
+{
+    Success = 0,
+    Failure = 1,
+    Config = 3,
+    PluginLoad = 4,
+    Crash = 5,
+}
+
+
+impl Exit
+{
+    pub fn exit(self) -> !
+    {
+        ExitCode::from(self as u8).exit_process()
+    }
+}
+
+
+fn parse_size(s: &str) -> Result<(u32, u32), String>
+{
+    let (w, h) = s
+        .split_once(['x', 'X'])
+        .ok_or(format!("expected WIDTHxHEIGHT, got {s:?}"))?;
+
+    let parse = |v: &str| {
+        v.trim()
+            .parse::<u32>()
+            .map_err(|e| format!("invalid size {v:?}: {e}"))
+    };
+
+    Ok((parse(w)?, parse(h)?))
+}
+
+
+fn parse_level(s: &str) -> Result<LevelFilter, String>
+{
+    logger::parse_level(s).ok_or(format!("unknown log level {s:?}"))
+}
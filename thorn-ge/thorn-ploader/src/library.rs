@@ -0,0 +1,211 @@
+use libloading::Library;
+use semver::{Version, VersionReq};
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, SystemTime};
+use thorn::plugin::dynamic::{
+    DynPlugin,
+    LoggerHandoff,
+    PLUGIN_DECLARATION_SYMBOL,
+    PluginDeclaration,
+    THORN_ABI_VERSION,
+    THORN_RUSTC_VERSION,
+    THORN_VERSION,
+};
+use thorn::prelude::*;
+
+
+/// Lists all shared libraries in `dir`, sorted by path so the discovery order is stable.
+pub fn find_libraries(dir: impl AsRef<Path>) -> ThResult<Vec<PathBuf>>
+{
+    let entries = std::fs::read_dir(dir.as_ref()).map_err(|e| {
+        ThError::Error(format!(
+            "Failed to read plugin directory {:?}: {e}",
+            dir.as_ref()
+        ))
+    })?;
+
+    let mut libs = entries
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| {
+            p.is_file() && p.extension() == Some(OsStr::new(std::env::consts::DLL_EXTENSION))
+        })
+        .collect::<Vec<_>>();
+
+    libs.sort();
+    Ok(libs)
+}
+
+
+/// A loaded plugin library. The library is loaded from a copy, so the original
+/// can be overwritten by a rebuild while the engine runs.
+pub struct PluginLibrary
+{
+    lib: Option<Library>,
+    source: PathBuf,
+    copy: PathBuf,
+    modified: Option<SystemTime>,
+    leaked: bool,
+}
+
+
+impl PluginLibrary
+{
+    pub fn source(&self) -> &Path
+    {
+        &self.source
+    }
+
+    /// Whether the source was rebuilt since it was loaded. A source that was written
+    /// to very recently is not reported yet, as the build might still be writing it.
+    pub fn changed(&self) -> bool
+    {
+        const SETTLE_TIME: Duration = Duration::from_millis(500);
+
+        let Ok(modified) = std::fs::metadata(&self.source).and_then(|m| m.modified())
+        else
+        {
+            return false;
+        };
+
+        let settled = modified.elapsed().map(|e| e > SETTLE_TIME).unwrap_or(false);
+
+        settled && Some(modified) != self.modified
+    }
+
+    /// Forgets about the current build of the source, so it is not reported as changed anymore.
+    pub fn refresh(&mut self)
+    {
+        self.modified = std::fs::metadata(&self.source)
+            .and_then(|m| m.modified())
+            .ok();
+    }
+
+    /// Keeps the library's code mapped for the rest of the process, for when
+    /// something may still reference it after its plugin was unloaded.
+    pub fn leak(&mut self)
+    {
+        self.leaked = true;
+    }
+}
+
+
+impl Drop for PluginLibrary
+{
+    fn drop(&mut self)
+    {
+        let lib = self.lib.take();
+
+        if self.leaked
+        {
+            std::mem::forget(lib);
+        }
+        else
+        {
+            drop(lib);
+        }
+
+        let _ = std::fs::remove_file(&self.copy);
+    }
+}
+
+
+/// Opens a plugin library and creates its plugin, after checking that it was built
+/// against a compatible engine with the same compiler.
+pub fn open(path: &Path) -> ThResult<(DynPlugin, PluginLibrary)>
+{
+    let fail = |reason: String| ThError::PluginLoadFailed(path.display().to_string(), reason);
+
+    let modified = std::fs::metadata(path).and_then(|m| m.modified()).ok();
+    let copy = copy_library(path).map_err(|e| fail(format!("Failed to copy library: {e}")))?;
+
+    let lib = PluginLibrary {
+        lib: Some(unsafe { Library::new(&copy) }.map_err(|e| fail(e.to_string()))?),
+        source: path.to_path_buf(),
+        copy,
+        modified,
+        leaked: false,
+    };
+
+    let plugin = unsafe { create_plugin(lib.lib.as_ref().unwrap()) }.map_err(fail)?;
+    Ok((plugin, lib))
+}
+
+
+/// Copies a library to a unique path, so that reloading it does not hand back the cached old version.
+fn copy_library(path: &Path) -> std::io::Result<PathBuf>
+{
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+    let dir = std::env::temp_dir().join(format!("thorn-plugins-{}", std::process::id()));
+    std::fs::create_dir_all(&dir)?;
+
+    let copy = dir.join(format!(
+        "{}-{}.{}",
+        path.file_stem().unwrap_or_default().to_string_lossy(),
+        COUNTER.fetch_add(1, Ordering::Relaxed),
+        std::env::consts::DLL_EXTENSION
+    ));
+
+    std::fs::copy(path, &copy)?;
+    Ok(copy)
+}
+
+
+unsafe fn create_plugin(lib: &Library) -> Result<DynPlugin, String>
+{
+    let decl = unsafe {
+        let symbol = lib
+            .get::<*const PluginDeclaration>(PLUGIN_DECLARATION_SYMBOL)
+            .map_err(|e| format!("Not a thorn plugin: {e}"))?;
+
+        &**symbol
+    };
+
+    // Nothing but the ABI version may be read before it is known to match.
+    if decl.abi_version != THORN_ABI_VERSION
+    {
+        return Err(format!(
+            "Plugin ABI version {} does not match the engine's ({THORN_ABI_VERSION})",
+            decl.abi_version
+        ));
+    }
+
+    let rustc = unsafe { decl.rustc_version() };
+    if rustc != THORN_RUSTC_VERSION
+    {
+        return Err(format!(
+            "Plugin was built with {rustc:?}, but the engine with {THORN_RUSTC_VERSION:?}"
+        ));
+    }
+
+    let engine = unsafe { decl.engine_version() };
+    if !is_compatible(engine.to_str().unwrap_or_default())
+    {
+        return Err(format!(
+            "Plugin was built against engine version {engine:?}, which is not compatible with {THORN_VERSION:?}"
+        ));
+    }
+
+    unsafe {
+        (decl.set_logger)(&LoggerHandoff::current());
+        Ok(*Box::from_raw((decl.create)()))
+    }
+}
+
+
+/// A plugin built against `plugin_version` works with this engine if the engine
+/// satisfies a caret requirement on it, the same way cargo resolves versions.
+fn is_compatible(plugin_version: &str) -> bool
+{
+    let engine = Version::parse(THORN_VERSION.to_str().unwrap_or_default());
+    let required = VersionReq::parse(&format!("^{plugin_version}"));
+
+    match (engine, required)
+    {
+        (Ok(engine), Ok(required)) => required.matches(&engine),
+        _ => false,
+    }
+}
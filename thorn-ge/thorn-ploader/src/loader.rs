@@ -1,42 +1,92 @@
-use std::any::TypeId;
+use crate::library::{self, PluginLibrary};
+use crate::resolve;
 use std::collections::HashMap;
+use std::path::Path;
+use thorn::plugin::dynamic::DynPlugin;
 use thorn::prelude::*;
 
 
 pub struct Plug
 {
     pub info: PluginInfo,
-    pub plugin: Box<dyn Plugin<LayerEvent> + Send + Sync>,
+    pub plugin: DynPlugin,
+
+    // Registrations the plugin made through its context.
+    scope: PluginScope,
+
+    // Declared after the plugin, so the plugin's code is unloaded last.
+    library: Option<PluginLibrary>,
 }
 
 
 impl Plug
 {
-    fn new(plugin: impl Plugin<LayerEvent> + Send + Sync + 'static) -> Self
+    fn new(plugin: DynPlugin, library: Option<PluginLibrary>) -> Self
     {
         Self {
             info: plugin.info(),
-            plugin: Box::new(plugin),
+            plugin,
+            scope: PluginScope::new(),
+            library,
         }
     }
 
-    pub fn deps(&self) -> &[TypeId]
+    pub fn deps(&self) -> &[Dependency]
     {
         &self.info.deps
     }
 
-    pub fn id(&self) -> TypeId
+    /// The shared library the plugin was loaded from, if any.
+    pub fn source(&self) -> Option<&Path>
+    {
+        self.library.as_ref().map(PluginLibrary::source)
+    }
+
+    fn context<'a>(&self, reg: &'a LayerReg<LayerEvent>) -> PluginContext<'a, LayerEvent>
     {
-        self.info.identity
+        PluginContext::new(reg, self.scope.clone())
+    }
+
+    /// Notifies the plugin and revokes everything it registered through its context.
+    fn unload(&mut self, reg: &LayerReg<LayerEvent>)
+    {
+        self.plugin.notify_unloaded(&self.context(reg));
+        self.revoke_all();
+    }
+
+    // Keeps the library loaded if the main loop might still run the plugin's code.
+    fn revoke_all(&mut self)
+    {
+        if !self.scope.revoke_all()
+            && let Some(lib) = &mut self.library
+        {
+            log::warn!(
+                "The main loop did not confirm that plugin {} was revoked. Keeping its library loaded",
+                self.info.name
+            );
+            lib.leak();
+        }
+    }
+
+    pub fn id(&self) -> LayerKey
+    {
+        self.info.identity.clone()
     }
 }
 
 
 pub struct PluginLoader
 {
-    plugins: HashMap<TypeId, Plug>,
+    // Layers may be backed by code of a plugin library, so they have to be dropped before the plugins.
     registry: LayerReg<LayerEvent>,
+    plugins: HashMap<LayerKey, Plug>,
     loaded: Vec<Plug>,
+
+    // State saved by plugins that are being reloaded.
+    states: HashMap<LayerKey, Vec<u8>>,
+
+    // Manifest sections, keyed by plugin label.
+    configs: HashMap<String, PluginConfig>,
 }
 
 
@@ -46,12 +96,19 @@ impl PluginLoader
     pub fn new() -> Self
     {
         Self {
-            plugins: HashMap::new(),
             registry: LayerReg::new(),
+            plugins: HashMap::new(),
             loaded: vec![],
+            states: HashMap::new(),
+            configs: HashMap::new(),
         }
     }
 
+    pub fn registry(&self) -> &LayerReg<LayerEvent>
+    {
+        &self.registry
+    }
+
     pub fn registry_mut(&mut self) -> &mut LayerReg<LayerEvent>
     {
         &mut self.registry
@@ -62,8 +119,50 @@ impl PluginLoader
         plugin: impl Plugin<LayerEvent> + Send + Sync + 'static,
     ) -> Option<Plug>
     {
-        let plug = Plug::new(plugin);
+        self.discover(Plug::new(Box::new(plugin), None))
+    }
+
+    /// Discovers the plugin exported by a shared library, see [`thorn::export_plugin`].
+    pub fn discover_library(&mut self, path: impl AsRef<Path>) -> ThResult<Option<Plug>>
+    {
+        let (plugin, lib) = library::open(path.as_ref())?;
+        let plug = Plug::new(plugin, Some(lib));
+
+        log::info!(
+            "Discovered Plugin {} version {} in {:?}",
+            plug.info.name,
+            plug.info.version,
+            path.as_ref()
+        );
+
+        Ok(self.discover(plug))
+    }
+
+    /// Discovers all plugin libraries in a directory. Libraries that fail to load are skipped.
+    pub fn discover_dir(&mut self, dir: impl AsRef<Path>) -> ThResult<()>
+    {
+        for path in library::find_libraries(dir)?
+        {
+            match self.discover_library(&path)
+            {
+                Ok(Some(plug)) =>
+                {
+                    log::warn!(
+                        "Plugin {} in {path:?} was already discovered. Skipping",
+                        plug.info.name
+                    )
+                }
+
+                Ok(None) => (),
+                Err(e) => log::error!("{e}"),
+            }
+        }
+
+        Ok(())
+    }
 
+    fn discover(&mut self, plug: Plug) -> Option<Plug>
+    {
         if self.plugins.contains_key(&plug.id())
         {
             return Some(plug);
@@ -74,72 +173,272 @@ impl PluginLoader
         None
     }
 
-    pub fn dep_sort(&self) -> ThResult<Vec<TypeId>>
+    pub fn set_configs(&mut self, configs: HashMap<String, PluginConfig>)
+    {
+        self.configs = configs;
+    }
+
+    /// All discovered plugins, loaded or not.
+    pub fn plugins(&self) -> impl Iterator<Item = &Plug>
+    {
+        self.plugins.values().chain(&self.loaded)
+    }
+
+    pub fn labels(&self) -> Vec<String>
+    {
+        self.plugins().map(|p| p.info.label()).collect()
+    }
+
+    /// Drops all discovered plugins that are not loaded yet and not listed in `enabled`.
+    pub fn retain_enabled(&mut self, enabled: &[String])
+    {
+        self.plugins.retain(|_, p| {
+            let keep = enabled.contains(&p.info.label());
+            if !keep
+            {
+                log::info!("Plugin {} is not enabled in the manifest", p.info.label());
+            }
+
+            keep
+        });
+    }
+
+    /// Looks up a discovered plugin, whether it is loaded or not.
+    pub fn plugin_info(&self, id: &LayerKey) -> Option<&PluginInfo>
+    {
+        self.plugins
+            .get(id)
+            .or_else(|| self.loaded.iter().find(|p| p.id() == *id))
+            .map(|p| &p.info)
+    }
+
+    pub fn dep_sort(&self) -> ThResult<Vec<LayerKey>>
     {
-        let plugs = self.plugins.keys().cloned().collect::<Vec<_>>();
-        let mut sorted = vec![];
+        resolve::resolve(&self.plugins, &self.loaded).map_err(ThError::PluginResolve)
+    }
 
-        loop
+    /// Loads all discovered plugins in dependency order. A plugin that fails to load stays
+    /// discovered, along with the plugins depending on it, and the others are loaded anyway.
+    /// Returns the first failure, later ones are logged.
+    pub fn load_all(&mut self) -> ThResult<()>
+    {
+        let mut result = Ok(());
+        let mut failed = vec![];
+
+        for id in self.dep_sort()?
         {
-            let sorted_len = sorted.len();
+            let Some(plug) = self.plugins.remove(&id)
+            else
+            {
+                continue;
+            };
 
-            'outer: for plug in &plugs
+            let provided = plug.info.provided().cloned().collect::<Vec<_>>();
+
+            if let Some(dep) = plug
+                .deps()
+                .iter()
+                .find(|d| !d.optional && failed.contains(&d.key))
             {
-                if sorted.contains(plug)
-                {
-                    continue;
-                }
+                log::error!(
+                    "Not loading Plugin {}, its dependency {} failed to load",
+                    plug.info.name,
+                    dep.type_name
+                );
+
+                self.plugins.insert(id, plug);
+                failed.extend(provided);
+                continue;
+            }
 
-                for dep in self.deps_of(*plug)
+            if let Err(e) = self.load_plugin(plug)
+            {
+                failed.extend(provided);
+
+                match result
                 {
-                    if !sorted.contains(dep)
-                    {
-                        continue 'outer;
-                    }
+                    Ok(_) => result = Err(e),
+                    Err(_) => log::error!("{e}"),
                 }
+            }
+        }
 
-                sorted.push(*plug);
+        result
+    }
+
+    pub fn unload_all(&mut self)
+    {
+        while let Some(plug) = self.loaded.pop()
+        {
+            self.unload_plugin(plug);
+        }
+    }
+
+    /// Unloads a plugin along with every plugin depending on it, in reverse load order.
+    /// Their layers are removed from the registry and they are kept as discovered,
+    /// so that `load_all` loads them again. Returns the unloaded plugins.
+    pub fn unload(&mut self, id: &LayerKey) -> ThResult<Vec<LayerKey>>
+    {
+        if !self.is_loaded(id)
+        {
+            return Err(ThError::Error(format!("Plugin {id:?} is not loaded")));
+        }
+
+        // Plugins are loaded after their deps, so one pass in load order finds all dependents.
+        let mut affected = vec![];
+        let mut provided = vec![];
+        for plug in &self.loaded
+        {
+            if plug.id() == *id || plug.deps().iter().any(|d| provided.contains(&d.key))
+            {
+                affected.push(plug.id());
+                provided.extend(plug.info.provided().cloned());
+            }
+        }
+
+        let mut unloaded = vec![];
+        for i in (0..self.loaded.len()).rev()
+        {
+            if !affected.contains(&self.loaded[i].id())
+            {
+                continue;
+            }
+
+            let mut plug = self.loaded.remove(i);
+
+            if let Some(state) = plug.plugin.save_state(&plug.context(&self.registry))
+            {
+                self.states.insert(plug.id(), state);
             }
 
-            if sorted_len == sorted.len()
+            plug.unload(&self.registry);
+
+            // The registry itself holds two references to every layer.
+            let layer = self.registry.remove_any(&plug.id());
+            if let (Some(layer), Some(lib)) = (layer, &mut plug.library)
+                && layer.strong_count() > 2
             {
-                break;
+                log::warn!(
+                    "Layer of plugin {} is still referenced after unloading. Keeping its library loaded",
+                    plug.info.name
+                );
+                lib.leak();
             }
+
+            log::info!(
+                "Unloaded Plugin {} version {}",
+                plug.info.name,
+                plug.info.version
+            );
+
+            unloaded.push(plug.id());
+            self.plugins.insert(plug.id(), plug);
         }
 
-        if plugs.len() != sorted.len()
+        Ok(unloaded)
+    }
+
+    /// Unloads a plugin and its dependents, reopens the libraries of those
+    /// loaded from a shared library, and loads them all again.
+    pub fn reload(&mut self, id: &LayerKey) -> ThResult<()>
+    {
+        for id in self.unload(id)?
         {
-            return Err(ThError::PluginLoadOrder);
+            self.reopen_library(&id);
         }
 
-        Ok(sorted)
+        self.load_all()
     }
 
-    pub fn load_all(&mut self) -> ThResult<()>
+    /// Reloads every plugin whose shared library was rebuilt since it was loaded. Plugins that
+    /// failed to load are loaded again once their library was rebuilt. Returns the first failure.
+    pub fn reload_changed(&mut self) -> ThResult<()>
     {
-        for plug in self.dep_sort()?
+        let changed = |p: &Plug| p.library.as_ref().is_some_and(PluginLibrary::changed);
+
+        let ids = self
+            .plugins()
+            .filter(|p| changed(p))
+            .map(Plug::id)
+            .collect::<Vec<_>>();
+
+        let mut result = Ok(());
+
+        for id in ids
         {
-            let plug = self.plugins.remove(&plug);
-            if let Some(plug) = plug
+            // Might have been reloaded already, as a dependent of another changed plugin.
+            if !self.plugins().any(|p| p.id() == id && changed(p))
             {
-                self.load_plugin(plug)?;
+                continue;
+            }
+
+            let reloaded = match self.is_loaded(&id)
+            {
+                true => self.reload(&id),
+                false =>
+                {
+                    self.reopen_library(&id);
+                    self.load_all()
+                }
+            };
+
+            if let Err(e) = reloaded
+            {
+                match result
+                {
+                    Ok(_) => result = Err(e),
+                    Err(_) => log::error!("{e}"),
+                }
             }
         }
 
-        Ok(())
+        result
     }
 
-    pub fn unload_all(&mut self)
+    fn reopen_library(&mut self, id: &LayerKey)
     {
-        while let Some(plug) = self.loaded.pop()
+        let Some(old) = self.plugins.get_mut(id)
+        else
         {
-            self.unload_plugin(plug);
+            return;
+        };
+
+        let Some(lib) = &mut old.library
+        else
+        {
+            return;
+        };
+
+        match library::open(lib.source())
+        {
+            Ok((plugin, new_lib)) if plugin.info().identity == *id =>
+            {
+                // Drops the old plugin before its library. Unloading waited for the main
+                // loop to drop the plugin's hooks and tasks, or leaked the library.
+                self.plugins
+                    .insert(id.clone(), Plug::new(plugin, Some(new_lib)));
+            }
+
+            Ok(_) =>
+            {
+                log::error!(
+                    "The rebuilt library {:?} contains a different plugin. Keeping the old build",
+                    lib.source()
+                );
+                lib.refresh();
+            }
+
+            Err(e) =>
+            {
+                log::error!("{e}. Keeping the old build");
+                lib.refresh();
+            }
         }
     }
 
-    fn deps_of(&self, id: TypeId) -> &[TypeId]
+    fn is_loaded(&self, id: &LayerKey) -> bool
     {
-        self.plugins.get(&id).map(|e| e.deps()).unwrap_or(&[])
+        self.loaded.iter().any(|p| p.id() == *id)
     }
 
     fn load_plugin(&mut self, mut plugin: Plug) -> ThResult<()>
@@ -150,23 +449,66 @@ impl PluginLoader
             plugin.info.version
         );
 
-        match plugin.plugin.load(&self.registry)
+        let config = self
+            .configs
+            .get(&plugin.info.label())
+            .cloned()
+            .unwrap_or_default();
+
+        if let Err(e) = plugin.plugin.configure(&config)
         {
-            Ok(layer) =>
+            let name = plugin.info.name.clone();
+            self.plugins.insert(plugin.id(), plugin);
+            return Err(ThError::PluginLoadFailed(
+                name,
+                format!("Invalid config: {e}"),
+            ));
+        }
+
+        let ctx = plugin.context(&self.registry);
+
+        match plugin.plugin.load(&ctx)
+        {
+            Ok(mut layer) =>
             {
+                // Plugins for named instances get their layer registered under that name.
+                if let (None, Some(name)) = (layer.name(), plugin.info.instance_name())
+                {
+                    layer = layer.with_name(name);
+                }
+
+                layer = layer.with_owner(&plugin.info);
+
                 if self.registry.insert_any(layer).is_some()
                 {
+                    plugin.revoke_all();
+
+                    let name = plugin.info.name.clone();
+                    self.plugins.insert(plugin.id(), plugin);
                     return Err(ThError::PluginLoadFailed(
-                        plugin.info.name,
+                        name,
                         "The plugin's Layer is already Loaded".into(),
                     ));
                 }
 
-                plugin.plugin.notify_loaded(&self.registry);
+                if let Some(state) = self.states.remove(&plugin.id())
+                {
+                    let ctx = plugin.context(&self.registry);
+                    plugin.plugin.restore_state(&ctx, state);
+                }
+
+                plugin.plugin.notify_loaded(&plugin.context(&self.registry));
                 self.loaded.push(plugin);
             }
 
-            Err(e) => return Err(ThError::PluginLoadFailed(plugin.info.name, e.to_string())),
+            Err(e) =>
+            {
+                plugin.revoke_all();
+
+                let name = plugin.info.name.clone();
+                self.plugins.insert(plugin.id(), plugin);
+                return Err(ThError::PluginLoadFailed(name, e.to_string()));
+            }
         }
 
         Ok(())
@@ -180,7 +522,7 @@ impl PluginLoader
             plugin.info.version
         );
 
-        plugin.plugin.notify_unloaded(&self.registry);
+        plugin.unload(&self.registry);
         self.plugins.insert(plugin.id(), plugin);
     }
 }
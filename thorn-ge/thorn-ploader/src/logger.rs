@@ -3,23 +3,24 @@ use std::env::var;
 use thorn::prelude::*;
 
 
-fn get_level(default: LevelFilter) -> LevelFilter
+pub fn parse_level(level: &str) -> Option<LevelFilter>
 {
-    if let Ok(level) = var("THORN_LOG")
+    match level.to_lowercase().trim()
     {
-        return match level.to_lowercase().trim()
-        {
-            "trace" => LevelFilter::Trace,
-            "debug" => LevelFilter::Debug,
-            "info" => LevelFilter::Info,
-            "warning" | "warn" => LevelFilter::Warn,
-            "error" | "err" => LevelFilter::Error,
-            "none" | "off" | "false" => LevelFilter::Off,
-            _ => default,
-        };
+        "trace" => Some(LevelFilter::Trace),
+        "debug" => Some(LevelFilter::Debug),
+        "info" => Some(LevelFilter::Info),
+        "warning" | "warn" => Some(LevelFilter::Warn),
+        "error" | "err" => Some(LevelFilter::Error),
+        "none" | "off" | "false" => Some(LevelFilter::Off),
+        _ => None,
     }
+}
+
 
-    default
+pub fn env_level() -> Option<LevelFilter>
+{
+    var("THORN_LOG").ok().and_then(|level| parse_level(&level))
 }
 
 
@@ -29,7 +30,7 @@ fn get_log_file(default: &str) -> String
 }
 
 
-pub fn init() -> ThResult<()>
+pub fn init(level: LevelFilter) -> ThResult<()>
 {
     let mut dispatch_chain = fern::Dispatch::new()
         .format(|out, msg, record| {
@@ -41,7 +42,7 @@ pub fn init() -> ThResult<()>
                 msg,
             ))
         })
-        .level(get_level(LevelFilter::Debug))
+        .level(level)
         .chain(
             fern::log_file(get_log_file(&format!("{}.log", env!("CARGO_CRATE_NAME"))))
                 .map_err(|e| ThError::Error(e.to_string()))?,
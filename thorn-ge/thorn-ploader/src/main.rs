@@ -1,71 +1,194 @@
 #![feature(exitcode_exit_method)]
 
 
+mod cli;
+mod library;
 mod loader;
 mod logger;
+mod manifest;
+mod resolve;
 
 
+use clap::Parser;
+use cli::{Args, Exit};
 use loader::PluginLoader;
+use log::LevelFilter;
+use manifest::{DEFAULT_MANIFEST, Manifest};
 use sample_plugin::SamplePlugin;
+use std::env::var;
+use std::path::{Path, PathBuf};
 use std::sync::mpsc::{Receiver, RecvTimeoutError};
 use std::thread;
-use std::{process::ExitCode, time::Duration};
+use std::time::{Duration, Instant};
 use thorn::engine::platform::ThornWindow;
 use thorn::engine::renderer::RendererPlugin;
 use thorn::engine::{
     core::{Core, CoreMsg, CorePlugin},
     event::{EngineEvent, EventEmitterPlugin, EventReceiverPlugin},
     gobject_manager::GobjectManagerPlugin,
-    platform::{PlatformEvent, PlatformPlugin},
+    input::{ActionMapPlugin, InputPlugin},
+    platform::{Fullscreen, GamepadPlugin, HeadlessPlatform, PlatformEvent, PlatformPlugin},
     tasks::TasksPlugin,
 };
-use thorn::prelude::{Backend, WindowParams};
+use thorn::prelude::Dependency;
 
 
 fn main()
 {
-    if let Err(e) = logger::init()
+    let args = Args::parse();
+
+    // A missing manifest is fine, unless it was asked for explicitly. Everything has a default.
+    let manifest_path = args
+        .config
+        .clone()
+        .or(var("THORN_MANIFEST").ok().map(PathBuf::from));
+
+    let (mut manifest, manifest_error) = match &manifest_path
+    {
+        Some(path) => split_err(Manifest::load(path)),
+        None if Path::new(DEFAULT_MANIFEST).exists() => split_err(Manifest::load(DEFAULT_MANIFEST)),
+        None => (Manifest::default(), None),
+    };
+
+    let level = args
+        .log_level
+        .or_else(logger::env_level)
+        .or(manifest.log_level.as_deref().and_then(logger::parse_level))
+        .unwrap_or(LevelFilter::Debug);
+
+    if let Err(e) = logger::init(level)
     {
         eprintln!("ERROR: Failed to initialize logger: {e}");
     }
 
+    if let Some(e) = manifest_error
+    {
+        log::error!("{e}");
+        Exit::Config.exit();
+    }
+
+    apply_args(&args, &mut manifest);
+
     let mut loader = PluginLoader::new();
     let (sender, core) = std::sync::mpsc::channel();
 
-    let (winit, proxy) = match ThornWindow::prepare()
+    let event_loop = if args.headless
     {
-        Ok(d) => d,
-        Err(e) =>
+        None
+    }
+    else
+    {
+        match ThornWindow::prepare()
         {
-            log::error!("{e}");
-            ExitCode::FAILURE.exit_process();
+            Ok(d) => Some(d),
+            Err(e) =>
+            {
+                log::error!("{e}");
+                Exit::Failure.exit();
+            }
         }
     };
 
     // Engine Plugins
-    loader.discover_plugin(CorePlugin(sender));
+    loader.discover_plugin(CorePlugin::new(sender));
     loader.discover_plugin(EventEmitterPlugin::<EngineEvent>::default());
     loader.discover_plugin(EventReceiverPlugin::<EngineEvent>::default());
     loader.discover_plugin(EventEmitterPlugin::<PlatformEvent>::default());
     loader.discover_plugin(EventReceiverPlugin::<PlatformEvent>::default());
-    loader.discover_plugin(PlatformPlugin(proxy));
     loader.discover_plugin(TasksPlugin);
     loader.discover_plugin(GobjectManagerPlugin);
-    loader.discover_plugin(RendererPlugin(Backend::Vulkan));
+    loader.discover_plugin(InputPlugin);
+    loader.discover_plugin(ActionMapPlugin::new());
+
+    loader.discover_plugin(GamepadPlugin::new());
+
+    match &event_loop
+    {
+        Some((_, proxy)) =>
+        {
+            loader.discover_plugin(PlatformPlugin::new(proxy.clone()));
+            loader.discover_plugin(RendererPlugin::new(manifest.renderer.backend));
+        }
+
+        None =>
+        {
+            let headless = HeadlessPlatform::new(manifest.window.clone());
+
+            if let Some(script) = &args.script
+                && let Err(e) = headless.load_script(script)
+            {
+                log::error!("{e}");
+                Exit::Config.exit();
+            }
+
+            loader.discover_plugin(PlatformPlugin::new(headless));
+        }
+    }
 
     // Static library plugins
     loader.discover_plugin(SamplePlugin);
 
+    // Dynamic library plugins
+    let plugin_dir = args
+        .plugin_dir
+        .clone()
+        .or(var("THORN_PLUGIN_DIR").ok().map(PathBuf::from))
+        .or(manifest.plugin_dir.clone())
+        .unwrap_or("plugins".into());
+
+    if let Err(e) = loader.discover_dir(&plugin_dir)
+    {
+        log::warn!("{e}");
+    }
+
+    if args.list_plugins
+    {
+        list_plugins(&loader, manifest.plugins.as_deref());
+    }
+
+    // Apply the manifest's plugin selection and config sections
+    if let Some(enabled) = &manifest.plugins
+    {
+        loader.retain_enabled(enabled);
+    }
+
+    let labels = loader.labels();
+    for label in manifest.plugin.keys().filter(|l| !labels.contains(l))
+    {
+        log::warn!("The manifest configures plugin {label}, which was not discovered");
+    }
+
+    loader.set_configs(manifest.plugin_configs());
+
+    if args.print_load_order
+    {
+        print_load_order(&loader);
+    }
+
     // Load all plugins
-    if loader.load_all().is_err()
+    if let Err(e) = loader.load_all()
     {
-        log::error!("Failed to resolve plugin dependencies. exting");
+        log::error!("{e}");
+        log::error!("Failed to load plugins. exting");
         loader.unload_all();
-        ExitCode::FAILURE.exit_process();
+        Exit::PluginLoad.exit();
     }
 
+    // Write the layer graph for debugging if asked to
+    if let Ok(path) = var("THORN_LAYER_GRAPH")
+    {
+        std::fs::write(&path, loader.registry().to_dot())
+            .unwrap_or_else(|e| log::error!("Failed to write layer graph to {path:?}: {e}"));
+    }
+
+    let Some((winit, _)) = event_loop
+    else
+    {
+        manage_plugins(loader, core).exit();
+    };
+
     let window = ThornWindow::new(
-        WindowParams::default(),
+        manifest.window,
         loader.registry_mut().get().unwrap(),
         loader.registry_mut().get().unwrap(),
     );
@@ -75,18 +198,125 @@ fn main()
     window.run(winit);
     core_layer.read().unwrap().terminate();
 
-    if let Err(e) = plugin_manager_handle.join()
+    match plugin_manager_handle.join()
     {
-        log::error!("{e:?}");
+        Ok(exit) => exit.exit(),
+        Err(e) =>
+        {
+            log::error!("{e:?}");
+            Exit::Crash.exit();
+        }
     }
 }
 
 
-fn manage_plugins(mut loader: PluginLoader, core: Receiver<CoreMsg>)
+fn split_err<T: Default, E>(result: Result<T, E>) -> (T, Option<E>)
+{
+    match result
+    {
+        Ok(v) => (v, None),
+        Err(e) => (T::default(), Some(e)),
+    }
+}
+
+
+/// Command line arguments take precedence over the manifest.
+fn apply_args(args: &Args, manifest: &mut Manifest)
+{
+    if let Some(size) = args.window
+    {
+        manifest.window.size = size;
+    }
+
+    if args.fullscreen
+    {
+        manifest.window.fullscreen = Fullscreen::Borderless;
+    }
+
+    let core = manifest.plugin.entry("Core".into()).or_default();
+
+    if let Some(fps_cap) = args.fps_cap
+    {
+        core.insert("fps_cap".into(), toml::Value::Integer(fps_cap.into()));
+    }
+
+    if let Some(frames) = args.frames
+    {
+        let frames = i64::try_from(frames).unwrap_or(i64::MAX);
+        core.insert("frame_limit".into(), toml::Value::Integer(frames));
+    }
+}
+
+
+fn list_plugins(loader: &PluginLoader, enabled: Option<&[String]>) -> !
+{
+    let mut plugins = loader.plugins().collect::<Vec<_>>();
+    plugins.sort_by_key(|p| p.info.label());
+
+    for plug in plugins
+    {
+        let label = plug.info.label();
+        let source = match plug.source()
+        {
+            Some(path) => path.display().to_string(),
+            None => "static".into(),
+        };
+
+        let disabled = enabled.is_some_and(|e| !e.contains(&label));
+
+        println!(
+            "{label} {} ({source}){}",
+            plug.info.version,
+            if disabled { " [disabled]" } else { "" }
+        );
+    }
+
+    Exit::Success.exit();
+}
+
+
+fn print_load_order(loader: &PluginLoader) -> !
+{
+    match loader.dep_sort()
+    {
+        Ok(order) =>
+        {
+            println!("Plugin load order:");
+
+            for (i, info) in order
+                .iter()
+                .filter_map(|id| loader.plugin_info(id))
+                .enumerate()
+            {
+                let deps = info.deps.iter().map(Dependency::label).collect::<Vec<_>>();
+                println!("{:>4}. {} {}", i + 1, info.name, info.version);
+
+                if !deps.is_empty()
+                {
+                    println!("        after {}", deps.join(", "));
+                }
+            }
+
+            Exit::Success.exit();
+        }
+
+        Err(e) =>
+        {
+            eprintln!("{e}");
+            Exit::PluginLoad.exit();
+        }
+    }
+}
+
+
+fn manage_plugins(mut loader: PluginLoader, core: Receiver<CoreMsg>) -> Exit
 {
     const MAX_TIMEOUTS: usize = 20;
+    const RELOAD_CHECK_INTERVAL: Duration = Duration::from_secs(1);
 
     let mut timeouts = 0;
+    let mut last_reload_check = Instant::now();
+    let mut exit = Exit::Success;
 
     loop
     {
@@ -103,6 +333,7 @@ fn manage_plugins(mut loader: PluginLoader, core: Receiver<CoreMsg>)
             Err(RecvTimeoutError::Timeout) =>
             {
                 log::warn!("Core loop connection timed out...");
+                log::debug!("Registered layers:\n{}", loader.registry().dump());
                 timeouts += 1;
 
                 if !loader
@@ -114,6 +345,7 @@ fn manage_plugins(mut loader: PluginLoader, core: Receiver<CoreMsg>)
                     .is_alive()
                 {
                     log::error!("Core loop seems to have crashed. Exiting");
+                    exit = Exit::Crash;
                     break;
                 }
             }
@@ -122,6 +354,7 @@ fn manage_plugins(mut loader: PluginLoader, core: Receiver<CoreMsg>)
             Err(RecvTimeoutError::Disconnected) =>
             {
                 log::error!("Core loop seems to have crashed. Exiting");
+                exit = Exit::Crash;
                 break;
             }
 
@@ -135,11 +368,27 @@ fn manage_plugins(mut loader: PluginLoader, core: Receiver<CoreMsg>)
                 "Core Loop times out {timeouts} times in a row. Assuming crash/deadlock. Shutting down..."
             );
 
+            #[cfg(all(feature = "debug-locks", debug_assertions))]
+            log::error!("Held layer locks:\n{}", thorn::layer::held_locks_report());
+
+            exit = Exit::Crash;
             break;
         }
+
+        // Hot reload plugin libraries that were rebuilt
+        if last_reload_check.elapsed() > RELOAD_CHECK_INTERVAL
+        {
+            last_reload_check = Instant::now();
+
+            if let Err(e) = loader.reload_changed()
+            {
+                log::error!("Failed to reload plugins: {e}");
+            }
+        }
     }
 
     // unload all plugins...
     loader.unload_all();
     log::info!("Exiting. Good Bye.");
+    exit
 }
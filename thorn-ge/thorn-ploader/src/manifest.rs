@@ -0,0 +1,55 @@
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use thorn::prelude::*;
+
+
+pub const DEFAULT_MANIFEST: &str = "thorn.toml";
+
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Manifest
+{
+    pub log_level: Option<String>,
+    pub plugin_dir: Option<PathBuf>,
+
+    // Labels of the plugins to load. All discovered plugins are loaded if missing.
+    pub plugins: Option<Vec<String>>,
+
+    pub window: WindowParams,
+    pub renderer: RendererSection,
+
+    // Per plugin config sections, keyed by the plugin's label.
+    pub plugin: HashMap<String, toml::Table>,
+}
+
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RendererSection
+{
+    pub backend: Backend,
+}
+
+
+impl Manifest
+{
+    pub fn load(path: impl AsRef<Path>) -> ThResult<Self>
+    {
+        let text = std::fs::read_to_string(path.as_ref()).map_err(|e| {
+            ThError::Error(format!("Failed to read manifest {:?}: {e}", path.as_ref()))
+        })?;
+
+        toml::from_str(&text)
+            .map_err(|e| ThError::Error(format!("Invalid manifest {:?}: {e}", path.as_ref())))
+    }
+
+    pub fn plugin_configs(&self) -> HashMap<String, PluginConfig>
+    {
+        self.plugin
+            .iter()
+            .map(|(label, table)| (label.clone(), PluginConfig::new(table.clone())))
+            .collect()
+    }
+}
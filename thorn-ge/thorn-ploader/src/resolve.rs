@@ -0,0 +1,452 @@
+use crate::loader::Plug;
+use std::collections::HashMap;
+use thorn::prelude::*;
+
+
+/// Finds a load order for the pending plugins, which satisfies all of their dependencies
+/// on each other and on the already loaded plugins.
+pub fn resolve(
+    pending: &HashMap<LayerKey, Plug>,
+    loaded: &[Plug],
+) -> Result<Vec<LayerKey>, Vec<ResolveError>>
+{
+    let mut providers = HashMap::<&LayerKey, Vec<&Plug>>::new();
+    for plug in pending.values().chain(loaded)
+    {
+        for key in plug.info.provided()
+        {
+            providers.entry(key).or_default().push(plug);
+        }
+    }
+
+    // Sorted, so the load order does not depend on the hash map's order
+    let mut plugs = pending.values().collect::<Vec<_>>();
+    plugs.sort_by(|a, b| a.info.name.cmp(&b.info.name));
+
+    let mut errors = vec![];
+    let mut edges = HashMap::new();
+    let mut unmet = HashMap::new();
+
+    for plug in &plugs
+    {
+        let mut after = vec![];
+
+        // Everything keeping the plugin from loading, besides other pending plugins
+        let mut missing = vec![];
+
+        for reason in plug.info.invalid()
+        {
+            missing.push(format!("a valid {reason}"));
+            errors.push(ResolveError::Invalid {
+                plugin: plug.info.name.clone(),
+                reason: reason.into(),
+            });
+        }
+
+        for dep in plug.deps()
+        {
+            let found = providers.get(&dep.key).map(Vec::as_slice).unwrap_or(&[]);
+            let matching = found
+                .iter()
+                .filter(|p| dep.req.matches(&p.info.version))
+                .collect::<Vec<_>>();
+
+            match (found.first(), matching.is_empty())
+            {
+                (None, _) if dep.optional => (),
+
+                (None, _) =>
+                {
+                    missing.push(dep.label());
+                    errors.push(ResolveError::Missing {
+                        plugin: plug.info.name.clone(),
+                        dep: dep.label(),
+                    })
+                }
+
+                (Some(p), true) =>
+                {
+                    missing.push(format!("{} {}", dep.label(), dep.req));
+                    errors.push(ResolveError::Incompatible {
+                        plugin: plug.info.name.clone(),
+                        dep: p.info.name.clone(),
+                        req: dep.req.clone(),
+                        found: p.info.version.clone(),
+                    })
+                }
+
+                // Loaded providers need no ordering, and a plugin can not wait on itself.
+                (Some(_), false) =>
+                {
+                    after.extend(
+                        matching
+                            .iter()
+                            .map(|p| p.id())
+                            .filter(|id| pending.contains_key(id) && *id != plug.id()),
+                    )
+                }
+            }
+        }
+
+        for conflict in &plug.info.conflicts
+        {
+            for other in providers.get(&conflict.key).into_iter().flatten()
+            {
+                if other.id() != plug.id() && conflict.req.matches(&other.info.version)
+                {
+                    missing.push(format!("{} to be removed", other.info.name));
+                    errors.push(ResolveError::Conflict {
+                        plugin: plug.info.name.clone(),
+                        other: other.info.name.clone(),
+                        version: other.info.version.clone(),
+                    });
+                }
+            }
+        }
+
+        edges.insert(plug.id(), after);
+        unmet.insert(plug.id(), missing);
+    }
+
+    let mut sorted = vec![];
+    let mut remaining = plugs.iter().map(|p| p.id()).collect::<Vec<_>>();
+
+    loop
+    {
+        let sorted_len = sorted.len();
+
+        remaining.retain(|id| {
+            let ready = unmet[id].is_empty() && edges[id].iter().all(|dep| sorted.contains(dep));
+            if ready
+            {
+                sorted.push(id.clone());
+            }
+
+            !ready
+        });
+
+        if sorted_len == sorted.len()
+        {
+            break;
+        }
+    }
+
+    for id in &remaining
+    {
+        let blockers = edges[id]
+            .iter()
+            .filter(|dep| !sorted.contains(dep))
+            .map(|dep| pending[dep].info.name.clone())
+            .collect::<Vec<_>>();
+
+        // Its own missing deps and conflicts were reported above already
+        if blockers.is_empty()
+        {
+            continue;
+        }
+
+        let mut waiting_on = unmet[id].clone();
+        waiting_on.extend(blockers);
+
+        errors.push(ResolveError::Unresolved {
+            plugin: pending[id].info.name.clone(),
+            waiting_on,
+        });
+    }
+
+    for cycle in find_cycles(&remaining, &edges)
+    {
+        errors.push(ResolveError::Cycle(
+            cycle
+                .iter()
+                .map(|id| pending[id].info.name.clone())
+                .collect(),
+        ));
+    }
+
+    if errors.is_empty()
+    {
+        Ok(sorted)
+    }
+    else
+    {
+        Err(errors)
+    }
+}
+
+
+/// Depth first search for cycles among `nodes`. Every cycle ends with its first node again.
+fn find_cycles(nodes: &[LayerKey], edges: &HashMap<LayerKey, Vec<LayerKey>>) -> Vec<Vec<LayerKey>>
+{
+    fn visit(
+        node: &LayerKey,
+        edges: &HashMap<LayerKey, Vec<LayerKey>>,
+        stack: &mut Vec<LayerKey>,
+        done: &mut Vec<LayerKey>,
+        cycles: &mut Vec<Vec<LayerKey>>,
+    )
+    {
+        if done.contains(node)
+        {
+            return;
+        }
+
+        if let Some(start) = stack.iter().position(|n| n == node)
+        {
+            let mut cycle = stack[start..].to_vec();
+            cycle.push(node.clone());
+            cycles.push(cycle);
+            return;
+        }
+
+        stack.push(node.clone());
+        for next in &edges[node]
+        {
+            visit(next, edges, stack, done, cycles);
+        }
+
+        stack.pop();
+        done.push(node.clone());
+    }
+
+    let mut cycles = vec![];
+    let mut done = vec![];
+
+    for node in nodes
+    {
+        visit(node, edges, &mut vec![], &mut done, &mut cycles);
+    }
+
+    cycles
+}
+
+
+#[cfg(test)]
+mod tests
+{
+    use crate::loader::PluginLoader;
+    use thorn::prelude::*;
+
+    struct A;
+    struct B;
+    struct C;
+    struct Missing;
+    struct Capability;
+
+    struct TestPlugin(PluginInfo);
+
+    impl Plugin<LayerEvent> for TestPlugin
+    {
+        fn info(&self) -> PluginInfo
+        {
+            self.0.clone()
+        }
+
+        fn load(
+            &mut self,
+            _ctx: &PluginContext<LayerEvent>,
+        ) -> Result<AnyLayer<LayerEvent>, Box<dyn std::error::Error>>
+        {
+            Err(ThError::Error("Test plugins are never loaded".into()).into())
+        }
+    }
+
+    // The load order by plugin name, or the errors as they are printed
+    fn resolve(plugins: Vec<PluginInfo>) -> Result<Vec<String>, Vec<String>>
+    {
+        let mut loader = PluginLoader::new();
+        for info in plugins
+        {
+            assert!(loader.discover_plugin(TestPlugin(info)).is_none());
+        }
+
+        match loader.dep_sort()
+        {
+            Ok(order) =>
+            {
+                Ok(order
+                    .iter()
+                    .map(|id| loader.plugin_info(id).unwrap().name.clone())
+                    .collect())
+            }
+
+            Err(ThError::PluginResolve(errors)) =>
+            {
+                Err(errors.iter().map(ToString::to_string).collect())
+            }
+            Err(e) => panic!("Unexpected error: {e}"),
+        }
+    }
+
+    fn plugin<T: 'static>(version: &str) -> PluginInfo
+    {
+        PluginInfo::build::<T>().version(version)
+    }
+
+    #[test]
+    fn loads_dependencies_first()
+    {
+        let order = resolve(vec![
+            plugin::<A>("1.0.0").dep::<C>(),
+            plugin::<B>("1.0.0"),
+            plugin::<C>("1.0.0").dep::<B>(),
+        ]);
+
+        assert_eq!(order.unwrap(), ["B", "C", "A"]);
+
+        // Independent plugins load in the order of their names
+        let order = resolve(vec![
+            plugin::<C>("1.0.0"),
+            plugin::<A>("1.0.0"),
+            plugin::<B>("1.0.0"),
+        ]);
+
+        assert_eq!(order.unwrap(), ["A", "B", "C"]);
+    }
+
+    #[test]
+    fn reports_missing_dependencies()
+    {
+        let errors = resolve(vec![plugin::<A>("1.0.0").dep::<Missing>()]);
+        assert_eq!(
+            errors.unwrap_err(),
+            ["Plugin A depends on Missing, which is missing"]
+        );
+
+        let errors = resolve(vec![plugin::<A>("1.0.0").dep_named::<B>("second")]);
+        assert_eq!(
+            errors.unwrap_err(),
+            ["Plugin A depends on B@second, which is missing"]
+        );
+    }
+
+    #[test]
+    fn skips_missing_optional_dependencies()
+    {
+        let order = resolve(vec![
+            plugin::<A>("1.0.0").optional_dep::<Missing>(),
+            plugin::<B>("1.0.0").optional_dep::<C>(),
+            plugin::<C>("1.0.0"),
+        ]);
+
+        assert_eq!(order.unwrap(), ["A", "C", "B"]);
+    }
+
+    #[test]
+    fn matches_version_requirements()
+    {
+        let order = resolve(vec![
+            plugin::<A>("1.0.0").depends(Dependency::on::<B>().version("^1.2")),
+            plugin::<B>("1.4.1"),
+        ]);
+
+        assert_eq!(order.unwrap(), ["B", "A"]);
+
+        let errors = resolve(vec![
+            plugin::<A>("1.0.0").depends(Dependency::on::<B>().version(">=2")),
+            plugin::<B>("1.4.1"),
+        ]);
+
+        assert_eq!(
+            errors.unwrap_err(),
+            ["Plugin A requires B >=2, but only 1.4.1 is present"]
+        );
+    }
+
+    #[test]
+    fn reports_invalid_versions()
+    {
+        let errors = resolve(vec![
+            plugin::<A>("one"),
+            plugin::<B>("1.0.0").depends(Dependency::on::<C>().version("~~1")),
+            plugin::<C>("1.0.0"),
+        ])
+        .unwrap_err();
+
+        assert_eq!(errors.len(), 2, "{errors:?}");
+        assert!(errors[0].starts_with("Plugin A declares an invalid version \"one\""));
+        assert!(errors[1].starts_with("Plugin B declares an invalid requirement \"~~1\" on C"));
+    }
+
+    #[test]
+    fn reports_conflicts()
+    {
+        let errors = resolve(vec![
+            plugin::<A>("1.0.0").conflicts::<B>(),
+            plugin::<B>("2.0.0"),
+        ]);
+
+        assert_eq!(errors.unwrap_err(), ["Plugin A conflicts with B 2.0.0"]);
+
+        // Only the versions matching the conflict's requirement
+        let order = resolve(vec![
+            plugin::<A>("1.0.0").conflicts_with(Dependency::on::<B>().version("<2")),
+            plugin::<B>("2.0.0"),
+        ]);
+
+        assert_eq!(order.unwrap(), ["A", "B"]);
+    }
+
+    #[test]
+    fn satisfies_dependencies_with_provided_capabilities()
+    {
+        let order = resolve(vec![
+            plugin::<A>("1.0.0").dep::<Capability>(),
+            plugin::<B>("1.0.0").provides::<Capability>(),
+        ]);
+
+        assert_eq!(order.unwrap(), ["B", "A"]);
+
+        // A capability conflicts with every plugin providing it
+        let errors = resolve(vec![
+            plugin::<A>("1.0.0").conflicts::<Capability>(),
+            plugin::<B>("1.0.0").provides::<Capability>(),
+        ]);
+
+        assert_eq!(errors.unwrap_err(), ["Plugin A conflicts with B 1.0.0"]);
+    }
+
+    #[test]
+    fn reports_plugins_waiting_on_unresolved_ones()
+    {
+        let errors = resolve(vec![
+            plugin::<A>("1.0.0").dep::<Missing>(),
+            plugin::<B>("1.0.0").dep::<A>(),
+            plugin::<C>("1.0.0").dep::<B>().dep::<Missing>(),
+        ]);
+
+        // A's missing dependency is only reported once, not as A being unresolved
+        assert_eq!(
+            errors.unwrap_err(),
+            [
+                "Plugin A depends on Missing, which is missing",
+                "Plugin C depends on Missing, which is missing",
+                "Plugin B can not be loaded, it is waiting on A",
+                "Plugin C can not be loaded, it is waiting on Missing, B",
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_cycles()
+    {
+        let errors = resolve(vec![
+            plugin::<A>("1.0.0").dep::<B>(),
+            plugin::<B>("1.0.0").dep::<A>(),
+            plugin::<C>("1.0.0"),
+        ]);
+
+        assert_eq!(
+            errors.unwrap_err(),
+            [
+                "Plugin A can not be loaded, it is waiting on B",
+                "Plugin B can not be loaded, it is waiting on A",
+                "Plugins A -> B -> A depend on each other",
+            ]
+        );
+
+        // Plugins depending on themselves load anyway
+        let order = resolve(vec![plugin::<A>("1.0.0").dep::<A>()]);
+        assert_eq!(order.unwrap(), ["A"]);
+    }
+}
@@ -16,6 +16,14 @@ type AnyResult<T> = Result<T, Box<dyn std::error::Error>>;
 fn main() -> Result<(), Box<dyn std::error::Error>>
 {
     println!("cargo:rerun-if-changed=../shaders");
+
+    // Plugin libraries have to be built by the same compiler as the runner loading them
+    let rustc = Command::new(var("RUSTC")?).arg("--version").output()?;
+    println!(
+        "cargo:rustc-env=THORN_RUSTC_VERSION={}",
+        String::from_utf8(rustc.stdout)?.trim()
+    );
+
     let target_dir = var("OUT_DIR").unwrap();
 
     let out = PathBuf::from(format!("{target_dir}/../../.."))
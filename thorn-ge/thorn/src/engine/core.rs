@@ -1,8 +1,9 @@
 use crate::prelude::*;
+use serde::Deserialize;
 use std::error::Error;
 use std::sync::Arc;
-use std::sync::mpsc::{Receiver, Sender, TryRecvError, channel};
-use std::thread::{JoinHandle, sleep, spawn};
+use std::sync::mpsc::{Receiver, RecvTimeoutError, Sender, TryRecvError, channel};
+use std::thread::{JoinHandle, ThreadId, sleep, spawn};
 use std::time::{Duration, Instant};
 
 
@@ -43,7 +44,49 @@ impl<T: CoreHook + Send + Sync> CoreHook for Layer<T>
 }
 
 
-pub struct CorePlugin(pub Sender<CoreMsg>);
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CoreConfig
+{
+    // At least 1
+    pub fps_cap: u32,
+
+    // Stops the main loop after this many frames
+    pub frame_limit: Option<u64>,
+}
+
+
+impl Default for CoreConfig
+{
+    fn default() -> Self
+    {
+        Self {
+            fps_cap: 120,
+            frame_limit: None,
+        }
+    }
+}
+
+
+pub struct CorePlugin
+{
+    loader: Sender<CoreMsg>,
+    config: CoreConfig,
+}
+
+
+impl CorePlugin
+{
+    pub fn new(loader: Sender<CoreMsg>) -> Self
+    {
+        Self {
+            loader,
+            config: CoreConfig::default(),
+        }
+    }
+}
+
+
 impl Plugin<LayerEvent> for CorePlugin
 {
     fn info(&self) -> PluginInfo
@@ -51,24 +94,43 @@ impl Plugin<LayerEvent> for CorePlugin
         PluginInfo::build::<Core>()
     }
 
+    fn configure(&mut self, config: &PluginConfig) -> Result<(), Box<dyn std::error::Error>>
+    {
+        let config: CoreConfig = config.get()?;
+        if config.fps_cap == 0
+        {
+            return Err(ThError::Error("fps_cap has to be at least 1".into()).into());
+        }
+
+        self.config = config;
+        Ok(())
+    }
+
     fn load(
         &mut self,
-        _reg: &LayerReg<LayerEvent>,
+        _ctx: &PluginContext<LayerEvent>,
     ) -> Result<AnyLayer<LayerEvent>, Box<dyn std::error::Error>>
     {
-        Ok(AnyLayer::new(Core::new(self.0.clone())))
+        Ok(AnyLayer::new(Core::new(self.loader.clone())))
     }
 
-    fn notify_loaded(&mut self, reg: &LayerReg<LayerEvent>)
+    fn notify_loaded(&mut self, ctx: &PluginContext<LayerEvent>)
     {
-        let core = reg.get::<Core>().unwrap();
+        let core = ctx.get::<Core>().unwrap();
         let core_clone = core.clone();
-        core.write().unwrap().start_main_loop(core_clone);
+        let mut core = core.write().unwrap();
+        core.start_main_loop(core_clone);
+        core.set_fps_cap(self.config.fps_cap);
+
+        if let Some(frames) = self.config.frame_limit
+        {
+            core.set_frame_limit(frames);
+        }
     }
 
-    fn notify_unloaded(&mut self, reg: &LayerReg<LayerEvent>)
+    fn notify_unloaded(&mut self, ctx: &PluginContext<LayerEvent>)
     {
-        reg.get::<Core>()
+        ctx.get::<Core>()
             .unwrap()
             .write()
             .unwrap()
@@ -81,8 +143,46 @@ enum MainLoopMsg
 {
     Terminate,
     SetFpsCap(u32),
-    SetTasks(Vec<MainLoopTask>),
-    AddHooks(Vec<Box<dyn CoreHook>>),
+    SetFrameLimit(u64),
+    SetTasks(Vec<(u64, MainLoopTask)>),
+    CancelTask(u64),
+    AddHooks(Vec<(u64, Box<dyn CoreHook>)>),
+    RemoveHook(u64),
+    Sync(Sender<()>),
+}
+
+
+/// Completes once the main loop handled everything sent to it before, see [`Core::sync`].
+pub struct MainLoopSync(Option<(Receiver<()>, ThreadId)>);
+
+
+impl MainLoopSync
+{
+    // Long enough for any sane frame
+    const TIMEOUT: Duration = Duration::from_secs(5);
+
+    /// Blocks until the main loop is between frames past this point, or stopped.
+    /// Returns false if that could not be confirmed, because the main loop took too long
+    /// or this is the main loop's own thread.
+    pub fn wait(self) -> bool
+    {
+        let Some((done, main_loop)) = self.0
+        else
+        {
+            return true;
+        };
+
+        if std::thread::current().id() == main_loop
+        {
+            return false;
+        }
+
+        match done.recv_timeout(Self::TIMEOUT)
+        {
+            Ok(_) | Err(RecvTimeoutError::Disconnected) => true,
+            Err(RecvTimeoutError::Timeout) => false,
+        }
+    }
 }
 
 
@@ -103,8 +203,9 @@ pub struct Core
 {
     loader: Sender<CoreMsg>,
     main_loop: Option<MainLoop>,
-    tasks: Option<Vec<MainLoopTask>>,
-    hooks: Option<Vec<Box<dyn CoreHook>>>,
+    tasks: Option<Vec<(u64, MainLoopTask)>>,
+    hooks: Option<Vec<(u64, Box<dyn CoreHook>)>>,
+    next_hook_id: u64,
 }
 
 impl Core
@@ -117,6 +218,7 @@ impl Core
             main_loop: None,
             tasks: None,
             hooks: None,
+            next_hook_id: 0,
         }
     }
 
@@ -127,12 +229,27 @@ impl Core
 
     pub fn set_fps_cap(&self, max_fps: u32)
     {
+        if max_fps == 0
+        {
+            log::warn!("Ignoring an fps cap of 0");
+            return;
+        }
+
         if let Some(m) = &self.main_loop
         {
             let _ = m.conn.send(MainLoopMsg::SetFpsCap(max_fps));
         }
     }
 
+    /// Terminates the application once the main loop has run `frames` frames in total.
+    pub fn set_frame_limit(&self, frames: u64)
+    {
+        if let Some(m) = &self.main_loop
+        {
+            let _ = m.conn.send(MainLoopMsg::SetFrameLimit(frames));
+        }
+    }
+
     pub fn terminate(&self)
     {
         if let Some(m) = &self.main_loop
@@ -155,27 +272,81 @@ impl Core
         false
     }
 
-    pub fn schedule_task(&mut self, task: MainLoopTask)
+    /// Runs the task once in the next frame. `id` is the task's id from [`Tasks`].
+    pub fn schedule_task(&mut self, id: u64, task: MainLoopTask)
     {
         if self.tasks.is_none()
         {
-            self.tasks = Some(vec![task]);
+            self.tasks = Some(vec![(id, task)]);
         }
         else
         {
-            self.tasks.as_mut().unwrap().push(task);
+            self.tasks.as_mut().unwrap().push((id, task));
+        }
+    }
+
+    /// Drops every scheduled run of the task that did not happen yet.
+    pub(crate) fn cancel_task(&mut self, id: u64)
+    {
+        // The task might not have been handed over to the main loop yet
+        if let Some(tasks) = &mut self.tasks
+        {
+            tasks.retain(|(task, _)| *task != id);
+        }
+
+        if let Some(m) = &self.main_loop
+        {
+            let _ = m.conn.send(MainLoopMsg::CancelTask(id));
         }
     }
 
-    pub(crate) fn add_hook(&mut self, hook: impl CoreHook + 'static)
+    pub(crate) fn add_hook(&mut self, hook: impl CoreHook + 'static) -> u64
     {
+        let id = self.next_hook_id;
+        self.next_hook_id += 1;
+
         if self.hooks.is_none()
         {
-            self.hooks = Some(vec![Box::new(hook)])
+            self.hooks = Some(vec![(id, Box::new(hook))])
         }
         else
         {
-            self.hooks.as_mut().unwrap().push(Box::new(hook));
+            self.hooks.as_mut().unwrap().push((id, Box::new(hook)));
+        }
+
+        id
+    }
+
+    pub(crate) fn remove_hook(&mut self, id: u64)
+    {
+        // The hook might not have been handed over to the main loop yet
+        if let Some(hooks) = &mut self.hooks
+        {
+            hooks.retain(|(hook, _)| *hook != id);
+        }
+
+        if let Some(m) = &self.main_loop
+        {
+            let _ = m.conn.send(MainLoopMsg::RemoveHook(id));
+        }
+    }
+
+    /// Removed hooks and cancelled tasks have been dropped by the main loop and none of
+    /// their code is running anymore, once the returned sync completes. Do not hold
+    /// any layer locks while waiting for it.
+    pub fn sync(&self) -> MainLoopSync
+    {
+        let Some(m) = &self.main_loop
+        else
+        {
+            return MainLoopSync(None);
+        };
+
+        let (done, wait) = channel();
+        match m.conn.send(MainLoopMsg::Sync(done))
+        {
+            Ok(_) => MainLoopSync(Some((wait, m.handle.thread().id()))),
+            Err(_) => MainLoopSync(None),
         }
     }
 
@@ -256,9 +427,17 @@ fn main_loop(core: Layer<Core>, msg: Receiver<MainLoopMsg>)
     let mut fps_cap = Duration::from_secs_f64(1.0 / 120.0);
     let mut tasks = vec![];
     let mut hooks = vec![];
+    let mut frame_limit = None;
+    let mut frames = 0u64;
 
     'mainloop: loop
     {
+        if frame_limit.is_some_and(|limit| frames >= limit)
+        {
+            log::info!("Reached the frame limit of {frames} frames");
+            break 'mainloop;
+        }
+
         let frame_start = Instant::now();
 
         // Dispatch all new messages...
@@ -285,11 +464,21 @@ fn main_loop(core: Layer<Core>, msg: Receiver<MainLoopMsg>)
                     }
                 }
 
+                Ok(MainLoopMsg::SetFrameLimit(limit)) => frame_limit = Some(limit),
+
                 // Receive all new hooks...
                 Ok(MainLoopMsg::AddHooks(h)) => hooks.extend(h),
+                Ok(MainLoopMsg::RemoveHook(id)) => hooks.retain(|(hook, _)| *hook != id),
 
                 // Add newly received tasks to the task queue
                 Ok(MainLoopMsg::SetTasks(t)) => tasks.extend(t),
+                Ok(MainLoopMsg::CancelTask(id)) => tasks.retain(|(task, _)| *task != id),
+
+                // Everything sent before has been handled
+                Ok(MainLoopMsg::Sync(done)) =>
+                {
+                    let _ = done.send(());
+                }
 
                 // Do nothing if there is no msg.
                 Err(TryRecvError::Empty) => break 'msgloop,
@@ -300,7 +489,7 @@ fn main_loop(core: Layer<Core>, msg: Receiver<MainLoopMsg>)
         }
 
         // Run all Frame preparation hooks
-        for hook in &mut hooks
+        for (_, hook) in &mut hooks
         {
             hook.prepare();
         }
@@ -314,13 +503,13 @@ fn main_loop(core: Layer<Core>, msg: Receiver<MainLoopMsg>)
         let frame_info = FrameInfo { delta };
 
         // Run all frame tick hooks
-        for hook in &mut hooks
+        for (_, hook) in &mut hooks
         {
             hook.tick(&frame_info);
         }
 
         // TODO: Maybe figure out something more smart with the tasks...
-        while let Some(task) = tasks.pop()
+        while let Some((_, task)) = tasks.pop()
         {
             if let Err(e) = task(&frame_info)
             {
@@ -329,13 +518,14 @@ fn main_loop(core: Layer<Core>, msg: Receiver<MainLoopMsg>)
         }
 
         // Run all frame end hooks
-        for hook in &mut hooks
+        for (_, hook) in &mut hooks
         {
             hook.finish();
         }
 
         // FRAME END
         ////////////
+        frames += 1;
         let frame_end = Instant::now();
         delta = frame_end - frame_start;
 
@@ -3,17 +3,42 @@ use std::marker::PhantomData;
 use crate::prelude::*;
 
 
-pub struct EventEmitterPlugin<E>(PhantomData<E>);
+pub struct EventEmitterPlugin<E>
+{
+    instance: Option<String>,
+    _event: PhantomData<E>,
+}
+
+
+impl<E> EventEmitterPlugin<E>
+{
+    /// An emitter that lives next to the default one, under the given name.
+    pub fn named(name: impl Into<String>) -> Self
+    {
+        Self {
+            instance: Some(name.into()),
+            _event: PhantomData,
+        }
+    }
+}
+
+
 impl<E: Send + Sync + 'static> Plugin<LayerEvent> for EventEmitterPlugin<E>
 {
     fn info(&self) -> PluginInfo
     {
-        PluginInfo::build::<EventEmitter<E>>()
+        let info = PluginInfo::build::<EventEmitter<E>>();
+
+        match &self.instance
+        {
+            Some(name) => info.instance(name),
+            None => info,
+        }
     }
 
     fn load(
         &mut self,
-        _reg: &LayerReg<LayerEvent>,
+        _ctx: &PluginContext<LayerEvent>,
     ) -> Result<AnyLayer<LayerEvent>, Box<dyn std::error::Error>>
     {
         Ok(AnyLayer::new(EventEmitter::<E>::new()))
@@ -25,7 +50,10 @@ impl<E> Default for EventEmitterPlugin<E>
 {
     fn default() -> Self
     {
-        EventEmitterPlugin(PhantomData)
+        EventEmitterPlugin {
+            instance: None,
+            _event: PhantomData,
+        }
     }
 }
 
@@ -39,7 +67,7 @@ pub struct EventEmitter<E>
 impl<E> EventEmitter<E>
 {
     #[allow(clippy::new_without_default)]
-    fn new() -> Self
+    pub(crate) fn new() -> Self
     {
         Self { buffer: vec![] }
     }
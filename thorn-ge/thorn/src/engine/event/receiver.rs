@@ -3,22 +3,50 @@ use std::marker::PhantomData;
 use crate::prelude::*;
 
 
-pub struct EventReceiverPlugin<E>(PhantomData<E>);
+pub struct EventReceiverPlugin<E>
+{
+    instance: Option<String>,
+    _event: PhantomData<E>,
+}
+
+
+impl<E> EventReceiverPlugin<E>
+{
+    /// A receiver for the emitter with the same instance name.
+    pub fn named(name: impl Into<String>) -> Self
+    {
+        Self {
+            instance: Some(name.into()),
+            _event: PhantomData,
+        }
+    }
+}
+
+
 impl<E: Send + Sync + 'static> Plugin<LayerEvent> for EventReceiverPlugin<E>
 {
     fn info(&self) -> PluginInfo
     {
-        PluginInfo::build::<EventReceiver<E>>().dep::<EventEmitter<E>>()
+        let info = PluginInfo::build::<EventReceiver<E>>();
+
+        match &self.instance
+        {
+            Some(name) => info.instance(name).dep_named::<EventEmitter<E>>(name),
+            None => info.dep::<EventEmitter<E>>(),
+        }
     }
 
     fn load(
         &mut self,
-        reg: &LayerReg<LayerEvent>,
+        ctx: &PluginContext<LayerEvent>,
     ) -> Result<AnyLayer<LayerEvent>, Box<dyn std::error::Error>>
     {
-        let emitter = reg
-            .get::<EventEmitter<E>>()
-            .ok_or(ThError::Error("Failed to fetch matching emitter".into()))?;
+        let emitter = match &self.instance
+        {
+            Some(name) => ctx.get_named::<EventEmitter<E>>(name),
+            None => ctx.get::<EventEmitter<E>>(),
+        }
+        .ok_or(ThError::Error("Failed to fetch matching emitter".into()))?;
 
         Ok(AnyLayer::new(EventReceiver::<E>::new(emitter)))
     }
@@ -29,7 +57,10 @@ impl<E> Default for EventReceiverPlugin<E>
 {
     fn default() -> Self
     {
-        EventReceiverPlugin(PhantomData)
+        EventReceiverPlugin {
+            instance: None,
+            _event: PhantomData,
+        }
     }
 }
 
@@ -38,7 +69,8 @@ pub struct EventReceiver<E: Send + Sync>
 {
     events: Vec<E>,
     emitter: Layer<EventEmitter<E>>,
-    subscribers: Vec<Box<dyn EventSubscriber<E>>>,
+    subscribers: Vec<(u64, Box<dyn EventSubscriber<E>>)>,
+    next_id: u64,
 }
 
 
@@ -50,13 +82,23 @@ impl<E: Send + Sync> EventReceiver<E>
         Self {
             emitter,
             subscribers: vec![],
+            next_id: 0,
             events: Vec::with_capacity(10),
         }
     }
 
-    pub fn subscribe(&mut self, subscriber: impl EventSubscriber<E> + 'static)
+    pub fn subscribe(&mut self, subscriber: impl EventSubscriber<E> + 'static) -> u64
+    {
+        let id = self.next_id;
+        self.next_id += 1;
+
+        self.subscribers.push((id, Box::new(subscriber)));
+        id
+    }
+
+    pub fn unsubscribe(&mut self, subscription: u64)
     {
-        self.subscribers.push(Box::new(subscriber));
+        self.subscribers.retain(|(id, _)| *id != subscription);
     }
 }
 
@@ -73,7 +115,7 @@ impl<E: Send + Sync> LayerDispatch<LayerEvent> for EventReceiver<E>
             {
                 self.subscribers
                     .iter_mut()
-                    .for_each(|sub| sub.receive_event(e))
+                    .for_each(|(_, sub)| sub.receive_event(e))
             }
 
             self.events.clear();
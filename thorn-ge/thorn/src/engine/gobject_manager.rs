@@ -1,9 +1,5 @@
 use std::{
-    sync::{
-        Arc,
-        Mutex,
-        atomic::{AtomicU64, Ordering},
-    },
+    sync::{Arc, Mutex},
     time::Duration,
 };
 
@@ -24,29 +20,29 @@ impl Plugin<LayerEvent> for GobjectManagerPlugin
 
     fn load(
         &mut self,
-        reg: &LayerReg<LayerEvent>,
+        ctx: &PluginContext<LayerEvent>,
     ) -> Result<AnyLayer<LayerEvent>, Box<dyn std::error::Error>>
     {
-        let tasks = reg
+        let tasks = ctx
             .get()
             .ok_or(ThError::Error("Failed to fetch engine tasks layer".into()))?;
 
-        let event_receiver = reg.get().ok_or(ThError::Error(
+        let event_receiver = ctx.get().ok_or(ThError::Error(
             "Failed to fetch engine event receiver layer".into(),
         ))?;
 
         Ok(AnyLayer::new(GobjectManager::new(event_receiver, tasks)))
     }
 
-    fn notify_loaded(&mut self, reg: &LayerReg<LayerEvent>)
+    fn notify_loaded(&mut self, ctx: &PluginContext<LayerEvent>)
     {
-        let me = reg.get::<GobjectManager>().unwrap();
+        let me = ctx.get::<GobjectManager>().unwrap();
         me.clone().write().unwrap().init(me);
     }
 
-    fn notify_unloaded(&mut self, reg: &LayerReg<LayerEvent>)
+    fn notify_unloaded(&mut self, ctx: &PluginContext<LayerEvent>)
     {
-        reg.get::<GobjectManager>()
+        ctx.get::<GobjectManager>()
             .unwrap()
             .write()
             .unwrap()
@@ -74,12 +70,10 @@ struct GobjContainer
 
 impl GobjContainer
 {
-    fn new(obj: impl Gobject + 'static) -> Self
+    fn new(id: u64, obj: impl Gobject + 'static) -> Self
     {
-        static ID: AtomicU64 = AtomicU64::new(0);
-
         Self {
-            id: ID.fetch_add(1, Ordering::Relaxed),
+            id,
             obj: Arc::new(Mutex::new(obj)),
         }
     }
@@ -92,6 +86,7 @@ pub struct GobjectManager
     tasks: Layer<Tasks>,
     task_id: Option<u64>,
     gobjs: Vec<GobjContainer>,
+    next_id: u64,
 }
 
 
@@ -99,7 +94,9 @@ impl GobjectManager
 {
     pub fn add_gobj(&mut self, gobj: impl Gobject + 'static) -> u64
     {
-        let obj = GobjContainer::new(gobj);
+        let obj = GobjContainer::new(self.next_id, gobj);
+        self.next_id += 1;
+
         let id = obj.id;
         obj.obj.lock().unwrap().reset();
         self.gobjs.push(obj);
@@ -122,6 +119,7 @@ impl GobjectManager
             tasks,
             task_id: None,
             gobjs: vec![],
+            next_id: 0,
         }
     }
 
@@ -0,0 +1,634 @@
+use super::Input;
+use super::binding::{Analog, Button, Chord};
+use crate::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+
+
+pub const DEFAULT_DEAD_ZONE: f32 = 0.15;
+
+
+pub struct ActionMapPlugin
+{
+    config: ActionMapConfig,
+}
+
+
+/// The plugin's manifest section. The bindings in it are the defaults,
+/// which are overridden by the ones in `bindings_file`, if it exists.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+struct ActionMapConfig
+{
+    bindings_file: Option<PathBuf>,
+
+    #[serde(flatten)]
+    bindings: Bindings,
+}
+
+
+impl ActionMapPlugin
+{
+    pub fn new() -> Self
+    {
+        Self {
+            config: ActionMapConfig::default(),
+        }
+    }
+}
+
+
+impl Default for ActionMapPlugin
+{
+    fn default() -> Self
+    {
+        Self::new()
+    }
+}
+
+
+impl Plugin<LayerEvent> for ActionMapPlugin
+{
+    fn info(&self) -> PluginInfo
+    {
+        PluginInfo::build::<ActionMap>()
+            .dep::<Input>()
+            .dep::<Tasks>()
+            .optional_dep::<Gamepads>()
+    }
+
+    fn configure(&mut self, config: &PluginConfig) -> Result<(), Box<dyn std::error::Error>>
+    {
+        self.config = config.get()?;
+        Ok(())
+    }
+
+    fn load(
+        &mut self,
+        ctx: &PluginContext<LayerEvent>,
+    ) -> Result<AnyLayer<LayerEvent>, Box<dyn std::error::Error>>
+    {
+        let input = ctx
+            .get::<Input>()
+            .ok_or(ThError::Error("Failed to fetch input layer".into()))?;
+
+        let mut bindings = self.config.bindings.clone();
+
+        if let Some(path) = self.config.bindings_file.as_ref().filter(|p| p.exists())
+        {
+            bindings.merge(Bindings::load(path)?);
+        }
+
+        let mut map = ActionMap::new(input, ctx.get::<Gamepads>(), bindings);
+        map.file = self.config.bindings_file.clone();
+
+        Ok(AnyLayer::new(map))
+    }
+
+    fn notify_loaded(&mut self, ctx: &PluginContext<LayerEvent>)
+    {
+        let map = ctx.get::<ActionMap>().unwrap();
+
+        if let Err(e) = ctx.hook(map)
+        {
+            log::error!("Actions will not be updated: {e}");
+        }
+    }
+}
+
+
+/// An axis driven by pairs of buttons and by gamepad axes. All inputs are added up.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AxisBinding
+{
+    pub positive: Vec<Chord>,
+    pub negative: Vec<Chord>,
+    pub analog: Vec<Analog>,
+
+    /// Overrides the dead zone of all bindings.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dead_zone: Option<f32>,
+}
+
+
+/// Which inputs trigger which action or drive which axis. An action may have several
+/// bindings and is active while any of them is held.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Bindings
+{
+    /// Gamepad axes below this magnitude read as zero, [`DEFAULT_DEAD_ZONE`] if not set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dead_zone: Option<f32>,
+
+    /// Whether chords also trigger while modifiers they do not name are held,
+    /// like `S` while `Ctrl+S` is pressed. Off if not set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ignore_extra_modifiers: Option<bool>,
+
+    pub actions: BTreeMap<String, Vec<Chord>>,
+    pub axes: BTreeMap<String, AxisBinding>,
+}
+
+
+impl Bindings
+{
+    pub fn load(path: impl AsRef<Path>) -> ThResult<Self>
+    {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| ThError::Error(format!("Failed to read bindings {path:?}: {e}")))?;
+
+        toml::from_str(&text)
+            .map_err(|e| ThError::Error(format!("Invalid bindings in {path:?}: {}", e.message())))
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> ThResult<()>
+    {
+        let path = path.as_ref();
+        let text = toml::to_string_pretty(self)
+            .map_err(|e| ThError::Error(format!("Failed to serialize bindings: {e}")))?;
+
+        std::fs::write(path, text)
+            .map_err(|e| ThError::Error(format!("Failed to write bindings {path:?}: {e}")))
+    }
+
+    /// Replaces the bindings of every action and axis that `other` binds,
+    /// and the options that `other` sets.
+    pub fn merge(&mut self, other: Bindings)
+    {
+        if other.dead_zone.is_some()
+        {
+            self.dead_zone = other.dead_zone;
+        }
+
+        if other.ignore_extra_modifiers.is_some()
+        {
+            self.ignore_extra_modifiers = other.ignore_extra_modifiers;
+        }
+
+        self.actions.extend(other.actions);
+        self.axes.extend(other.axes);
+    }
+}
+
+
+#[derive(Debug, Clone, Copy, Default)]
+struct ActionState
+{
+    down: bool,
+    was_down: bool,
+}
+
+
+/// Maps input to named actions and axes, so gameplay code does not depend on
+/// which keys the player chose. Updated when hooks tick, after [`Input`] and [`Gamepads`]
+/// applied the frame's events in their `prepare`. Hooks of plugins depending on the map
+/// tick after it and tasks run after all hooks, so they see the current frame.
+pub struct ActionMap
+{
+    input: Layer<Input>,
+    gamepads: Option<Layer<Gamepads>>,
+    bindings: Bindings,
+    file: Option<PathBuf>,
+    actions: HashMap<String, ActionState>,
+    axes: HashMap<String, f32>,
+}
+
+
+impl ActionMap
+{
+    fn new(input: Layer<Input>, gamepads: Option<Layer<Gamepads>>, bindings: Bindings) -> Self
+    {
+        Self {
+            input,
+            gamepads,
+            bindings,
+            file: None,
+            actions: HashMap::new(),
+            axes: HashMap::new(),
+        }
+    }
+
+    /// Whether any binding of the action is held.
+    pub fn pressed(&self, action: &str) -> bool
+    {
+        self.actions.get(action).is_some_and(|a| a.down)
+    }
+
+    /// Whether the action became active during the current frame.
+    pub fn just_pressed(&self, action: &str) -> bool
+    {
+        self.actions
+            .get(action)
+            .is_some_and(|a| a.down && !a.was_down)
+    }
+
+    /// Whether the action stopped being active during the current frame.
+    pub fn just_released(&self, action: &str) -> bool
+    {
+        self.actions
+            .get(action)
+            .is_some_and(|a| !a.down && a.was_down)
+    }
+
+    /// The axis' value between -1 and 1, or 0 for unknown axes.
+    pub fn axis(&self, axis: &str) -> f32
+    {
+        self.axes.get(axis).copied().unwrap_or(0.0)
+    }
+
+    pub fn bindings(&self) -> &Bindings
+    {
+        &self.bindings
+    }
+
+    /// Replaces all bindings, e.g. after the player changed them in a menu.
+    pub fn set_bindings(&mut self, bindings: Bindings)
+    {
+        self.bindings = bindings;
+    }
+
+    /// Adds another binding to an action.
+    pub fn bind(&mut self, action: impl Into<String>, chord: impl Into<Chord>)
+    {
+        self.bindings
+            .actions
+            .entry(action.into())
+            .or_default()
+            .push(chord.into());
+    }
+
+    pub fn bind_axis(&mut self, axis: impl Into<String>, binding: AxisBinding)
+    {
+        self.bindings.axes.insert(axis.into(), binding);
+    }
+
+    /// Removes all bindings of an action or axis.
+    pub fn unbind(&mut self, name: &str)
+    {
+        self.bindings.actions.remove(name);
+        self.bindings.axes.remove(name);
+    }
+
+    /// Saves the bindings to the file configured in the manifest.
+    pub fn save(&self) -> ThResult<()>
+    {
+        let file = self.file.as_ref().ok_or(ThError::Error(
+            "No bindings file is configured for the action map".into(),
+        ))?;
+
+        self.bindings.save(file)
+    }
+
+    fn update(&mut self)
+    {
+        let input = self.input.read().unwrap();
+        let gamepads = self.gamepads.as_ref().map(|g| g.read().unwrap());
+        let state = InputState {
+            input: &input,
+            gamepads: gamepads.as_deref(),
+            ignore_extra_modifiers: self.bindings.ignore_extra_modifiers.unwrap_or(false),
+        };
+
+        for action in self.actions.values_mut()
+        {
+            action.was_down = action.down;
+            action.down = false;
+        }
+
+        for (action, chords) in &self.bindings.actions
+        {
+            let down = chords.iter().any(|c| state.chord_held(c));
+
+            self.actions.entry(action.clone()).or_default().down = down;
+        }
+
+        // Keep released states of unbound actions for one more frame, then drop them
+        self.actions.retain(|_, a| a.down || a.was_down);
+
+        self.axes.clear();
+        for (name, axis) in &self.bindings.axes
+        {
+            let dead_zone = axis
+                .dead_zone
+                .or(self.bindings.dead_zone)
+                .unwrap_or(DEFAULT_DEAD_ZONE);
+            let held = |chords: &[Chord]| chords.iter().any(|c| state.chord_held(c));
+
+            let digital = held(&axis.positive) as i32 - held(&axis.negative) as i32;
+            let analog = axis
+                .analog
+                .iter()
+                .map(|a| apply_dead_zone(state.axis_value(a), dead_zone))
+                .sum::<f32>();
+
+            self.axes
+                .insert(name.clone(), (digital as f32 + analog).clamp(-1.0, 1.0));
+        }
+    }
+}
+
+
+/// Everything bindings are evaluated against.
+struct InputState<'a>
+{
+    input: &'a Input,
+    gamepads: Option<&'a Gamepads>,
+    ignore_extra_modifiers: bool,
+}
+
+
+impl InputState<'_>
+{
+    fn chord_held(&self, chord: &Chord) -> bool
+    {
+        self.modifiers_match(chord) && chord.buttons.iter().all(|b| self.button_held(b))
+    }
+
+    /// Chords need exactly their modifiers, besides the ones held by modifier keys they bind.
+    fn modifiers_match(&self, chord: &Chord) -> bool
+    {
+        let held = self.input.modifiers();
+        if !held.contains(chord.modifiers)
+        {
+            return false;
+        }
+
+        let allowed = chord.buttons.iter().fold(chord.modifiers, |m, b| {
+            match b
+            {
+                Button::Key(key) => m.union(key.modifier()),
+                _ => m,
+            }
+        });
+
+        self.ignore_extra_modifiers || allowed.contains(held)
+    }
+
+    /// Buttons pressed and released within one frame count as held during that frame.
+    fn button_held(&self, button: &Button) -> bool
+    {
+        match *button
+        {
+            Button::Key(key) => self.input.is_pressed(key) || self.input.just_pressed(key),
+
+            Button::Mouse(button) =>
+            {
+                self.input.is_mouse_pressed(button) || self.input.mouse_just_pressed(button)
+            }
+
+            Button::Pad(button) =>
+            {
+                self.gamepads.is_some_and(|g| {
+                    g.any_pressed(button) || g.connected().any(|pad| g.just_pressed(pad, button))
+                })
+            }
+        }
+    }
+
+    fn axis_value(&self, analog: &Analog) -> f32
+    {
+        let value = self
+            .gamepads
+            .map(|g| g.any_axis(analog.axis))
+            .unwrap_or(0.0);
+
+        if analog.inverted { -value } else { value }
+    }
+}
+
+
+/// Rescales the value, so it starts at 0 at the edge of the dead zone instead of jumping.
+fn apply_dead_zone(value: f32, dead_zone: f32) -> f32
+{
+    if value.abs() <= dead_zone || dead_zone >= 1.0
+    {
+        return 0.0;
+    }
+
+    value.signum() * (value.abs() - dead_zone) / (1.0 - dead_zone)
+}
+
+
+impl CoreHook for ActionMap
+{
+    fn tick(&mut self, _frame_info: &FrameInfo)
+    {
+        self.update();
+    }
+}
+
+
+impl LayerDispatch<LayerEvent> for ActionMap
+{
+    fn dispatch(&mut self, _event: &LayerEvent) {}
+}
+
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::engine::input::{GamepadButton, Key, Modifiers};
+
+    fn action_map(bindings: Bindings) -> (ActionMap, Layer<Input>)
+    {
+        let input = Layer::new(Input::default());
+        (ActionMap::new(input.clone(), None, bindings), input)
+    }
+
+    // Delivers the events and runs one frame of Input and the action map
+    fn frame(map: &mut ActionMap, input: &Layer<Input>, events: &[PlatformEvent])
+    {
+        {
+            let mut input = input.write().unwrap();
+            for event in events
+            {
+                input.receive_event(event);
+            }
+
+            input.next_frame();
+        }
+
+        map.update();
+    }
+
+    fn ctrl() -> Modifiers
+    {
+        Modifiers {
+            ctrl: true,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn merges_bindings()
+    {
+        let mut bindings: Bindings = toml::from_str(
+            r#"
+            dead_zone = 0.2
+            actions.jump = ["Space"]
+            actions.save = ["Ctrl+S"]
+            axes.move_x = { positive = ["D"], negative = ["A"] }
+            "#,
+        )
+        .unwrap();
+
+        let mut other = Bindings::default();
+        other
+            .actions
+            .insert("jump".into(), vec![Chord::new(GamepadButton::South)]);
+
+        bindings.merge(other);
+
+        assert_eq!(bindings.dead_zone, Some(0.2));
+        assert_eq!(bindings.actions["jump"], [Chord::new(GamepadButton::South)]);
+        assert_eq!(bindings.actions["save"], ["Ctrl+S".parse().unwrap()]);
+        assert!(bindings.axes.contains_key("move_x"));
+
+        bindings.merge(Bindings {
+            dead_zone: Some(0.3),
+            ignore_extra_modifiers: Some(true),
+            ..Default::default()
+        });
+
+        assert_eq!(bindings.dead_zone, Some(0.3));
+        assert_eq!(bindings.ignore_extra_modifiers, Some(true));
+        assert_eq!(bindings.actions.len(), 2);
+    }
+
+    #[test]
+    fn saves_and_loads_bindings()
+    {
+        let mut bindings = Bindings {
+            dead_zone: Some(0.25),
+            ..Default::default()
+        };
+
+        bindings.actions.insert(
+            "save".into(),
+            vec!["Ctrl+S".parse().unwrap(), "Mouse:4".parse().unwrap()],
+        );
+
+        bindings.axes.insert(
+            "look_y".into(),
+            AxisBinding {
+                positive: vec![Chord::new(Key::Up)],
+                negative: vec![Chord::new(Key::Down)],
+                analog: vec!["-Pad:RightStickY".parse().unwrap()],
+                dead_zone: None,
+            },
+        );
+
+        let path = std::env::temp_dir().join(format!("thorn-bindings-{}.toml", std::process::id()));
+        bindings.save(&path).unwrap();
+        let loaded = Bindings::load(&path);
+        let _ = std::fs::remove_file(&path);
+
+        assert_eq!(loaded.unwrap(), bindings);
+    }
+
+    #[test]
+    fn taps_within_one_frame_press_the_action()
+    {
+        let mut bindings = Bindings::default();
+        bindings
+            .actions
+            .insert("jump".into(), vec![Chord::new(Key::Space)]);
+        let (mut map, input) = action_map(bindings);
+
+        frame(
+            &mut map,
+            &input,
+            &[
+                PlatformEvent::KeyPressed(Key::Space),
+                PlatformEvent::KeyReleased(Key::Space),
+            ],
+        );
+
+        assert!(map.pressed("jump"));
+        assert!(map.just_pressed("jump"));
+
+        frame(&mut map, &input, &[]);
+        assert!(!map.pressed("jump"));
+        assert!(map.just_released("jump"));
+    }
+
+    #[test]
+    fn matches_modifiers_exactly()
+    {
+        let mut bindings = Bindings::default();
+        bindings
+            .actions
+            .insert("step".into(), vec![Chord::new(Key::S)]);
+        bindings.actions.insert(
+            "save".into(),
+            vec![Chord::new(Key::S).with_modifiers(ctrl())],
+        );
+        bindings
+            .actions
+            .insert("crouch".into(), vec![Chord::new(Key::ControlLeft)]);
+        let (mut map, input) = action_map(bindings);
+
+        frame(
+            &mut map,
+            &input,
+            &[
+                PlatformEvent::KeyPressed(Key::ControlLeft),
+                PlatformEvent::ModifiersChanged(ctrl()),
+                PlatformEvent::KeyPressed(Key::S),
+            ],
+        );
+
+        assert!(map.pressed("save"));
+        assert!(!map.pressed("step"));
+
+        // Modifier keys hold their own modifier
+        assert!(map.pressed("crouch"));
+
+        let mut bindings = map.bindings().clone();
+        bindings.ignore_extra_modifiers = Some(true);
+        map.set_bindings(bindings);
+
+        frame(&mut map, &input, &[]);
+        assert!(map.pressed("save"));
+        assert!(map.pressed("step"));
+    }
+
+    #[test]
+    fn drives_axes()
+    {
+        let mut bindings = Bindings::default();
+        bindings.axes.insert(
+            "move_x".into(),
+            AxisBinding {
+                positive: vec![Chord::new(Key::D)],
+                negative: vec![Chord::new(Key::A)],
+                ..Default::default()
+            },
+        );
+        let (mut map, input) = action_map(bindings);
+
+        frame(&mut map, &input, &[PlatformEvent::KeyPressed(Key::D)]);
+        assert_eq!(map.axis("move_x"), 1.0);
+
+        frame(&mut map, &input, &[PlatformEvent::KeyPressed(Key::A)]);
+        assert_eq!(map.axis("move_x"), 0.0);
+
+        frame(&mut map, &input, &[PlatformEvent::KeyReleased(Key::D)]);
+        assert_eq!(map.axis("move_x"), -1.0);
+        assert_eq!(map.axis("unknown"), 0.0);
+    }
+
+    #[test]
+    fn rescales_dead_zone()
+    {
+        assert_eq!(apply_dead_zone(0.1, 0.2), 0.0);
+        assert_eq!(apply_dead_zone(-1.0, 0.2), -1.0);
+        assert!((apply_dead_zone(0.6, 0.2) - 0.5).abs() < 1e-6);
+        assert_eq!(apply_dead_zone(0.5, 1.0), 0.0);
+    }
+}
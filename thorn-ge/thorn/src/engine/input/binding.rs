@@ -0,0 +1,381 @@
+use super::{GamepadAxis, GamepadButton, Key, Modifiers, MouseButton};
+use crate::prelude::*;
+use serde::de::{DeserializeOwned, IntoDeserializer};
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+
+/// Anything that can be held down.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Button
+{
+    Key(Key),
+    Mouse(MouseButton),
+    Pad(GamepadButton),
+}
+
+
+/// Buttons that have to be held down together, like `Ctrl+Shift+S`.
+/// Written as button names joined by `+`, with `Mouse:` and `Pad:` prefixes
+/// for mouse and gamepad buttons, e.g. `Shift+Mouse:Left` or `Pad:LeftBumper+Pad:South`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Chord
+{
+    pub modifiers: Modifiers,
+    pub buttons: Vec<Button>,
+}
+
+
+/// A gamepad axis, written as `Pad:LeftStickX`, or `-Pad:LeftStickY` to invert it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Analog
+{
+    pub axis: GamepadAxis,
+    pub inverted: bool,
+}
+
+
+impl Chord
+{
+    pub fn new(button: impl Into<Button>) -> Self
+    {
+        Self {
+            modifiers: Modifiers::default(),
+            buttons: vec![button.into()],
+        }
+    }
+
+    pub fn with(mut self, button: impl Into<Button>) -> Self
+    {
+        self.buttons.push(button.into());
+        self
+    }
+
+    pub fn with_modifiers(mut self, modifiers: Modifiers) -> Self
+    {
+        self.modifiers = modifiers;
+        self
+    }
+}
+
+
+impl From<Key> for Button
+{
+    fn from(key: Key) -> Self
+    {
+        Self::Key(key)
+    }
+}
+
+
+impl From<MouseButton> for Button
+{
+    fn from(button: MouseButton) -> Self
+    {
+        Self::Mouse(button)
+    }
+}
+
+
+impl From<GamepadButton> for Button
+{
+    fn from(button: GamepadButton) -> Self
+    {
+        Self::Pad(button)
+    }
+}
+
+
+impl<T: Into<Button>> From<T> for Chord
+{
+    fn from(button: T) -> Self
+    {
+        Self::new(button)
+    }
+}
+
+
+impl From<GamepadAxis> for Analog
+{
+    fn from(axis: GamepadAxis) -> Self
+    {
+        Self {
+            axis,
+            inverted: false,
+        }
+    }
+}
+
+
+impl FromStr for Button
+{
+    type Err = ThError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err>
+    {
+        if let Some(button) = s.strip_prefix("Mouse:")
+        {
+            return match button.parse::<u16>()
+            {
+                Ok(n) => Ok(Self::Mouse(MouseButton::Other(n))),
+                Err(_) => variant(button).map(Self::Mouse),
+            };
+        }
+
+        if let Some(button) = s.strip_prefix("Pad:")
+        {
+            return variant(button).map(Self::Pad);
+        }
+
+        if let Some(code) = s.strip_prefix("Scancode:")
+        {
+            return code
+                .parse()
+                .map(|c| Self::Key(Key::Unknown(c)))
+                .map_err(|_| ThError::Error(format!("Invalid scancode {code:?}")));
+        }
+
+        variant(s).map(Self::Key)
+    }
+}
+
+
+impl Display for Button
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result
+    {
+        match self
+        {
+            Self::Key(Key::Unknown(code)) => write!(f, "Scancode:{code}"),
+            Self::Key(key) => write!(f, "{key:?}"),
+            Self::Mouse(MouseButton::Other(n)) => write!(f, "Mouse:{n}"),
+            Self::Mouse(button) => write!(f, "Mouse:{button:?}"),
+            Self::Pad(button) => write!(f, "Pad:{button:?}"),
+        }
+    }
+}
+
+
+impl FromStr for Chord
+{
+    type Err = ThError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err>
+    {
+        let mut chord = Self {
+            modifiers: Modifiers::default(),
+            buttons: vec![],
+        };
+
+        for part in s.split('+').map(str::trim)
+        {
+            match part
+            {
+                "Shift" => chord.modifiers.shift = true,
+                "Ctrl" => chord.modifiers.ctrl = true,
+                "Alt" => chord.modifiers.alt = true,
+                "Super" => chord.modifiers.logo = true,
+                _ => chord.buttons.push(part.parse()?),
+            }
+        }
+
+        if chord.buttons.is_empty()
+        {
+            return Err(ThError::Error(format!(
+                "{s:?} has no button besides modifiers"
+            )));
+        }
+
+        Ok(chord)
+    }
+}
+
+
+impl Display for Chord
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result
+    {
+        let modifiers = [
+            (self.modifiers.shift, "Shift"),
+            (self.modifiers.ctrl, "Ctrl"),
+            (self.modifiers.alt, "Alt"),
+            (self.modifiers.logo, "Super"),
+        ];
+
+        let parts = modifiers
+            .iter()
+            .filter(|(held, _)| *held)
+            .map(|(_, name)| name.to_string())
+            .chain(self.buttons.iter().map(Button::to_string));
+
+        write!(f, "{}", parts.collect::<Vec<_>>().join("+"))
+    }
+}
+
+
+impl FromStr for Analog
+{
+    type Err = ThError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err>
+    {
+        let (inverted, s) = match s.strip_prefix('-')
+        {
+            Some(s) => (true, s),
+            None => (false, s),
+        };
+
+        let axis = s
+            .strip_prefix("Pad:")
+            .ok_or(ThError::Error(format!("{s:?} is not a gamepad axis")))?;
+
+        Ok(Self {
+            axis: variant(axis)?,
+            inverted,
+        })
+    }
+}
+
+
+impl Display for Analog
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result
+    {
+        let sign = if self.inverted { "-" } else { "" };
+        write!(f, "{sign}Pad:{:?}", self.axis)
+    }
+}
+
+
+/// Parses the name of a unit variant, like `Space` for [`Key::Space`].
+fn variant<T: DeserializeOwned>(name: &str) -> ThResult<T>
+{
+    T::deserialize(name.into_deserializer())
+        .map_err(|_: serde::de::value::Error| ThError::Error(format!("Unknown input {name:?}")))
+}
+
+
+macro_rules! string_conversions {
+    ($($t:ty),*) => {
+        $(
+            impl TryFrom<String> for $t
+            {
+                type Error = ThError;
+
+                fn try_from(s: String) -> Result<Self, Self::Error>
+                {
+                    s.parse()
+                }
+            }
+
+            impl From<$t> for String
+            {
+                fn from(v: $t) -> Self
+                {
+                    v.to_string()
+                }
+            }
+        )*
+    };
+}
+
+string_conversions!(Chord, Analog);
+
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn parses_chords()
+    {
+        let chord: Chord = "Ctrl+Shift+S".parse().unwrap();
+        assert_eq!(
+            chord,
+            Chord::new(Key::S).with_modifiers(Modifiers {
+                shift: true,
+                ctrl: true,
+                ..Default::default()
+            })
+        );
+
+        let chord: Chord = "Pad:LeftBumper + Pad:South".parse().unwrap();
+        assert_eq!(
+            chord,
+            Chord::new(GamepadButton::LeftBumper).with(GamepadButton::South)
+        );
+
+        assert_eq!(
+            "Mouse:Left".parse::<Button>().unwrap(),
+            Button::Mouse(MouseButton::Left)
+        );
+        assert_eq!(
+            "Mouse:4".parse::<Button>().unwrap(),
+            Button::Mouse(MouseButton::Other(4))
+        );
+        assert_eq!(
+            "Scancode:30".parse::<Button>().unwrap(),
+            Button::Key(Key::Unknown(30))
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_chords()
+    {
+        assert!("Ctrl+Shift".parse::<Chord>().is_err());
+        assert!("".parse::<Chord>().is_err());
+        assert!("Ctrl+Foo".parse::<Chord>().is_err());
+        assert!("Pad:Left".parse::<Chord>().is_err());
+        assert!("Scancode:abc".parse::<Chord>().is_err());
+    }
+
+    #[test]
+    fn parses_analogs()
+    {
+        let analog: Analog = "-Pad:LeftStickY".parse().unwrap();
+        assert_eq!(analog.axis, GamepadAxis::LeftStickY);
+        assert!(analog.inverted);
+
+        assert_eq!(
+            "Pad:RightTrigger".parse::<Analog>().unwrap(),
+            Analog::from(GamepadAxis::RightTrigger)
+        );
+
+        assert!("LeftStickX".parse::<Analog>().is_err());
+        assert!("Pad:South".parse::<Analog>().is_err());
+    }
+
+    #[test]
+    fn display_round_trips()
+    {
+        let chords = [
+            "S",
+            "Shift+Ctrl+Alt+Super+F5",
+            "Ctrl+Mouse:Left",
+            "Mouse:7",
+            "Pad:LeftBumper+Pad:South",
+            "Alt+Scancode:30",
+        ];
+
+        for text in chords
+        {
+            let chord: Chord = text.parse().unwrap();
+            assert_eq!(chord.to_string(), text);
+            assert_eq!(chord.to_string().parse::<Chord>().unwrap(), chord);
+        }
+
+        for text in ["Pad:LeftStickX", "-Pad:RightTrigger"]
+        {
+            let analog: Analog = text.parse().unwrap();
+            assert_eq!(analog.to_string(), text);
+        }
+
+        // Modifiers are written in a fixed order
+        let chord: Chord = "Ctrl+Shift+S".parse().unwrap();
+        assert_eq!(chord.to_string(), "Shift+Ctrl+S");
+    }
+}
@@ -0,0 +1,231 @@
+use serde::Deserialize;
+
+
+/// A key by its physical position on a US keyboard, independent of the active layout.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+pub enum Key
+{
+    A,
+    B,
+    C,
+    D,
+    E,
+    F,
+    G,
+    H,
+    I,
+    J,
+    K,
+    L,
+    M,
+    N,
+    O,
+    P,
+    Q,
+    R,
+    S,
+    T,
+    U,
+    V,
+    W,
+    X,
+    Y,
+    Z,
+
+    Digit0,
+    Digit1,
+    Digit2,
+    Digit3,
+    Digit4,
+    Digit5,
+    Digit6,
+    Digit7,
+    Digit8,
+    Digit9,
+
+    F1,
+    F2,
+    F3,
+    F4,
+    F5,
+    F6,
+    F7,
+    F8,
+    F9,
+    F10,
+    F11,
+    F12,
+
+    Up,
+    Down,
+    Left,
+    Right,
+
+    Escape,
+    Enter,
+    Space,
+    Tab,
+    Backspace,
+    Delete,
+    Insert,
+    Home,
+    End,
+    PageUp,
+    PageDown,
+    CapsLock,
+    PrintScreen,
+    Pause,
+
+    ShiftLeft,
+    ShiftRight,
+    ControlLeft,
+    ControlRight,
+    AltLeft,
+    AltRight,
+    SuperLeft,
+    SuperRight,
+
+    Backquote,
+    Minus,
+    Equal,
+    BracketLeft,
+    BracketRight,
+    Backslash,
+    Semicolon,
+    Quote,
+    Comma,
+    Period,
+    Slash,
+
+    Numpad0,
+    Numpad1,
+    Numpad2,
+    Numpad3,
+    Numpad4,
+    Numpad5,
+    Numpad6,
+    Numpad7,
+    Numpad8,
+    Numpad9,
+    NumpadAdd,
+    NumpadSubtract,
+    NumpadMultiply,
+    NumpadDivide,
+    NumpadDecimal,
+    NumpadEnter,
+    NumLock,
+
+    /// Any key thorn has no name for. Carries the platform's scancode, if there is one.
+    Unknown(u32),
+}
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+pub enum MouseButton
+{
+    Left,
+    Right,
+    Middle,
+    Back,
+    Forward,
+    Other(u16),
+}
+
+
+/// The modifier keys held down, regardless of which side they are on.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Deserialize)]
+#[serde(default)]
+pub struct Modifiers
+{
+    pub shift: bool,
+    pub ctrl: bool,
+    pub alt: bool,
+    pub logo: bool,
+}
+
+
+impl Modifiers
+{
+    pub fn is_empty(&self) -> bool
+    {
+        *self == Self::default()
+    }
+
+    /// Whether every modifier held in `other` is held here too.
+    pub fn contains(&self, other: Modifiers) -> bool
+    {
+        (self.shift || !other.shift)
+            && (self.ctrl || !other.ctrl)
+            && (self.alt || !other.alt)
+            && (self.logo || !other.logo)
+    }
+
+    /// The modifiers held in either.
+    pub fn union(&self, other: Modifiers) -> Self
+    {
+        Self {
+            shift: self.shift || other.shift,
+            ctrl: self.ctrl || other.ctrl,
+            alt: self.alt || other.alt,
+            logo: self.logo || other.logo,
+        }
+    }
+}
+
+
+impl Key
+{
+    /// The modifier held while this key is down, none for keys that are not modifiers.
+    pub fn modifier(&self) -> Modifiers
+    {
+        let mut modifiers = Modifiers::default();
+
+        match self
+        {
+            Self::ShiftLeft | Self::ShiftRight => modifiers.shift = true,
+            Self::ControlLeft | Self::ControlRight => modifiers.ctrl = true,
+            Self::AltLeft | Self::AltRight => modifiers.alt = true,
+            Self::SuperLeft | Self::SuperRight => modifiers.logo = true,
+            _ => (),
+        }
+
+        modifiers
+    }
+}
+
+
+/// Gamepad buttons by their position on the pad, South being A on an Xbox controller.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+pub enum GamepadButton
+{
+    South,
+    East,
+    North,
+    West,
+    LeftBumper,
+    RightBumper,
+    LeftTrigger,
+    RightTrigger,
+    Select,
+    Start,
+    Mode,
+    LeftStick,
+    RightStick,
+    DPadUp,
+    DPadDown,
+    DPadLeft,
+    DPadRight,
+}
+
+
+/// Sticks go from -1 to 1, with up and right being positive. Triggers go from 0 to 1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+pub enum GamepadAxis
+{
+    LeftStickX,
+    LeftStickY,
+    RightStickX,
+    RightStickY,
+    LeftTrigger,
+    RightTrigger,
+}
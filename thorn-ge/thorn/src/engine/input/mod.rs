@@ -0,0 +1,326 @@
+mod actions;
+mod binding;
+mod keys;
+
+pub use actions::{ActionMap, ActionMapPlugin, AxisBinding, Bindings};
+pub use binding::{Analog, Button, Chord};
+pub use keys::{GamepadAxis, GamepadButton, Key, Modifiers, MouseButton};
+
+
+use crate::engine::platform::WindowId;
+use crate::prelude::*;
+use std::collections::{HashMap, HashSet};
+use std::hash::Hash;
+
+
+pub struct InputPlugin;
+impl Plugin<LayerEvent> for InputPlugin
+{
+    fn info(&self) -> PluginInfo
+    {
+        PluginInfo::build::<Input>()
+            .dep::<EventReceiver<PlatformEvent>>()
+            .dep::<Tasks>()
+    }
+
+    fn load(
+        &mut self,
+        _ctx: &PluginContext<LayerEvent>,
+    ) -> Result<AnyLayer<LayerEvent>, Box<dyn std::error::Error>>
+    {
+        Ok(AnyLayer::new(Input::default()))
+    }
+
+    fn notify_loaded(&mut self, ctx: &PluginContext<LayerEvent>)
+    {
+        let input = ctx.get::<Input>().unwrap();
+
+        let hooked = ctx
+            .subscribe::<PlatformEvent>(input.clone())
+            .and_then(|_| ctx.hook(input));
+
+        if let Err(e) = hooked
+        {
+            log::error!("Input will not be updated: {e}");
+        }
+    }
+}
+
+
+/// Which buttons of one kind are held down, and which changed during the current frame.
+pub(crate) struct Buttons<T>
+{
+    pub(crate) down: HashSet<T>,
+    pub(crate) pressed: HashSet<T>,
+    pub(crate) released: HashSet<T>,
+}
+
+
+impl<T: Copy + Eq + Hash> Buttons<T>
+{
+    pub(crate) fn press(&mut self, button: T)
+    {
+        if self.down.insert(button)
+        {
+            self.pressed.insert(button);
+        }
+    }
+
+    pub(crate) fn release(&mut self, button: T)
+    {
+        if self.down.remove(&button)
+        {
+            self.released.insert(button);
+        }
+    }
+
+    pub(crate) fn release_all(&mut self)
+    {
+        self.released.extend(self.down.drain());
+    }
+
+    pub(crate) fn next_frame(&mut self)
+    {
+        self.pressed.clear();
+        self.released.clear();
+    }
+}
+
+
+impl<T> Default for Buttons<T>
+{
+    fn default() -> Self
+    {
+        Self {
+            down: HashSet::new(),
+            pressed: HashSet::new(),
+            released: HashSet::new(),
+        }
+    }
+}
+
+
+/// Keyboard and mouse state. The platform's input events are collected as they arrive and
+/// applied together at the start of each frame, so the state does not change during a frame.
+#[derive(Default)]
+pub struct Input
+{
+    pending: Vec<PlatformEvent>,
+    keys: Buttons<Key>,
+    buttons: Buttons<MouseButton>,
+    modifiers: Modifiers,
+    cursor: Option<(WindowId, f64, f64)>,
+    scale_factors: HashMap<WindowId, f64>,
+    mouse_delta: (f64, f64),
+    scroll: (f32, f32),
+    text: String,
+    preedit: Option<(String, Option<(usize, usize)>)>,
+}
+
+
+impl Input
+{
+    pub fn is_pressed(&self, key: Key) -> bool
+    {
+        self.keys.down.contains(&key)
+    }
+
+    /// Whether the key went down during the current frame.
+    pub fn just_pressed(&self, key: Key) -> bool
+    {
+        self.keys.pressed.contains(&key)
+    }
+
+    /// Whether the key went up during the current frame.
+    pub fn just_released(&self, key: Key) -> bool
+    {
+        self.keys.released.contains(&key)
+    }
+
+    pub fn is_mouse_pressed(&self, button: MouseButton) -> bool
+    {
+        self.buttons.down.contains(&button)
+    }
+
+    pub fn mouse_just_pressed(&self, button: MouseButton) -> bool
+    {
+        self.buttons.pressed.contains(&button)
+    }
+
+    pub fn mouse_just_released(&self, button: MouseButton) -> bool
+    {
+        self.buttons.released.contains(&button)
+    }
+
+    pub fn pressed_keys(&self) -> impl Iterator<Item = Key> + '_
+    {
+        self.keys.down.iter().copied()
+    }
+
+    pub fn modifiers(&self) -> Modifiers
+    {
+        self.modifiers
+    }
+
+    /// Cursor position in physical pixels relative to the window's top left corner,
+    /// or `None` while the cursor is outside of the window.
+    pub fn cursor(&self) -> Option<(f64, f64)>
+    {
+        self.cursor.map(|(_, x, y)| (x, y))
+    }
+
+    /// Cursor position in logical pixels, for UI laid out independent of the monitor.
+    pub fn cursor_logical(&self) -> Option<(f64, f64)>
+    {
+        self.cursor.map(|(window, x, y)| {
+            let scale = self.scale_factor(window);
+            (x / scale, y / scale)
+        })
+    }
+
+    /// The scale factor of the monitor the window is on.
+    pub fn scale_factor(&self, window: WindowId) -> f64
+    {
+        self.scale_factors.get(&window).copied().unwrap_or(1.0)
+    }
+
+    /// The window the cursor is in
+    pub fn cursor_window(&self) -> Option<WindowId>
+    {
+        self.cursor.map(|(window, ..)| window)
+    }
+
+    /// Raw mouse movement during the current frame. Unlike the cursor, it is not
+    /// affected by acceleration and keeps going at the window's border.
+    pub fn mouse_delta(&self) -> (f64, f64)
+    {
+        self.mouse_delta
+    }
+
+    /// Scroll wheel movement during the current frame, in lines.
+    pub fn scroll(&self) -> (f32, f32)
+    {
+        self.scroll
+    }
+
+    /// Text typed into any window during the current frame, while its text input is enabled
+    /// through [`PlatformWindow::set_text_input`](crate::engine::platform::PlatformWindow::set_text_input).
+    pub fn text(&self) -> &str
+    {
+        &self.text
+    }
+
+    /// Text the IME is composing, with the cursor as a byte range into it.
+    /// It is replaced by committed text once the user is done.
+    pub fn preedit(&self) -> Option<(&str, Option<(usize, usize)>)>
+    {
+        self.preedit
+            .as_ref()
+            .map(|(text, cursor)| (text.as_str(), *cursor))
+    }
+
+    fn next_frame(&mut self)
+    {
+        self.keys.next_frame();
+        self.buttons.next_frame();
+        self.mouse_delta = (0.0, 0.0);
+        self.scroll = (0.0, 0.0);
+        self.text.clear();
+
+        for event in std::mem::take(&mut self.pending)
+        {
+            self.apply(event);
+        }
+    }
+
+    fn apply(&mut self, event: PlatformEvent)
+    {
+        match event
+        {
+            PlatformEvent::KeyPressed(key) => self.keys.press(key),
+            PlatformEvent::KeyReleased(key) => self.keys.release(key),
+            PlatformEvent::MouseButtonPressed(button) => self.buttons.press(button),
+            PlatformEvent::MouseButtonReleased(button) => self.buttons.release(button),
+            PlatformEvent::ModifiersChanged(modifiers) => self.modifiers = modifiers,
+            PlatformEvent::CursorMoved(window, x, y) => self.cursor = Some((window, x, y)),
+
+            // Moving into another window can be reported before leaving the old one
+            PlatformEvent::CursorLeft(window) =>
+            {
+                if self.cursor_window() == Some(window)
+                {
+                    self.cursor = None;
+                }
+            }
+
+            PlatformEvent::MouseMotion(x, y) =>
+            {
+                self.mouse_delta.0 += x;
+                self.mouse_delta.1 += y;
+            }
+
+            PlatformEvent::MouseScroll(x, y) =>
+            {
+                self.scroll.0 += x;
+                self.scroll.1 += y;
+            }
+
+            PlatformEvent::TextCommitted(_, text) => self.text.push_str(&text),
+            PlatformEvent::TextPreedit(_, text, _) if text.is_empty() => self.preedit = None,
+            PlatformEvent::TextPreedit(_, text, cursor) => self.preedit = Some((text, cursor)),
+
+            PlatformEvent::WindowSizeChange(window, size) =>
+            {
+                self.scale_factors.insert(window, size.scale_factor);
+            }
+
+            PlatformEvent::ScaleFactorChanged(window, scale_factor) =>
+            {
+                self.scale_factors.insert(window, scale_factor);
+            }
+
+            // Release events for held keys are not delivered to unfocused windows
+            PlatformEvent::WindowLostFocus(_) =>
+            {
+                self.keys.release_all();
+                self.buttons.release_all();
+                self.modifiers = Modifiers::default();
+            }
+
+            _ => (),
+        }
+    }
+}
+
+
+impl EventSubscriber<PlatformEvent> for Input
+{
+    fn receive_event(&mut self, event: &PlatformEvent)
+    {
+        if event.is_input()
+            || matches!(
+                event,
+                PlatformEvent::WindowLostFocus(_)
+                    | PlatformEvent::WindowSizeChange(..)
+                    | PlatformEvent::ScaleFactorChanged(..)
+            )
+        {
+            self.pending.push(event.clone());
+        }
+    }
+}
+
+
+impl CoreHook for Input
+{
+    fn prepare(&mut self)
+    {
+        self.next_frame();
+    }
+}
+
+
+impl LayerDispatch<LayerEvent> for Input
+{
+    fn dispatch(&mut self, _event: &LayerEvent) {}
+}
@@ -1,6 +1,7 @@
 pub mod core;
 pub mod event;
 pub mod gobject_manager;
+pub mod input;
 pub mod platform;
 pub mod renderer;
 pub mod tasks;
@@ -10,7 +11,15 @@ pub mod prelude
     pub use super::core::{Core, CoreHook, FrameInfo};
     pub use super::event::{EventEmitter, EventReceiver};
     pub use super::gobject_manager::{Gobject, GobjectManager};
-    pub use super::platform::{Platform, PlatformEvent, WindowParams};
-    pub use super::renderer::{Backend, Renderer};
+    pub use super::input::{ActionMap, Input, Key, Modifiers, MouseButton};
+    pub use super::platform::{
+        Gamepads,
+        Platform,
+        PlatformEvent,
+        WindowId,
+        WindowParams,
+        WindowSize,
+    };
+    pub use super::renderer::{Backend, Camera, DrawCommand, Renderer};
     pub use super::tasks::Tasks;
 }
@@ -0,0 +1,240 @@
+use crate::prelude::*;
+use std::sync::{Arc, Mutex};
+
+
+/// An image with 8 bit RGBA pixels, row by row from the top left.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClipboardImage
+{
+    pub width: u32,
+    pub height: u32,
+    pub rgba: Vec<u8>,
+}
+
+
+/// Where [`Platform`] reads and writes the clipboard.
+pub trait ClipboardBackend: Send
+{
+    fn text(&mut self) -> ThResult<String>;
+    fn set_text(&mut self, text: String) -> ThResult<()>;
+
+    fn image(&mut self) -> ThResult<ClipboardImage>
+    {
+        Err(ThError::Error(
+            "This clipboard does not support images".into(),
+        ))
+    }
+
+    fn set_image(&mut self, _image: ClipboardImage) -> ThResult<()>
+    {
+        Err(ThError::Error(
+            "This clipboard does not support images".into(),
+        ))
+    }
+}
+
+
+/// The system clipboard, or an in-memory one if it is not available, like on a headless machine.
+pub fn system_clipboard() -> Box<dyn ClipboardBackend>
+{
+    match SystemClipboard::new()
+    {
+        Ok(clipboard) => Box::new(clipboard),
+        Err(e) =>
+        {
+            log::warn!("Using an in-memory clipboard: {e}");
+            Box::new(MemoryClipboard::default())
+        }
+    }
+}
+
+
+pub struct SystemClipboard(arboard::Clipboard);
+
+
+impl SystemClipboard
+{
+    pub fn new() -> ThResult<Self>
+    {
+        arboard::Clipboard::new()
+            .map(Self)
+            .map_err(|e| ThError::Error(format!("No system clipboard: {e}")))
+    }
+}
+
+
+impl ClipboardBackend for SystemClipboard
+{
+    fn text(&mut self) -> ThResult<String>
+    {
+        self.0.get_text().map_err(clipboard_error)
+    }
+
+    fn set_text(&mut self, text: String) -> ThResult<()>
+    {
+        self.0.set_text(text).map_err(clipboard_error)
+    }
+
+    fn image(&mut self) -> ThResult<ClipboardImage>
+    {
+        let image = self.0.get_image().map_err(clipboard_error)?;
+
+        Ok(ClipboardImage {
+            width: image.width as u32,
+            height: image.height as u32,
+            rgba: image.bytes.into_owned(),
+        })
+    }
+
+    fn set_image(&mut self, image: ClipboardImage) -> ThResult<()>
+    {
+        let image = arboard::ImageData {
+            width: image.width as usize,
+            height: image.height as usize,
+            bytes: image.rgba.into(),
+        };
+
+        self.0.set_image(image).map_err(clipboard_error)
+    }
+}
+
+
+fn clipboard_error(e: arboard::Error) -> ThError
+{
+    ThError::Error(format!("Clipboard: {e}"))
+}
+
+
+#[derive(Debug, Clone)]
+enum Content
+{
+    Text(String),
+    Image(ClipboardImage),
+}
+
+
+/// A clipboard that only lives as long as the application. Clones share their contents,
+/// so a test can keep one to look at what was copied.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryClipboard
+{
+    content: Arc<Mutex<Option<Content>>>,
+}
+
+
+impl ClipboardBackend for MemoryClipboard
+{
+    fn text(&mut self) -> ThResult<String>
+    {
+        match &*self.content.lock().unwrap()
+        {
+            Some(Content::Text(text)) => Ok(text.clone()),
+            Some(Content::Image(_)) => Err(ThError::Error("The clipboard holds an image".into())),
+            None => Err(ThError::Error("The clipboard is empty".into())),
+        }
+    }
+
+    fn set_text(&mut self, text: String) -> ThResult<()>
+    {
+        *self.content.lock().unwrap() = Some(Content::Text(text));
+        Ok(())
+    }
+
+    fn image(&mut self) -> ThResult<ClipboardImage>
+    {
+        match &*self.content.lock().unwrap()
+        {
+            Some(Content::Image(image)) => Ok(image.clone()),
+            Some(Content::Text(_)) => Err(ThError::Error("The clipboard holds text".into())),
+            None => Err(ThError::Error("The clipboard is empty".into())),
+        }
+    }
+
+    fn set_image(&mut self, image: ClipboardImage) -> ThResult<()>
+    {
+        if image.rgba.len() != image.width as usize * image.height as usize * 4
+        {
+            return Err(ThError::Error(
+                "Image size does not match its pixels".into(),
+            ));
+        }
+
+        *self.content.lock().unwrap() = Some(Content::Image(image));
+        Ok(())
+    }
+}
+
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    fn image() -> ClipboardImage
+    {
+        ClipboardImage {
+            width: 2,
+            height: 1,
+            rgba: vec![255, 0, 0, 255, 0, 0, 255, 128],
+        }
+    }
+
+    #[test]
+    fn round_trips_text_and_images()
+    {
+        let mut clipboard = MemoryClipboard::default();
+
+        clipboard.set_text("copied".into()).unwrap();
+        assert_eq!(clipboard.text().unwrap(), "copied");
+
+        clipboard.set_image(image()).unwrap();
+        assert_eq!(clipboard.image().unwrap(), image());
+
+        clipboard.set_text("again".into()).unwrap();
+        assert_eq!(clipboard.text().unwrap(), "again");
+    }
+
+    #[test]
+    fn only_returns_what_it_holds()
+    {
+        let mut clipboard = MemoryClipboard::default();
+        assert!(clipboard.text().is_err());
+        assert!(clipboard.image().is_err());
+
+        clipboard.set_image(image()).unwrap();
+        assert!(clipboard.text().is_err());
+
+        clipboard.set_text("text".into()).unwrap();
+        assert!(clipboard.image().is_err());
+    }
+
+    #[test]
+    fn rejects_images_with_the_wrong_size()
+    {
+        let mut clipboard = MemoryClipboard::default();
+        clipboard.set_text("kept".into()).unwrap();
+
+        let mut wrong = image();
+        wrong.height = 2;
+        assert!(clipboard.set_image(wrong).is_err());
+
+        let mut wrong = image();
+        wrong.rgba.pop();
+        assert!(clipboard.set_image(wrong).is_err());
+
+        assert_eq!(clipboard.text().unwrap(), "kept");
+    }
+
+    #[test]
+    fn clones_share_contents()
+    {
+        let mut clipboard = MemoryClipboard::default();
+        let mut clone = clipboard.clone();
+
+        clipboard.set_text("shared".into()).unwrap();
+        assert_eq!(clone.text().unwrap(), "shared");
+
+        clone.set_image(image()).unwrap();
+        assert_eq!(clipboard.image().unwrap(), image());
+    }
+}
@@ -0,0 +1,659 @@
+use crate::engine::input::{Buttons, GamepadAxis, GamepadButton};
+use crate::msg_thread::MsgThread;
+use crate::prelude::*;
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::{Display, Formatter};
+use std::sync::mpsc::{Receiver, TryRecvError};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+
+/// How long the backend thread waits for gamepad events before it checks for new commands.
+const POLL_INTERVAL: Duration = Duration::from_millis(4);
+
+
+type BackendFactory = Arc<dyn Fn() -> ThResult<Box<dyn GamepadBackend>> + Send + Sync>;
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize)]
+pub struct GamepadId(pub usize);
+
+
+impl Display for GamepadId
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result
+    {
+        write!(f, "Gamepad {}", self.0)
+    }
+}
+
+
+/// Vibration of both motors. Strengths go from 0 to 1.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Rumble
+{
+    /// The low frequency motor
+    pub strong: f32,
+
+    /// The high frequency motor
+    pub weak: f32,
+
+    pub duration: Duration,
+}
+
+
+/// Source of gamepad events. Runs on its own thread, which it is created on.
+pub trait GamepadBackend
+{
+    /// Waits up to `timeout` for gamepad events and appends them to `events`.
+    /// Gamepads that are connected when the backend is created have to be reported as well.
+    fn poll(&mut self, timeout: Duration, events: &mut Vec<PlatformEvent>);
+
+    fn rumble(&mut self, pad: GamepadId, rumble: Rumble) -> ThResult<()>;
+}
+
+
+pub struct GamepadPlugin
+{
+    backend: Option<BackendFactory>,
+}
+
+
+impl GamepadPlugin
+{
+    /// Uses gilrs, if thorn was built with the `gamepad` feature.
+    pub fn new() -> Self
+    {
+        #[cfg(feature = "gamepad")]
+        return Self::with_backend(|| {
+            super::gilrs_backend::GilrsBackend::new().map(|b| Box::new(b) as _)
+        });
+
+        #[cfg(not(feature = "gamepad"))]
+        return Self { backend: None };
+    }
+
+    /// Uses a custom backend, like [`MockGamepads`]. `backend` is called on the
+    /// backend thread every time the plugin is loaded.
+    pub fn with_backend<F>(backend: F) -> Self
+    where
+        F: Fn() -> ThResult<Box<dyn GamepadBackend>> + Send + Sync + 'static,
+    {
+        Self {
+            backend: Some(Arc::new(backend)),
+        }
+    }
+}
+
+
+impl Default for GamepadPlugin
+{
+    fn default() -> Self
+    {
+        Self::new()
+    }
+}
+
+
+impl Plugin<LayerEvent> for GamepadPlugin
+{
+    fn info(&self) -> PluginInfo
+    {
+        PluginInfo::build::<Gamepads>()
+            .dep::<EventReceiver<PlatformEvent>>()
+            .dep::<Tasks>()
+    }
+
+    fn load(
+        &mut self,
+        ctx: &PluginContext<LayerEvent>,
+    ) -> Result<AnyLayer<LayerEvent>, Box<dyn std::error::Error>>
+    {
+        let emitter = ctx
+            .get::<EventEmitter<PlatformEvent>>()
+            .ok_or(ThError::Error(
+                "Failed to fetch platform event emitter".into(),
+            ))?;
+
+        let backend = match &self.backend
+        {
+            Some(factory) =>
+            {
+                let factory = factory.clone();
+                Some(MsgThread::new(move |msg| {
+                    backend_thread(factory, emitter, msg)
+                }))
+            }
+
+            None =>
+            {
+                log::info!(
+                    "Thorn was built without the gamepad feature, gamepads are not supported"
+                );
+                None
+            }
+        };
+
+        Ok(AnyLayer::new(Gamepads {
+            backend,
+            pending: vec![],
+            pads: BTreeMap::new(),
+        }))
+    }
+
+    fn notify_loaded(&mut self, ctx: &PluginContext<LayerEvent>)
+    {
+        let pads = ctx.get::<Gamepads>().unwrap();
+
+        let hooked = ctx
+            .subscribe::<PlatformEvent>(pads.clone())
+            .and_then(|_| ctx.hook(pads));
+
+        if let Err(e) = hooked
+        {
+            log::error!("Gamepads will not be updated: {e}");
+        }
+    }
+
+    fn notify_unloaded(&mut self, ctx: &PluginContext<LayerEvent>)
+    {
+        ctx.get::<Gamepads>().unwrap().write().unwrap().stop();
+    }
+}
+
+
+enum GamepadMsg
+{
+    Rumble(GamepadId, Rumble),
+    Stop,
+}
+
+
+fn backend_thread(
+    factory: BackendFactory,
+    emitter: Layer<EventEmitter<PlatformEvent>>,
+    msg: Receiver<GamepadMsg>,
+)
+{
+    let mut backend = match factory()
+    {
+        Ok(b) => b,
+        Err(e) =>
+        {
+            log::warn!("Gamepads are not available: {e}");
+            return;
+        }
+    };
+
+    let mut events = vec![];
+
+    loop
+    {
+        loop
+        {
+            match msg.try_recv()
+            {
+                Ok(GamepadMsg::Rumble(pad, rumble)) =>
+                {
+                    if let Err(e) = backend.rumble(pad, rumble)
+                    {
+                        log::warn!("Failed to rumble {pad}: {e}");
+                    }
+                }
+
+                Err(TryRecvError::Empty) => break,
+                Ok(GamepadMsg::Stop) | Err(TryRecvError::Disconnected) => return,
+            }
+        }
+
+        backend.poll(POLL_INTERVAL, &mut events);
+
+        if !events.is_empty()
+        {
+            let mut emitter = emitter.write().unwrap();
+            events.drain(..).for_each(|e| emitter.emit(e));
+        }
+    }
+}
+
+
+struct Pad
+{
+    name: String,
+    buttons: Buttons<GamepadButton>,
+    axes: HashMap<GamepadAxis, f32>,
+}
+
+
+/// State of all connected gamepads. Like [`Input`], the state is updated at the start of each frame.
+pub struct Gamepads
+{
+    backend: Option<MsgThread<GamepadMsg, ()>>,
+    pending: Vec<PlatformEvent>,
+    pads: BTreeMap<GamepadId, Pad>,
+}
+
+
+impl Gamepads
+{
+    pub fn connected(&self) -> impl Iterator<Item = GamepadId> + '_
+    {
+        self.pads.keys().copied()
+    }
+
+    pub fn is_connected(&self, pad: GamepadId) -> bool
+    {
+        self.pads.contains_key(&pad)
+    }
+
+    pub fn name(&self, pad: GamepadId) -> Option<&str>
+    {
+        self.pads.get(&pad).map(|p| p.name.as_str())
+    }
+
+    pub fn is_pressed(&self, pad: GamepadId, button: GamepadButton) -> bool
+    {
+        self.pads
+            .get(&pad)
+            .is_some_and(|p| p.buttons.down.contains(&button))
+    }
+
+    /// Whether the button went down during the current frame.
+    pub fn just_pressed(&self, pad: GamepadId, button: GamepadButton) -> bool
+    {
+        self.pads
+            .get(&pad)
+            .is_some_and(|p| p.buttons.pressed.contains(&button))
+    }
+
+    /// Whether the button went up during the current frame.
+    pub fn just_released(&self, pad: GamepadId, button: GamepadButton) -> bool
+    {
+        self.pads
+            .get(&pad)
+            .is_some_and(|p| p.buttons.released.contains(&button))
+    }
+
+    pub fn axis(&self, pad: GamepadId, axis: GamepadAxis) -> f32
+    {
+        self.pads
+            .get(&pad)
+            .and_then(|p| p.axes.get(&axis))
+            .copied()
+            .unwrap_or(0.0)
+    }
+
+    /// Whether the button is held on any gamepad.
+    pub fn any_pressed(&self, button: GamepadButton) -> bool
+    {
+        self.pads.values().any(|p| p.buttons.down.contains(&button))
+    }
+
+    /// The axis of the gamepad that moves it the furthest.
+    pub fn any_axis(&self, axis: GamepadAxis) -> f32
+    {
+        self.pads
+            .keys()
+            .map(|pad| self.axis(*pad, axis))
+            .fold(0.0, |a, b| if b.abs() > a.abs() { b } else { a })
+    }
+
+    pub fn rumble(&self, pad: GamepadId, rumble: Rumble) -> ThResult<()>
+    {
+        if !self.is_connected(pad)
+        {
+            return Err(ThError::Error(format!("{pad} is not connected")));
+        }
+
+        match &self.backend
+        {
+            Some(backend) if !backend.is_finished() =>
+            {
+                backend.msg(GamepadMsg::Rumble(pad, rumble));
+                Ok(())
+            }
+
+            _ => Err(ThError::Error("No gamepad backend is running".into())),
+        }
+    }
+
+    fn stop(&mut self)
+    {
+        if let Some(backend) = self.backend.take()
+        {
+            backend.msg(GamepadMsg::Stop);
+            let _ = backend.join();
+        }
+    }
+
+    fn next_frame(&mut self)
+    {
+        for pad in self.pads.values_mut()
+        {
+            pad.buttons.next_frame();
+        }
+
+        for event in std::mem::take(&mut self.pending)
+        {
+            self.apply(event);
+        }
+    }
+
+    fn apply(&mut self, event: PlatformEvent)
+    {
+        match event
+        {
+            PlatformEvent::GamepadConnected(pad, name) =>
+            {
+                log::info!("{pad} connected: {name}");
+                self.pads.insert(
+                    pad,
+                    Pad {
+                        name,
+                        buttons: Buttons::default(),
+                        axes: HashMap::new(),
+                    },
+                );
+            }
+
+            PlatformEvent::GamepadDisconnected(pad) =>
+            {
+                log::info!("{pad} disconnected");
+                self.pads.remove(&pad);
+            }
+
+            PlatformEvent::GamepadPressed(pad, button) =>
+            {
+                if let Some(p) = self.pads.get_mut(&pad)
+                {
+                    p.buttons.press(button);
+                }
+            }
+
+            PlatformEvent::GamepadReleased(pad, button) =>
+            {
+                if let Some(p) = self.pads.get_mut(&pad)
+                {
+                    p.buttons.release(button);
+                }
+            }
+
+            PlatformEvent::GamepadAxisChanged(pad, axis, value) =>
+            {
+                if let Some(p) = self.pads.get_mut(&pad)
+                {
+                    p.axes.insert(axis, value);
+                }
+            }
+
+            _ => (),
+        }
+    }
+}
+
+
+impl Drop for Gamepads
+{
+    fn drop(&mut self)
+    {
+        self.stop();
+    }
+}
+
+
+impl EventSubscriber<PlatformEvent> for Gamepads
+{
+    fn receive_event(&mut self, event: &PlatformEvent)
+    {
+        if event.is_gamepad()
+        {
+            self.pending.push(event.clone());
+        }
+    }
+}
+
+
+impl CoreHook for Gamepads
+{
+    fn prepare(&mut self)
+    {
+        self.next_frame();
+    }
+}
+
+
+impl LayerDispatch<LayerEvent> for Gamepads
+{
+    fn dispatch(&mut self, _event: &LayerEvent) {}
+}
+
+
+#[derive(Default)]
+struct MockState
+{
+    next_id: usize,
+    connected: Vec<GamepadId>,
+    events: Vec<PlatformEvent>,
+    rumbles: Vec<(GamepadId, Rumble)>,
+}
+
+
+/// A gamepad backend driven by hand, for tests and for playing back recorded input.
+/// Clones share their state, so one clone can be handed to [`GamepadPlugin::with_backend`]
+/// while another one presses buttons.
+#[derive(Clone, Default)]
+pub struct MockGamepads(Arc<Mutex<MockState>>);
+
+
+impl MockGamepads
+{
+    pub fn new() -> Self
+    {
+        Self::default()
+    }
+
+    pub fn connect(&self, name: impl Into<String>) -> GamepadId
+    {
+        let mut state = self.0.lock().unwrap();
+
+        let pad = GamepadId(state.next_id);
+        state.next_id += 1;
+        state.connected.push(pad);
+        state
+            .events
+            .push(PlatformEvent::GamepadConnected(pad, name.into()));
+
+        pad
+    }
+
+    pub fn disconnect(&self, pad: GamepadId)
+    {
+        let mut state = self.0.lock().unwrap();
+        state.connected.retain(|p| *p != pad);
+        state.events.push(PlatformEvent::GamepadDisconnected(pad));
+    }
+
+    pub fn press(&self, pad: GamepadId, button: GamepadButton)
+    {
+        self.push(PlatformEvent::GamepadPressed(pad, button));
+    }
+
+    pub fn release(&self, pad: GamepadId, button: GamepadButton)
+    {
+        self.push(PlatformEvent::GamepadReleased(pad, button));
+    }
+
+    pub fn set_axis(&self, pad: GamepadId, axis: GamepadAxis, value: f32)
+    {
+        self.push(PlatformEvent::GamepadAxisChanged(pad, axis, value));
+    }
+
+    /// Takes all rumbles that were requested so far.
+    pub fn take_rumbles(&self) -> Vec<(GamepadId, Rumble)>
+    {
+        std::mem::take(&mut self.0.lock().unwrap().rumbles)
+    }
+
+    fn push(&self, event: PlatformEvent)
+    {
+        self.0.lock().unwrap().events.push(event);
+    }
+}
+
+
+impl GamepadBackend for MockGamepads
+{
+    fn poll(&mut self, timeout: Duration, events: &mut Vec<PlatformEvent>)
+    {
+        events.append(&mut self.0.lock().unwrap().events);
+
+        if events.is_empty()
+        {
+            std::thread::sleep(timeout);
+        }
+    }
+
+    fn rumble(&mut self, pad: GamepadId, rumble: Rumble) -> ThResult<()>
+    {
+        let mut state = self.0.lock().unwrap();
+
+        if !state.connected.contains(&pad)
+        {
+            return Err(ThError::Error(format!("{pad} is not connected")));
+        }
+
+        state.rumbles.push((pad, rumble));
+        Ok(())
+    }
+}
+
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use std::time::Instant;
+
+    fn gamepads(mock: &MockGamepads) -> (Gamepads, Layer<EventEmitter<PlatformEvent>>)
+    {
+        let emitter = Layer::new(EventEmitter::new());
+        let mock = mock.clone();
+        let factory: BackendFactory = Arc::new(move || Ok(Box::new(mock.clone()) as _));
+
+        let backend_emitter = emitter.clone();
+        let backend = MsgThread::new(move |msg| backend_thread(factory, backend_emitter, msg));
+
+        let pads = Gamepads {
+            backend: Some(backend),
+            pending: vec![],
+            pads: BTreeMap::new(),
+        };
+
+        (pads, emitter)
+    }
+
+    // Waits until the backend thread emitted `count` events, then starts a new frame with them
+    fn frame(pads: &mut Gamepads, emitter: &Layer<EventEmitter<PlatformEvent>>, count: usize)
+    {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let mut events = vec![];
+
+        while events.len() < count && Instant::now() < deadline
+        {
+            emitter.write().unwrap().drain_into(&mut events);
+            std::thread::sleep(POLL_INTERVAL);
+        }
+
+        assert_eq!(events.len(), count, "{events:?}");
+        events.iter().for_each(|e| pads.receive_event(e));
+        pads.prepare();
+    }
+
+    fn rumble() -> Rumble
+    {
+        Rumble {
+            strong: 1.0,
+            weak: 0.5,
+            duration: Duration::from_millis(100),
+        }
+    }
+
+    #[test]
+    fn tracks_buttons_across_frames()
+    {
+        let mock = MockGamepads::new();
+        let (mut pads, emitter) = gamepads(&mock);
+
+        let pad = mock.connect("Mock Pad");
+        frame(&mut pads, &emitter, 1);
+
+        assert!(pads.is_connected(pad));
+        assert_eq!(pads.name(pad), Some("Mock Pad"));
+
+        mock.press(pad, GamepadButton::South);
+        mock.set_axis(pad, GamepadAxis::LeftStickX, -0.5);
+
+        // Nothing changes until the next frame
+        assert!(!pads.is_pressed(pad, GamepadButton::South));
+        frame(&mut pads, &emitter, 2);
+
+        assert!(pads.is_pressed(pad, GamepadButton::South));
+        assert!(pads.just_pressed(pad, GamepadButton::South));
+        assert!(pads.any_pressed(GamepadButton::South));
+        assert_eq!(pads.any_axis(GamepadAxis::LeftStickX), -0.5);
+
+        frame(&mut pads, &emitter, 0);
+        assert!(pads.is_pressed(pad, GamepadButton::South));
+        assert!(!pads.just_pressed(pad, GamepadButton::South));
+
+        mock.release(pad, GamepadButton::South);
+        frame(&mut pads, &emitter, 1);
+
+        assert!(!pads.is_pressed(pad, GamepadButton::South));
+        assert!(pads.just_released(pad, GamepadButton::South));
+
+        mock.disconnect(pad);
+        frame(&mut pads, &emitter, 1);
+
+        assert!(!pads.is_connected(pad));
+        assert_eq!(pads.connected().count(), 0);
+        assert_eq!(pads.axis(pad, GamepadAxis::LeftStickX), 0.0);
+    }
+
+    #[test]
+    fn forwards_rumbles_to_connected_pads()
+    {
+        let mock = MockGamepads::new();
+        let (mut pads, emitter) = gamepads(&mock);
+
+        let pad = mock.connect("Mock Pad");
+        let other = mock.connect("Other Pad");
+        frame(&mut pads, &emitter, 2);
+
+        pads.rumble(pad, rumble()).unwrap();
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let mut rumbles = vec![];
+
+        while rumbles.is_empty() && Instant::now() < deadline
+        {
+            rumbles = mock.take_rumbles();
+            std::thread::sleep(POLL_INTERVAL);
+        }
+
+        assert_eq!(rumbles, [(pad, rumble())]);
+        assert!(mock.take_rumbles().is_empty());
+
+        mock.disconnect(other);
+        frame(&mut pads, &emitter, 1);
+
+        assert!(pads.rumble(other, rumble()).is_err());
+        assert!(pads.rumble(GamepadId(7), rumble()).is_err());
+
+        // Without a backend there is nothing to rumble
+        pads.stop();
+        assert!(pads.rumble(pad, rumble()).is_err());
+        assert!(mock.take_rumbles().is_empty());
+    }
+}
@@ -0,0 +1,191 @@
+use super::gamepad::{GamepadBackend, GamepadId, Rumble};
+use crate::engine::input::{GamepadAxis, GamepadButton};
+use crate::prelude::*;
+use gilrs::ff::{BaseEffect, BaseEffectType, Effect, EffectBuilder, Replay, Ticks};
+use gilrs::{Axis, Button, EventType, Gilrs};
+use std::collections::HashMap;
+use std::time::Duration;
+
+
+pub struct GilrsBackend
+{
+    gilrs: Gilrs,
+    ids: HashMap<GamepadId, gilrs::GamepadId>,
+
+    // Effects stop playing when they are dropped
+    effects: HashMap<GamepadId, Effect>,
+
+    // Gamepads that were connected before the backend was created
+    initial: Vec<PlatformEvent>,
+}
+
+
+impl GilrsBackend
+{
+    pub fn new() -> ThResult<Self>
+    {
+        let gilrs = Gilrs::new().map_err(|e| ThError::Error(e.to_string()))?;
+        let mut ids = HashMap::new();
+        let mut initial = vec![];
+
+        for (id, pad) in gilrs.gamepads()
+        {
+            let pad_id = GamepadId(id.into());
+            ids.insert(pad_id, id);
+            initial.push(PlatformEvent::GamepadConnected(pad_id, pad.name().into()));
+        }
+
+        Ok(Self {
+            gilrs,
+            ids,
+            effects: HashMap::new(),
+            initial,
+        })
+    }
+
+    fn convert(&mut self, event: gilrs::Event) -> Option<PlatformEvent>
+    {
+        let pad = GamepadId(event.id.into());
+
+        Some(match event.event
+        {
+            EventType::Connected =>
+            {
+                self.ids.insert(pad, event.id);
+                let name = self.gilrs.gamepad(event.id).name().to_string();
+                PlatformEvent::GamepadConnected(pad, name)
+            }
+
+            EventType::Disconnected =>
+            {
+                self.ids.remove(&pad);
+                self.effects.remove(&pad);
+                PlatformEvent::GamepadDisconnected(pad)
+            }
+
+            EventType::ButtonPressed(button, _) =>
+            {
+                PlatformEvent::GamepadPressed(pad, button_from(button)?)
+            }
+            EventType::ButtonReleased(button, _) =>
+            {
+                PlatformEvent::GamepadReleased(pad, button_from(button)?)
+            }
+
+            // Analog triggers are reported as buttons with a value
+            EventType::ButtonChanged(Button::LeftTrigger2, value, _) =>
+            {
+                PlatformEvent::GamepadAxisChanged(pad, GamepadAxis::LeftTrigger, value)
+            }
+
+            EventType::ButtonChanged(Button::RightTrigger2, value, _) =>
+            {
+                PlatformEvent::GamepadAxisChanged(pad, GamepadAxis::RightTrigger, value)
+            }
+
+            EventType::AxisChanged(axis, value, _) =>
+            {
+                PlatformEvent::GamepadAxisChanged(pad, axis_from(axis)?, value)
+            }
+
+            _ => return None,
+        })
+    }
+}
+
+
+impl GamepadBackend for GilrsBackend
+{
+    fn poll(&mut self, timeout: Duration, events: &mut Vec<PlatformEvent>)
+    {
+        events.append(&mut self.initial);
+
+        let mut next = self.gilrs.next_event_blocking(Some(timeout));
+        while let Some(event) = next
+        {
+            events.extend(self.convert(event));
+            next = self.gilrs.next_event();
+        }
+    }
+
+    fn rumble(&mut self, pad: GamepadId, rumble: Rumble) -> ThResult<()>
+    {
+        let id = *self
+            .ids
+            .get(&pad)
+            .ok_or(ThError::Error(format!("{pad} is not connected")))?;
+
+        let magnitude = |strength: f32| (strength.clamp(0.0, 1.0) * u16::MAX as f32) as u16;
+        let scheduling = Replay {
+            play_for: Ticks::from_ms(rumble.duration.as_millis() as u32),
+            ..Default::default()
+        };
+
+        let effect = EffectBuilder::new()
+            .add_effect(BaseEffect {
+                kind: BaseEffectType::Strong {
+                    magnitude: magnitude(rumble.strong),
+                },
+                scheduling,
+                ..Default::default()
+            })
+            .add_effect(BaseEffect {
+                kind: BaseEffectType::Weak {
+                    magnitude: magnitude(rumble.weak),
+                },
+                scheduling,
+                ..Default::default()
+            })
+            .repeat(gilrs::ff::Repeat::For(scheduling.play_for))
+            .gamepads(&[id])
+            .finish(&mut self.gilrs)
+            .map_err(|e| ThError::Error(e.to_string()))?;
+
+        effect.play().map_err(|e| ThError::Error(e.to_string()))?;
+
+        // Replaces and stops the previous rumble
+        self.effects.insert(pad, effect);
+        Ok(())
+    }
+}
+
+
+fn button_from(button: Button) -> Option<GamepadButton>
+{
+    Some(match button
+    {
+        Button::South => GamepadButton::South,
+        Button::East => GamepadButton::East,
+        Button::North => GamepadButton::North,
+        Button::West => GamepadButton::West,
+        Button::LeftTrigger => GamepadButton::LeftBumper,
+        Button::RightTrigger => GamepadButton::RightBumper,
+        Button::LeftTrigger2 => GamepadButton::LeftTrigger,
+        Button::RightTrigger2 => GamepadButton::RightTrigger,
+        Button::Select => GamepadButton::Select,
+        Button::Start => GamepadButton::Start,
+        Button::Mode => GamepadButton::Mode,
+        Button::LeftThumb => GamepadButton::LeftStick,
+        Button::RightThumb => GamepadButton::RightStick,
+        Button::DPadUp => GamepadButton::DPadUp,
+        Button::DPadDown => GamepadButton::DPadDown,
+        Button::DPadLeft => GamepadButton::DPadLeft,
+        Button::DPadRight => GamepadButton::DPadRight,
+        _ => return None,
+    })
+}
+
+
+fn axis_from(axis: Axis) -> Option<GamepadAxis>
+{
+    Some(match axis
+    {
+        Axis::LeftStickX => GamepadAxis::LeftStickX,
+        Axis::LeftStickY => GamepadAxis::LeftStickY,
+        Axis::RightStickX => GamepadAxis::RightStickX,
+        Axis::RightStickY => GamepadAxis::RightStickY,
+        Axis::LeftZ => GamepadAxis::LeftTrigger,
+        Axis::RightZ => GamepadAxis::RightTrigger,
+        _ => return None,
+    })
+}
@@ -0,0 +1,531 @@
+use super::{
+    ClipboardBackend,
+    CursorGrab,
+    MemoryClipboard,
+    PlatformBackend,
+    PlatformMsg,
+    WindowCmd,
+    WindowId,
+    WindowSize,
+};
+use crate::prelude::*;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+
+/// What is known about a window that is not shown anywhere.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HeadlessWindow
+{
+    pub params: WindowParams,
+    pub scale_factor: f64,
+    pub minimized: bool,
+    pub cursor_visible: bool,
+    pub cursor_grab: CursorGrab,
+    pub text_input: bool,
+}
+
+
+impl HeadlessWindow
+{
+    /// The inner size in physical pixels. The params hold the logical size.
+    pub fn size(&self) -> WindowSize
+    {
+        let (w, h) = self.params.size;
+        WindowSize::from_logical(w as f64, h as f64, self.scale_factor)
+    }
+
+    fn new(params: WindowParams) -> Self
+    {
+        Self {
+            params,
+            scale_factor: 1.0,
+            minimized: false,
+            cursor_visible: true,
+            cursor_grab: CursorGrab::None,
+            text_input: false,
+        }
+    }
+}
+
+
+// A platform event and the frame to emit it on, as written in a script
+#[derive(Deserialize)]
+struct ScriptedEvent
+{
+    frame: u64,
+    event: PlatformEvent,
+}
+
+
+#[derive(Deserialize)]
+struct Script
+{
+    #[serde(default)]
+    event: Vec<ScriptedEvent>,
+}
+
+
+#[derive(Default)]
+struct HeadlessState
+{
+    emitter: Option<Layer<EventEmitter<PlatformEvent>>>,
+    windows: BTreeMap<WindowId, HeadlessWindow>,
+
+    // Events emitted before the platform layer was loaded
+    pending: Vec<PlatformEvent>,
+
+    // Events waiting for their frame, in order
+    scheduled: Vec<(u64, PlatformEvent)>,
+    frame: u64,
+    terminated: bool,
+}
+
+
+impl HeadlessState
+{
+    fn emit(&mut self, event: PlatformEvent)
+    {
+        match &self.emitter
+        {
+            Some(emitter) => emitter.write().unwrap().emit(event),
+            None => self.pending.push(event),
+        }
+    }
+
+    fn open(&mut self, id: WindowId, params: WindowParams)
+    {
+        let window = HeadlessWindow::new(params);
+        let size = window.size();
+
+        self.windows.insert(id, window);
+        self.emit(PlatformEvent::WindowOpen(id));
+        self.emit(PlatformEvent::WindowSizeChange(id, size));
+    }
+
+    fn control(&mut self, id: WindowId, cmd: WindowCmd)
+    {
+        let Some(win) = self.windows.get_mut(&id)
+        else
+        {
+            log::warn!("Ignoring a command for {id}, which is not open");
+            return;
+        };
+
+        let event = match cmd
+        {
+            WindowCmd::SetTitle(title) =>
+            {
+                win.params.title = title;
+                None
+            }
+
+            WindowCmd::Resize(w, h) if win.params.size != (w, h) =>
+            {
+                win.params.size = (w, h);
+                Some(PlatformEvent::WindowSizeChange(id, win.size()))
+            }
+
+            WindowCmd::Move(x, y) if win.params.position != Some((x, y)) =>
+            {
+                win.params.position = Some((x, y));
+
+                let scale = |v: i32| (v as f64 * win.scale_factor).round() as i32;
+                Some(PlatformEvent::WindowPositionChange(id, scale(x), scale(y)))
+            }
+
+            WindowCmd::SetFullscreen(mode) =>
+            {
+                win.params.fullscreen = mode;
+                None
+            }
+
+            WindowCmd::SetMinimized(minimized) if win.minimized != minimized =>
+            {
+                win.minimized = minimized;
+                Some(match minimized
+                {
+                    true => PlatformEvent::WindowMinimized(id),
+                    false => PlatformEvent::WindowRestored(id),
+                })
+            }
+
+            WindowCmd::SetMaximized(maximized) =>
+            {
+                win.params.maximized = maximized;
+                None
+            }
+
+            WindowCmd::SetVsync(vsync) =>
+            {
+                win.params.vsync = vsync;
+                None
+            }
+
+            WindowCmd::SetCursorVisible(visible) =>
+            {
+                win.cursor_visible = visible;
+                None
+            }
+
+            WindowCmd::SetCursorGrab(grab) =>
+            {
+                win.cursor_grab = grab;
+                None
+            }
+
+            WindowCmd::SetTextInput(enabled) =>
+            {
+                win.text_input = enabled;
+                None
+            }
+
+            _ => None,
+        };
+
+        if let Some(event) = event
+        {
+            self.emit(event);
+        }
+    }
+}
+
+
+/// A platform without a windowing system, for servers and CI. Windows only exist as state:
+/// commands change it and report the same events a real window would, like
+/// [`PlatformEvent::WindowSizeChange`] after a resize. Everything else a user would do comes
+/// from [`Self::emit`], or from a script played back frame by frame.
+///
+/// Clones share their state, so one clone can be handed to [`PlatformPlugin::new`](super::PlatformPlugin::new)
+/// while another one drives it.
+#[derive(Clone)]
+pub struct HeadlessPlatform(Arc<Mutex<HeadlessState>>);
+
+
+impl HeadlessPlatform
+{
+    /// The main window is opened with `params` once the platform layer loads.
+    pub fn new(params: WindowParams) -> Self
+    {
+        let mut state = HeadlessState::default();
+        state.open(WindowId::MAIN, params);
+
+        Self(Arc::new(Mutex::new(state)))
+    }
+
+    /// Emits an event right away, or once the platform layer loads.
+    pub fn emit(&self, event: PlatformEvent)
+    {
+        self.0.lock().unwrap().emit(event);
+    }
+
+    /// Emits an event at the start of a frame, counted from 0. Events for frames that
+    /// already passed go out on the next one.
+    pub fn at_frame(&self, frame: u64, event: PlatformEvent)
+    {
+        let mut state = self.0.lock().unwrap();
+        let index = state.scheduled.partition_point(|(f, _)| *f <= frame);
+        state.scheduled.insert(index, (frame, event));
+    }
+
+    /// Schedules the events of a TOML script, with one entry per event:
+    ///
+    /// ```toml
+    /// [[event]]
+    /// frame = 60
+    /// event = { KeyPressed = "Space" }
+    ///
+    /// [[event]]
+    /// frame = 120
+    /// event = { WindowClose = 0 }
+    /// ```
+    pub fn load_script(&self, path: impl AsRef<Path>) -> ThResult<()>
+    {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| ThError::Error(format!("Failed to read script {path:?}: {e}")))?;
+
+        let script: Script = toml::from_str(&text)
+            .map_err(|e| ThError::Error(format!("Invalid script {path:?}: {}", e.message())))?;
+
+        for ScriptedEvent { frame, event } in script.event
+        {
+            self.at_frame(frame, event);
+        }
+
+        Ok(())
+    }
+
+    /// The frame that starts next.
+    pub fn frame(&self) -> u64
+    {
+        self.0.lock().unwrap().frame
+    }
+
+    /// Moves the window to a monitor with a different scale factor. Like on most platforms,
+    /// the window keeps its logical size, so its physical size changes.
+    pub fn set_scale_factor(&self, id: WindowId, scale_factor: f64)
+    {
+        let mut state = self.0.lock().unwrap();
+
+        let Some(win) = state.windows.get_mut(&id)
+        else
+        {
+            return;
+        };
+
+        win.scale_factor = scale_factor;
+        let size = win.size();
+
+        state.emit(PlatformEvent::ScaleFactorChanged(id, scale_factor));
+        state.emit(PlatformEvent::WindowSizeChange(id, size));
+    }
+
+    pub fn window(&self, id: WindowId) -> Option<HeadlessWindow>
+    {
+        self.0.lock().unwrap().windows.get(&id).cloned()
+    }
+
+    /// Whether the platform layer was unloaded.
+    pub fn is_terminated(&self) -> bool
+    {
+        self.0.lock().unwrap().terminated
+    }
+}
+
+
+impl PlatformBackend for HeadlessPlatform
+{
+    fn send(&self, msg: PlatformMsg)
+    {
+        let mut state = self.0.lock().unwrap();
+
+        match msg
+        {
+            PlatformMsg::Terminate => state.terminated = true,
+            PlatformMsg::OpenWindow(id, params) => state.open(id, params),
+
+            PlatformMsg::CloseWindow(WindowId::MAIN) =>
+            {
+                log::warn!("The main window closes when the application terminates");
+            }
+
+            PlatformMsg::CloseWindow(id) =>
+            {
+                if state.windows.remove(&id).is_some()
+                {
+                    log::info!("{id} closed");
+                }
+            }
+
+            PlatformMsg::Window(id, cmd) => state.control(id, cmd),
+        }
+    }
+
+    fn attach(&self, emitter: Layer<EventEmitter<PlatformEvent>>)
+    {
+        let mut state = self.0.lock().unwrap();
+        let pending = std::mem::take(&mut state.pending);
+
+        {
+            let mut emitter = emitter.write().unwrap();
+            pending.into_iter().for_each(|event| emitter.emit(event));
+        }
+
+        state.emitter = Some(emitter);
+    }
+
+    fn next_frame(&self)
+    {
+        let mut state = self.0.lock().unwrap();
+
+        let frame = state.frame;
+        let due = state.scheduled.partition_point(|(f, _)| *f <= frame);
+        let events = state.scheduled.drain(..due).collect::<Vec<_>>();
+
+        for (_, event) in events
+        {
+            state.emit(event);
+        }
+
+        state.frame += 1;
+    }
+
+    fn clipboard(&self) -> Box<dyn ClipboardBackend>
+    {
+        Box::new(MemoryClipboard::default())
+    }
+}
+
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::engine::input::Key;
+
+    fn attached(platform: &HeadlessPlatform) -> Layer<EventEmitter<PlatformEvent>>
+    {
+        let emitter = Layer::new(EventEmitter::new());
+        platform.attach(emitter.clone());
+        emitter
+    }
+
+    fn drain(emitter: &Layer<EventEmitter<PlatformEvent>>) -> Vec<PlatformEvent>
+    {
+        let mut events = vec![];
+        emitter.write().unwrap().drain_into(&mut events);
+        events
+    }
+
+    #[test]
+    fn plays_back_scripts()
+    {
+        let script = r#"
+            [[event]]
+            frame = 2
+            event = { KeyReleased = "Space" }
+
+            [[event]]
+            frame = 0
+            event = { MouseScroll = [0.0, 1.0] }
+
+            [[event]]
+            frame = 2
+            event = { KeyPressed = "Space" }
+
+            [[event]]
+            frame = 4
+            event = { WindowClose = 0 }
+        "#;
+
+        let path = std::env::temp_dir().join(format!("thorn-script-{}.toml", std::process::id()));
+        std::fs::write(&path, script).unwrap();
+
+        let platform = HeadlessPlatform::new(WindowParams::default());
+        let loaded = platform.load_script(&path);
+        let _ = std::fs::remove_file(&path);
+        loaded.unwrap();
+
+        // Events from before the layer loaded are delivered once it attaches
+        let emitter = attached(&platform);
+        assert_eq!(
+            drain(&emitter),
+            [
+                PlatformEvent::WindowOpen(WindowId::MAIN),
+                PlatformEvent::WindowSizeChange(WindowId::MAIN, WindowSize::new(800, 600, 1.0)),
+            ]
+        );
+
+        let mut frames = vec![];
+        for _ in 0..5
+        {
+            platform.next_frame();
+            frames.push(drain(&emitter));
+        }
+
+        // Events of the same frame keep the script's order
+        assert_eq!(
+            frames,
+            [
+                vec![PlatformEvent::MouseScroll(0.0, 1.0)],
+                vec![],
+                vec![
+                    PlatformEvent::KeyReleased(Key::Space),
+                    PlatformEvent::KeyPressed(Key::Space),
+                ],
+                vec![],
+                vec![PlatformEvent::WindowClose(WindowId::MAIN)],
+            ]
+        );
+
+        assert_eq!(platform.frame(), 5);
+
+        // Late events go out on the next frame
+        platform.at_frame(1, PlatformEvent::MouseMotion(1.0, 2.0));
+        platform.next_frame();
+        assert_eq!(drain(&emitter), [PlatformEvent::MouseMotion(1.0, 2.0)]);
+    }
+
+    #[test]
+    fn rejects_invalid_scripts()
+    {
+        let path =
+            std::env::temp_dir().join(format!("thorn-bad-script-{}.toml", std::process::id()));
+        std::fs::write(&path, "[[event]]\nframe = 1\nevent = { NoSuchEvent = 0 }\n").unwrap();
+
+        let platform = HeadlessPlatform::new(WindowParams::default());
+        let loaded = platform.load_script(&path);
+        let _ = std::fs::remove_file(&path);
+
+        assert!(loaded.is_err());
+        assert!(platform.load_script(&path).is_err());
+    }
+
+    #[test]
+    fn reports_window_commands()
+    {
+        let platform = HeadlessPlatform::new(WindowParams::default());
+        let emitter = attached(&platform);
+        drain(&emitter);
+
+        let main = WindowId::MAIN;
+        platform.set_scale_factor(main, 2.0);
+        assert_eq!(
+            drain(&emitter),
+            [
+                PlatformEvent::ScaleFactorChanged(main, 2.0),
+                PlatformEvent::WindowSizeChange(main, WindowSize::new(1600, 1200, 2.0)),
+            ]
+        );
+
+        platform.send(PlatformMsg::Window(main, WindowCmd::Resize(640, 480)));
+        platform.send(PlatformMsg::Window(main, WindowCmd::Resize(640, 480)));
+        platform.send(PlatformMsg::Window(main, WindowCmd::Move(10, 20)));
+        platform.send(PlatformMsg::Window(main, WindowCmd::SetMinimized(true)));
+        platform.send(PlatformMsg::Window(main, WindowCmd::SetMinimized(false)));
+        platform.send(PlatformMsg::Window(
+            main,
+            WindowCmd::SetTitle("Renamed".into()),
+        ));
+        platform.send(PlatformMsg::Window(main, WindowCmd::SetTextInput(true)));
+
+        // Commands that change nothing report nothing
+        assert_eq!(
+            drain(&emitter),
+            [
+                PlatformEvent::WindowSizeChange(main, WindowSize::new(1280, 960, 2.0)),
+                PlatformEvent::WindowPositionChange(main, 20, 40),
+                PlatformEvent::WindowMinimized(main),
+                PlatformEvent::WindowRestored(main),
+            ]
+        );
+
+        let window = platform.window(main).unwrap();
+        assert_eq!(window.params.title, "Renamed");
+        assert_eq!(window.params.size, (640, 480));
+        assert!(window.text_input);
+        assert!(!window.minimized);
+
+        let second = WindowId(1);
+        platform.send(PlatformMsg::OpenWindow(second, WindowParams::default()));
+        assert_eq!(
+            drain(&emitter).first(),
+            Some(&PlatformEvent::WindowOpen(second))
+        );
+
+        platform.send(PlatformMsg::CloseWindow(second));
+        platform.send(PlatformMsg::Window(second, WindowCmd::Resize(1, 1)));
+        assert!(platform.window(second).is_none());
+        assert!(drain(&emitter).is_empty());
+
+        // The main window stays open until the platform terminates
+        platform.send(PlatformMsg::CloseWindow(main));
+        assert!(platform.window(main).is_some());
+
+        platform.send(PlatformMsg::Terminate);
+        assert!(platform.is_terminated());
+    }
+}
@@ -0,0 +1,374 @@
+use super::{
+    ClipboardBackend,
+    ClipboardImage,
+    Cursor,
+    CursorGrab,
+    Fullscreen,
+    Icon,
+    WindowId,
+    WindowSize,
+    system_clipboard,
+};
+use crate::prelude::*;
+use std::collections::BTreeMap;
+use std::ops::Deref;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+
+
+/// Commands from the [`Platform`] layer to its backend.
+pub enum PlatformMsg
+{
+    Terminate,
+    OpenWindow(WindowId, WindowParams),
+    CloseWindow(WindowId),
+    Window(WindowId, WindowCmd),
+}
+
+
+pub enum WindowCmd
+{
+    SetTitle(String),
+    Resize(u32, u32),
+    Move(i32, i32),
+    SetFullscreen(Fullscreen),
+    SetMinimized(bool),
+    SetMaximized(bool),
+    SetVsync(bool),
+    SetCursorVisible(bool),
+    SetCursorGrab(CursorGrab),
+    SetCursor(Cursor),
+    SetWindowIcon(Option<Icon>),
+    SetTextInput(bool),
+    SetImeCursorArea((i32, i32), (u32, u32)),
+}
+
+
+/// Carries out what the [`Platform`] layer is asked to do, and reports what happens to the
+/// windows as [`PlatformEvent`]s. Commands are applied asynchronously.
+pub trait PlatformBackend: Send + Sync
+{
+    fn send(&self, msg: PlatformMsg);
+
+    /// Called when the platform layer loads, with the emitter to report events through.
+    fn attach(&self, _emitter: Layer<EventEmitter<PlatformEvent>>) {}
+
+    /// Called on the core thread before every frame.
+    fn next_frame(&self) {}
+
+    fn clipboard(&self) -> Box<dyn ClipboardBackend>
+    {
+        system_clipboard()
+    }
+}
+
+
+pub struct PlatformPlugin(Arc<dyn PlatformBackend>);
+
+
+impl PlatformPlugin
+{
+    pub fn new(backend: impl PlatformBackend + 'static) -> Self
+    {
+        Self(Arc::new(backend))
+    }
+}
+
+
+impl Plugin<LayerEvent> for PlatformPlugin
+{
+    fn info(&self) -> PluginInfo
+    {
+        PluginInfo::build::<Platform>()
+            .dep::<EventEmitter<PlatformEvent>>()
+            .dep::<EventReceiver<PlatformEvent>>()
+            .dep::<Tasks>()
+            .optional_dep::<Renderer>()
+    }
+
+    fn load(
+        &mut self,
+        ctx: &PluginContext<LayerEvent>,
+    ) -> Result<AnyLayer<LayerEvent>, Box<dyn std::error::Error>>
+    {
+        let event_emitter = ctx.get().ok_or(ThError::Error(
+            "Failed to fetch platform event emitter".into(),
+        ))?;
+
+        self.0.attach(event_emitter);
+
+        Ok(AnyLayer::new(Platform {
+            main: PlatformWindow {
+                id: WindowId::MAIN,
+                backend: self.0.clone(),
+                sizes: Arc::default(),
+            },
+            next_id: AtomicU32::new(WindowId::MAIN.0 + 1),
+            clipboard: Mutex::new(self.0.clipboard()),
+        }))
+    }
+
+    fn notify_loaded(&mut self, ctx: &PluginContext<LayerEvent>)
+    {
+        let platform = ctx.get::<Platform>().unwrap();
+
+        let hooked = ctx
+            .subscribe::<PlatformEvent>(platform.clone())
+            .and_then(|_| ctx.hook(platform));
+
+        if let Err(e) = hooked
+        {
+            log::error!("The platform will not be updated: {e}");
+        }
+    }
+
+    fn notify_unloaded(&mut self, ctx: &PluginContext<LayerEvent>)
+    {
+        ctx.get::<Platform>().unwrap().write().unwrap().terminate();
+    }
+}
+
+
+/// Controls the main window, which it derefs to, and the clipboard.
+pub struct Platform
+{
+    main: PlatformWindow,
+    next_id: AtomicU32,
+    clipboard: Mutex<Box<dyn ClipboardBackend>>,
+}
+
+
+impl Platform
+{
+    fn terminate(&mut self)
+    {
+        self.main.backend.send(PlatformMsg::Terminate);
+    }
+
+    /// Opens another window, which is reported through [`PlatformEvent::WindowOpen`] once
+    /// it exists. It has its own surface to render into.
+    pub fn open_window(&self, params: WindowParams) -> PlatformWindow
+    {
+        let id = WindowId(self.next_id.fetch_add(1, Ordering::Relaxed));
+        self.main.backend.send(PlatformMsg::OpenWindow(id, params));
+
+        self.window(id)
+    }
+
+    /// Controls a window opened through [`Self::open_window`]. Commands for windows that are
+    /// closed or were never opened are ignored.
+    pub fn window(&self, id: WindowId) -> PlatformWindow
+    {
+        PlatformWindow {
+            id,
+            backend: self.main.backend.clone(),
+            sizes: self.main.sizes.clone(),
+        }
+    }
+
+    pub fn clipboard_text(&self) -> ThResult<String>
+    {
+        self.clipboard.lock().unwrap().text()
+    }
+
+    pub fn set_clipboard_text(&self, text: impl Into<String>) -> ThResult<()>
+    {
+        self.clipboard.lock().unwrap().set_text(text.into())
+    }
+
+    /// Fails if the clipboard holds no image, or the backend does not support images.
+    pub fn clipboard_image(&self) -> ThResult<ClipboardImage>
+    {
+        self.clipboard.lock().unwrap().image()
+    }
+
+    pub fn set_clipboard_image(&self, image: ClipboardImage) -> ThResult<()>
+    {
+        self.clipboard.lock().unwrap().set_image(image)
+    }
+
+    /// Replaces the system clipboard, e.g. with a [`MemoryClipboard`](super::MemoryClipboard)
+    /// in tests.
+    pub fn set_clipboard_backend(&self, backend: Box<dyn ClipboardBackend>)
+    {
+        *self.clipboard.lock().unwrap() = backend;
+    }
+}
+
+
+impl Deref for Platform
+{
+    type Target = PlatformWindow;
+
+    fn deref(&self) -> &Self::Target
+    {
+        &self.main
+    }
+}
+
+
+/// Controls one window. All changes are applied asynchronously by the backend.
+#[derive(Clone)]
+pub struct PlatformWindow
+{
+    id: WindowId,
+    backend: Arc<dyn PlatformBackend>,
+
+    // The last size reported for each window
+    sizes: Arc<RwLock<BTreeMap<WindowId, WindowSize>>>,
+}
+
+
+impl PlatformWindow
+{
+    pub fn id(&self) -> WindowId
+    {
+        self.id
+    }
+
+    /// Closes the window, after [`PlatformEvent::WindowClose`] asked for it.
+    /// The main window stays open until the application terminates.
+    pub fn close(&self)
+    {
+        self.backend.send(PlatformMsg::CloseWindow(self.id));
+
+        if self.id != WindowId::MAIN
+        {
+            self.sizes.write().unwrap().remove(&self.id);
+        }
+    }
+
+    /// The inner size and scale factor, as last reported by
+    /// [`PlatformEvent::WindowSizeChange`]. `None` until the window is open.
+    pub fn size(&self) -> Option<WindowSize>
+    {
+        self.sizes.read().unwrap().get(&self.id).copied()
+    }
+
+    /// The scale factor of the monitor the window is on, 1 until the window is open.
+    pub fn scale_factor(&self) -> f64
+    {
+        self.size().map_or(1.0, |s| s.scale_factor)
+    }
+
+    pub fn set_title(&self, title: impl Into<String>)
+    {
+        self.send(WindowCmd::SetTitle(title.into()));
+    }
+
+    /// Requests a new inner size in logical pixels. The platform may pick a different size,
+    /// which is reported as [`PlatformEvent::WindowSizeChange`].
+    pub fn resize(&self, width: u32, height: u32)
+    {
+        self.send(WindowCmd::Resize(width, height));
+    }
+
+    /// Moves the window's top left corner to a position in logical pixels.
+    pub fn move_to(&self, x: i32, y: i32)
+    {
+        self.send(WindowCmd::Move(x, y));
+    }
+
+    pub fn set_fullscreen(&self, fullscreen: Fullscreen)
+    {
+        self.send(WindowCmd::SetFullscreen(fullscreen));
+    }
+
+    pub fn set_minimized(&self, minimized: bool)
+    {
+        self.send(WindowCmd::SetMinimized(minimized));
+    }
+
+    pub fn set_maximized(&self, maximized: bool)
+    {
+        self.send(WindowCmd::SetMaximized(maximized));
+    }
+
+    /// A hint for the renderer, which picks the closest present mode the display supports.
+    pub fn set_vsync(&self, vsync: bool)
+    {
+        self.send(WindowCmd::SetVsync(vsync));
+    }
+
+    pub fn set_cursor_visible(&self, visible: bool)
+    {
+        self.send(WindowCmd::SetCursorVisible(visible));
+    }
+
+    pub fn set_cursor_grab(&self, grab: CursorGrab)
+    {
+        self.send(WindowCmd::SetCursorGrab(grab));
+    }
+
+    pub fn set_cursor(&self, cursor: Cursor)
+    {
+        self.send(WindowCmd::SetCursor(cursor));
+    }
+
+    /// Sets the icon shown in the title bar and task bar, or resets it with `None`.
+    pub fn set_window_icon(&self, icon: Option<Icon>)
+    {
+        self.send(WindowCmd::SetWindowIcon(icon));
+    }
+
+    /// Enables text input. The window emits [`PlatformEvent::TextCommitted`] for typed text and
+    /// lets the platform's IME compose text, reported as [`PlatformEvent::TextPreedit`].
+    pub fn set_text_input(&self, enabled: bool)
+    {
+        self.send(WindowCmd::SetTextInput(enabled));
+    }
+
+    /// Places the IME's candidate window next to the text being edited, in logical pixels
+    /// relative to the window.
+    pub fn set_ime_cursor_area(&self, position: (i32, i32), size: (u32, u32))
+    {
+        self.send(WindowCmd::SetImeCursorArea(position, size));
+    }
+
+    fn send(&self, cmd: WindowCmd)
+    {
+        self.backend.send(PlatformMsg::Window(self.id, cmd));
+    }
+}
+
+
+impl LayerDispatch<LayerEvent> for Platform
+{
+    fn dispatch(&mut self, _event: &LayerEvent) {}
+}
+
+
+impl EventSubscriber<PlatformEvent> for Platform
+{
+    fn receive_event(&mut self, event: &PlatformEvent)
+    {
+        let mut sizes = self.main.sizes.write().unwrap();
+
+        match event
+        {
+            PlatformEvent::WindowSizeChange(id, size) =>
+            {
+                sizes.insert(*id, *size);
+            }
+
+            PlatformEvent::ScaleFactorChanged(id, scale_factor) =>
+            {
+                if let Some(size) = sizes.get_mut(id)
+                {
+                    size.scale_factor = *scale_factor;
+                }
+            }
+
+            _ => (),
+        }
+    }
+}
+
+
+impl CoreHook for Platform
+{
+    fn prepare(&mut self)
+    {
+        self.main.backend.next_frame();
+    }
+}
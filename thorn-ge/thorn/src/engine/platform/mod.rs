@@ -1,10 +1,44 @@
+use super::input::{GamepadAxis, GamepadButton, Key, Modifiers, MouseButton};
+use serde::Deserialize;
+use std::path::PathBuf;
+
+
+mod clipboard;
+mod gamepad;
+#[cfg(feature = "gamepad")]
+mod gilrs_backend;
+mod headless;
+mod layer;
+mod text;
+mod window;
+pub use clipboard::{
+    ClipboardBackend,
+    ClipboardImage,
+    MemoryClipboard,
+    SystemClipboard,
+    system_clipboard,
+};
+pub use gamepad::{GamepadBackend, GamepadId, GamepadPlugin, Gamepads, MockGamepads, Rumble};
+pub use headless::{HeadlessPlatform, HeadlessWindow};
+pub use layer::{
+    Platform,
+    PlatformBackend,
+    PlatformMsg,
+    PlatformPlugin,
+    PlatformWindow,
+    WindowCmd,
+};
+pub use text::{ImeEvent, MockIme, TextInput};
+pub use window::{Cursor, CursorGrab, Fullscreen, Icon, Theme, WindowId, WindowSize};
+
+
 // Reexport platform for each implementation
 #[cfg(any(target_os = "linux", target_os = "windows", target_os = "macos"))]
 mod winit;
 #[cfg(any(target_os = "linux", target_os = "windows", target_os = "macos"))]
 mod winit_impl;
 #[cfg(any(target_os = "linux", target_os = "windows", target_os = "macos"))]
-pub use winit::{Platform, PlatformPlugin};
+mod winit_input;
 #[cfg(any(target_os = "linux", target_os = "windows", target_os = "macos"))]
 pub use winit_impl::ThornWindow;
 
@@ -12,26 +46,156 @@ pub use winit_impl::ThornWindow;
 //TODO: android and web in the future ???
 
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+/// Events of a single window carry its id. Keyboard and mouse button events go to the
+/// focused window, see [`PlatformEvent::WindowGotFocus`].
+#[derive(Debug, Clone, PartialEq, Deserialize)]
 pub enum PlatformEvent
 {
-    WindowOpen,
-    WindowClose,
-    WindowLostFocus,
-    WindowGotFocus,
-    WindowMinimized,
-    WindowSizeChange(u32, u32),
-    WindowPositionChange(i32, i32),
+    WindowOpen(WindowId),
+
+    // The user asked to close the window. Only the main window closes on its own,
+    // when the application terminates.
+    WindowClose(WindowId),
+    WindowLostFocus(WindowId),
+    WindowGotFocus(WindowId),
+    WindowMinimized(WindowId),
+
+    // The window is shown again after being minimized
+    WindowRestored(WindowId),
+
+    // Whether the window is fully hidden by other windows, or on another workspace
+    WindowOccluded(WindowId, bool),
+    WindowSizeChange(WindowId, WindowSize),
+
+    // Position of the window's top left corner on the desktop, in physical pixels
+    WindowPositionChange(WindowId, i32, i32),
+
+    // The window moved to a monitor with a different scale factor. Its new size follows
+    // as a WindowSizeChange.
+    ScaleFactorChanged(WindowId, f64),
+    ThemeChanged(WindowId, Theme),
+
+    // Files dragged over the window, one event per file
+    FileHovered(WindowId, PathBuf),
+    FileHoverCancelled(WindowId),
+    FileDropped(WindowId, PathBuf),
+
+    // Mobile platforms take the windows' surfaces away while the app is in the background
+    Suspended,
+    Resumed,
     PlatformError(String),
+
+    // Key repeats are not reported
+    KeyPressed(Key),
+    KeyReleased(Key),
+    ModifiersChanged(Modifiers),
+    MouseButtonPressed(MouseButton),
+    MouseButtonReleased(MouseButton),
+
+    // Cursor position in physical pixels, relative to the window under it
+    CursorMoved(WindowId, f64, f64),
+    CursorLeft(WindowId),
+
+    // Raw mouse movement, independent of the cursor
+    MouseMotion(f64, f64),
+
+    // Scroll wheel movement in lines
+    MouseScroll(f32, f32),
+
+    // Typed or composed text, only while the window's text input is enabled
+    TextCommitted(WindowId, String),
+
+    // Text the IME is still composing, with the cursor as a byte range into it.
+    // An empty string ends the composition.
+    TextPreedit(WindowId, String, Option<(usize, usize)>),
+
+    GamepadConnected(GamepadId, String),
+    GamepadDisconnected(GamepadId),
+    GamepadPressed(GamepadId, GamepadButton),
+    GamepadReleased(GamepadId, GamepadButton),
+    GamepadAxisChanged(GamepadId, GamepadAxis, f32),
+}
+
+
+impl PlatformEvent
+{
+    pub fn is_input(&self) -> bool
+    {
+        matches!(
+            self,
+            Self::KeyPressed(_)
+                | Self::KeyReleased(_)
+                | Self::ModifiersChanged(_)
+                | Self::MouseButtonPressed(_)
+                | Self::MouseButtonReleased(_)
+                | Self::CursorMoved(..)
+                | Self::CursorLeft(_)
+                | Self::MouseMotion(..)
+                | Self::MouseScroll(..)
+                | Self::TextCommitted(..)
+                | Self::TextPreedit(..)
+        )
+    }
+
+    /// The window this event happened in, if it belongs to one.
+    pub fn window(&self) -> Option<WindowId>
+    {
+        match self
+        {
+            Self::WindowOpen(id)
+            | Self::WindowClose(id)
+            | Self::WindowLostFocus(id)
+            | Self::WindowGotFocus(id)
+            | Self::WindowMinimized(id)
+            | Self::WindowRestored(id)
+            | Self::WindowOccluded(id, _)
+            | Self::WindowSizeChange(id, _)
+            | Self::WindowPositionChange(id, ..)
+            | Self::ScaleFactorChanged(id, _)
+            | Self::ThemeChanged(id, _)
+            | Self::FileHovered(id, _)
+            | Self::FileHoverCancelled(id)
+            | Self::FileDropped(id, _)
+            | Self::CursorMoved(id, ..)
+            | Self::CursorLeft(id)
+            | Self::TextCommitted(id, _)
+            | Self::TextPreedit(id, ..) => Some(*id),
+            _ => None,
+        }
+    }
+
+    pub fn is_gamepad(&self) -> bool
+    {
+        matches!(
+            self,
+            Self::GamepadConnected(..)
+                | Self::GamepadDisconnected(_)
+                | Self::GamepadPressed(..)
+                | Self::GamepadReleased(..)
+                | Self::GamepadAxisChanged(..)
+        )
+    }
 }
 
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
 pub struct WindowParams
 {
     pub title: String,
+
+    // Positions and sizes are in logical pixels, scaled by the monitor's scale factor
     pub position: Option<(i32, i32)>,
     pub size: (u32, u32),
+    pub min_size: Option<(u32, u32)>,
+    pub max_size: Option<(u32, u32)>,
+    pub resizable: bool,
+    pub decorations: bool,
+    pub maximized: bool,
+    pub fullscreen: Fullscreen,
+
+    // Prefer a present mode that waits for the display's refresh
+    pub vsync: bool,
 }
 
 
@@ -45,6 +209,13 @@ impl Default for WindowParams
                 .into(),
             position: None,
             size: (800, 600),
+            min_size: None,
+            max_size: None,
+            resizable: true,
+            decorations: true,
+            maximized: false,
+            fullscreen: Fullscreen::Windowed,
+            vsync: false,
         }
     }
 }
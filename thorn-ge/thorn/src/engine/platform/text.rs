@@ -0,0 +1,259 @@
+use super::WindowId;
+use crate::prelude::*;
+use std::sync::{Arc, Mutex};
+
+
+/// What an input method reports while the user is writing.
+/// Each platform translates its own IME events into these.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ImeEvent
+{
+    Enabled,
+
+    /// Text that is still being composed, with the cursor as a byte range into it.
+    /// An empty string ends the composition.
+    Preedit(String, Option<(usize, usize)>),
+
+    /// Finished text that replaces the composition
+    Commit(String),
+    Disabled,
+}
+
+
+/// Turns a window's typed text and IME events into [`PlatformEvent`]s,
+/// while text input is enabled.
+#[derive(Debug)]
+pub struct TextInput
+{
+    window: WindowId,
+    enabled: bool,
+    composing: bool,
+}
+
+
+impl TextInput
+{
+    pub fn new(window: WindowId) -> Self
+    {
+        Self {
+            window,
+            enabled: false,
+            composing: false,
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool
+    {
+        self.enabled
+    }
+
+    /// Disabling text input in the middle of a composition throws the composed text away.
+    pub fn set_enabled(&mut self, enabled: bool) -> Option<PlatformEvent>
+    {
+        self.enabled = enabled;
+
+        if !enabled && self.composing
+        {
+            self.composing = false;
+            return Some(PlatformEvent::TextPreedit(self.window, String::new(), None));
+        }
+
+        None
+    }
+
+    /// Text produced by a key press. Control characters are left to the key events.
+    pub fn key_text(&mut self, text: &str) -> Option<PlatformEvent>
+    {
+        if !self.enabled || self.composing
+        {
+            return None;
+        }
+
+        let text: String = text.chars().filter(|c| !c.is_control()).collect();
+        (!text.is_empty()).then_some(PlatformEvent::TextCommitted(self.window, text))
+    }
+
+    pub fn ime(&mut self, event: ImeEvent) -> Option<PlatformEvent>
+    {
+        if !self.enabled
+        {
+            return None;
+        }
+
+        match event
+        {
+            ImeEvent::Preedit(text, cursor) =>
+            {
+                self.composing = !text.is_empty();
+                Some(PlatformEvent::TextPreedit(self.window, text, cursor))
+            }
+
+            ImeEvent::Commit(text) =>
+            {
+                self.composing = false;
+                (!text.is_empty()).then_some(PlatformEvent::TextCommitted(self.window, text))
+            }
+
+            // The platform switched the IME off, while text input stays enabled
+            ImeEvent::Disabled if self.composing =>
+            {
+                self.composing = false;
+                Some(PlatformEvent::TextPreedit(self.window, String::new(), None))
+            }
+
+            ImeEvent::Enabled | ImeEvent::Disabled => None,
+        }
+    }
+}
+
+
+/// An input method without a platform. It emits the same events as a window's IME would,
+/// for driving text input from tests or scripts.
+#[derive(Clone)]
+pub struct MockIme
+{
+    emitter: Layer<EventEmitter<PlatformEvent>>,
+    text_input: Arc<Mutex<TextInput>>,
+}
+
+
+impl MockIme
+{
+    /// Writes into the main window
+    pub fn new(emitter: Layer<EventEmitter<PlatformEvent>>) -> Self
+    {
+        Self::for_window(emitter, WindowId::MAIN)
+    }
+
+    pub fn for_window(emitter: Layer<EventEmitter<PlatformEvent>>, window: WindowId) -> Self
+    {
+        Self {
+            emitter,
+            text_input: Arc::new(Mutex::new(TextInput::new(window))),
+        }
+    }
+
+    pub fn set_enabled(&self, enabled: bool)
+    {
+        let event = self.text_input.lock().unwrap().set_enabled(enabled);
+        self.emit(event);
+    }
+
+    /// Types text on the keyboard, without composing it
+    pub fn type_text(&self, text: &str)
+    {
+        let event = self.text_input.lock().unwrap().key_text(text);
+        self.emit(event);
+    }
+
+    /// Updates the text being composed, with the cursor at its end
+    pub fn compose(&self, text: &str)
+    {
+        let cursor = Some((text.len(), text.len()));
+        self.ime(ImeEvent::Preedit(text.into(), cursor));
+    }
+
+    pub fn commit(&self, text: &str)
+    {
+        self.ime(ImeEvent::Preedit(String::new(), None));
+        self.ime(ImeEvent::Commit(text.into()));
+    }
+
+    pub fn ime(&self, event: ImeEvent)
+    {
+        let event = self.text_input.lock().unwrap().ime(event);
+        self.emit(event);
+    }
+
+    fn emit(&self, event: Option<PlatformEvent>)
+    {
+        if let Some(event) = event
+        {
+            self.emitter.write().unwrap().emit(event);
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    fn mock_ime() -> (MockIme, Layer<EventEmitter<PlatformEvent>>)
+    {
+        let emitter = Layer::new(EventEmitter::new());
+        (MockIme::new(emitter.clone()), emitter)
+    }
+
+    fn drain(emitter: &Layer<EventEmitter<PlatformEvent>>) -> Vec<PlatformEvent>
+    {
+        let mut events = vec![];
+        emitter.write().unwrap().drain_into(&mut events);
+        events
+    }
+
+    #[test]
+    fn ignores_input_while_disabled()
+    {
+        let (ime, emitter) = mock_ime();
+
+        ime.type_text("a");
+        ime.compose("ka");
+        ime.commit("か");
+
+        assert!(drain(&emitter).is_empty());
+    }
+
+    #[test]
+    fn composes_and_commits()
+    {
+        let (ime, emitter) = mock_ime();
+
+        ime.set_enabled(true);
+        assert!(drain(&emitter).is_empty());
+
+        ime.compose("k");
+        ime.compose("ka");
+
+        // Typed text is part of the composition
+        ime.type_text("a");
+
+        ime.commit("か");
+
+        assert_eq!(
+            drain(&emitter),
+            vec![
+                PlatformEvent::TextPreedit(WindowId::MAIN, "k".into(), Some((1, 1))),
+                PlatformEvent::TextPreedit(WindowId::MAIN, "ka".into(), Some((2, 2))),
+                PlatformEvent::TextPreedit(WindowId::MAIN, String::new(), None),
+                PlatformEvent::TextCommitted(WindowId::MAIN, "か".into()),
+            ]
+        );
+
+        ime.type_text("b\n");
+        assert_eq!(
+            drain(&emitter),
+            vec![PlatformEvent::TextCommitted(WindowId::MAIN, "b".into())]
+        );
+    }
+
+    #[test]
+    fn disabling_ends_the_composition()
+    {
+        let (ime, emitter) = mock_ime();
+
+        ime.set_enabled(true);
+        ime.compose("ka");
+        ime.set_enabled(false);
+        ime.commit("か");
+
+        assert_eq!(
+            drain(&emitter),
+            vec![
+                PlatformEvent::TextPreedit(WindowId::MAIN, "ka".into(), Some((2, 2))),
+                PlatformEvent::TextPreedit(WindowId::MAIN, String::new(), None),
+            ]
+        );
+    }
+}
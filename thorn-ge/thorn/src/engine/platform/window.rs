@@ -0,0 +1,150 @@
+use serde::Deserialize;
+use std::fmt::{Display, Formatter};
+
+
+/// Identifies a window. The one opened at startup is [`WindowId::MAIN`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize)]
+pub struct WindowId(pub u32);
+
+
+impl WindowId
+{
+    pub const MAIN: Self = Self(0);
+}
+
+
+impl Display for WindowId
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result
+    {
+        write!(f, "Window {}", self.0)
+    }
+}
+
+
+/// A window's inner size in physical pixels, which is what the renderer draws, and the scale
+/// factor of the monitor it is on. Logical pixels are physical ones divided by the scale
+/// factor, they keep the same size on screen across monitors.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+pub struct WindowSize
+{
+    pub width: u32,
+    pub height: u32,
+    pub scale_factor: f64,
+}
+
+
+impl WindowSize
+{
+    pub fn new(width: u32, height: u32, scale_factor: f64) -> Self
+    {
+        Self {
+            width,
+            height,
+            scale_factor,
+        }
+    }
+
+    pub fn from_logical(width: f64, height: f64, scale_factor: f64) -> Self
+    {
+        Self {
+            width: (width * scale_factor).round() as u32,
+            height: (height * scale_factor).round() as u32,
+            scale_factor,
+        }
+    }
+
+    pub fn physical(&self) -> (u32, u32)
+    {
+        (self.width, self.height)
+    }
+
+    pub fn logical(&self) -> (f64, f64)
+    {
+        (
+            self.width as f64 / self.scale_factor,
+            self.height as f64 / self.scale_factor,
+        )
+    }
+
+    pub fn is_empty(&self) -> bool
+    {
+        self.width == 0 || self.height == 0
+    }
+}
+
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Fullscreen
+{
+    #[default]
+    Windowed,
+
+    /// A borderless window covering the current monitor
+    Borderless,
+
+    /// Takes over the current monitor, switching it to the video mode closest to the window's size
+    Exclusive,
+}
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum Theme
+{
+    Light,
+    Dark,
+}
+
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CursorGrab
+{
+    #[default]
+    None,
+
+    /// Keeps the cursor inside of the window
+    Confined,
+
+    /// Keeps the cursor in place. Use the raw mouse motion to move things.
+    /// Falls back to confining the cursor on platforms that can not lock it.
+    Locked,
+}
+
+
+/// An image with 8 bit RGBA pixels, row by row from the top left.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Icon
+{
+    pub width: u32,
+    pub height: u32,
+    pub rgba: Vec<u8>,
+}
+
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum Cursor
+{
+    #[default]
+    Default,
+    Pointer,
+    Text,
+    Crosshair,
+    Move,
+    Wait,
+    Progress,
+    NotAllowed,
+    Grab,
+    Grabbing,
+    ResizeHorizontal,
+    ResizeVertical,
+    ResizeDiagonal,
+    ResizeAntiDiagonal,
+
+    /// A custom image, with the click point at `hotspot` pixels from its top left
+    Custom
+    {
+        image: Icon,
+        hotspot: (u16, u16),
+    },
+}
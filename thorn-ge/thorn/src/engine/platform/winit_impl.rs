@@ -1,119 +1,418 @@
-use super::winit::WinitMsg;
-use crate::{prelude::*, utils::show_msg_box};
+use super::{
+    Cursor,
+    CursorGrab,
+    Fullscreen,
+    Icon,
+    ImeEvent,
+    PlatformMsg,
+    TextInput,
+    Theme,
+    WindowCmd,
+    WindowId,
+    WindowSize,
+    winit_input,
+};
+use crate::{engine::renderer::WindowSurface, prelude::*, utils::show_msg_box};
+use std::collections::{BTreeMap, HashMap};
 use winit::{
     application::ApplicationHandler,
     dpi::{LogicalPosition, LogicalSize},
-    event::WindowEvent,
+    event::{DeviceEvent, DeviceId, ElementState, Ime, MouseScrollDelta, WindowEvent},
     event_loop::{ActiveEventLoop, EventLoop, EventLoopProxy},
+    monitor::MonitorHandle,
     raw_window_handle::{HasDisplayHandle, HasWindowHandle},
-    window::Window,
+    window::{CursorGrabMode, CursorIcon, CustomCursor, Window},
 };
 
 
-pub struct ThornWindow
+// A window and what is tracked for it. The winit window is created once the event loop runs,
+// until then commands only change the params.
+struct ThWindow
 {
+    id: WindowId,
     params: WindowParams,
     window: Option<Window>,
+    has_surface: bool,
+    is_minimized: bool,
+    text_input: TextInput,
+}
+
+
+impl ThWindow
+{
+    fn new(id: WindowId, params: WindowParams) -> Self
+    {
+        Self {
+            id,
+            params,
+            window: None,
+            has_surface: false,
+            is_minimized: false,
+            text_input: TextInput::new(id),
+        }
+    }
+
+    fn surface(&self) -> Option<WindowSurface>
+    {
+        let window = self.window.as_ref()?;
+
+        Some(WindowSurface {
+            id: self.id,
+            display: window.display_handle().ok()?.as_raw(),
+            window: window.window_handle().ok()?.as_raw(),
+            size: self.size()?,
+            vsync: self.params.vsync,
+        })
+    }
+
+    fn size(&self) -> Option<WindowSize>
+    {
+        let window = self.window.as_ref()?;
+        let size = window.inner_size();
+
+        Some(WindowSize::new(
+            size.width,
+            size.height,
+            window.scale_factor(),
+        ))
+    }
+
+    fn update_minimized(&mut self, minimized: bool) -> Option<PlatformEvent>
+    {
+        if self.is_minimized == minimized
+        {
+            return None;
+        }
+
+        self.is_minimized = minimized;
+        Some(match minimized
+        {
+            true => PlatformEvent::WindowMinimized(self.id),
+            false => PlatformEvent::WindowRestored(self.id),
+        })
+    }
+}
+
+
+pub struct ThornWindow
+{
+    windows: BTreeMap<WindowId, ThWindow>,
+    ids: HashMap<winit::window::WindowId, WindowId>,
     event_emitter: Layer<EventEmitter<PlatformEvent>>,
     renderer: Layer<Renderer>,
-    is_renderer_initialized: bool,
+    is_suspended: bool,
 }
 
 
-impl ApplicationHandler<WinitMsg> for ThornWindow
+impl ApplicationHandler<PlatformMsg> for ThornWindow
 {
     fn resumed(&mut self, event_loop: &ActiveEventLoop)
     {
-        if self.window.is_none()
+        // Windows requested while there was no event loop, or while suspended
+        let pending: Vec<_> = self
+            .windows
+            .values()
+            .filter(|w| w.window.is_none())
+            .map(|w| w.id)
+            .collect();
+
+        for id in pending
         {
-            self.create_window(event_loop);
+            self.create_window(event_loop, id);
         }
 
-        if !self.is_renderer_initialized && !event_loop.exiting()
+        if event_loop.exiting()
         {
-            let win = self.window.as_mut().unwrap();
-            match self.renderer.write().unwrap().initialize(
-                win.display_handle().expect("No Display handle...").as_raw(),
-                win.window_handle().expect("No window handle...").as_raw(),
-                win.inner_size().width,
-                win.inner_size().height,
-            )
-            {
-                Err(e) =>
-                {
-                    log::error!("Failed to initialize renderer: {e}");
-                    show_msg_box("ERROR: Failed to initialize the renderer");
-                }
+            return;
+        }
 
-                Ok(_) => self.is_renderer_initialized = true,
+        // The surfaces were destroyed on suspension, the window handles might have changed too
+        if self.is_suspended
+        {
+            let surfaces: Vec<_> = self
+                .windows
+                .values()
+                .filter(|w| w.has_surface)
+                .filter_map(ThWindow::surface)
+                .collect();
+
+            if let Err(e) = self.renderer.write().unwrap().resume(&surfaces)
+            {
+                log::error!("Failed to recreate the surfaces: {e}");
+                self.emit(PlatformEvent::PlatformError(e.to_string()));
+                return;
             }
+
+            self.is_suspended = false;
+            self.emit(PlatformEvent::Resumed);
+        }
+
+        let without_surface: Vec<_> = self
+            .windows
+            .values()
+            .filter(|w| w.window.is_some() && !w.has_surface)
+            .map(|w| w.id)
+            .collect();
+
+        for id in without_surface
+        {
+            self.add_surface(id);
+        }
+    }
+
+    fn suspended(&mut self, _event_loop: &ActiveEventLoop)
+    {
+        if self.is_suspended
+        {
+            return;
+        }
+
+        // The surfaces have to be gone before this returns
+        if self.windows.values().any(|w| w.has_surface)
+        {
+            self.renderer.write().unwrap().suspend();
         }
 
-        //TODO: Create new surface
+        self.is_suspended = true;
+        self.emit(PlatformEvent::Suspended);
     }
 
     fn window_event(
         &mut self,
         _event_loop: &ActiveEventLoop,
-        _window_id: winit::window::WindowId,
+        window_id: winit::window::WindowId,
         event: WindowEvent,
     )
     {
+        let Some(&id) = self.ids.get(&window_id)
+        else
+        {
+            return;
+        };
+
         match event
         {
             WindowEvent::Resized(size) =>
             {
-                self.event_emitter
-                    .write()
-                    .unwrap()
-                    .emit(PlatformEvent::WindowSizeChange(size.width, size.height));
+                let Some(win) = self.windows.get_mut(&id)
+                else
+                {
+                    return;
+                };
+
+                // Some platforms report minimizing as a resize to nothing
+                let minimized = size.width == 0
+                    || size.height == 0
+                    || win.window.as_ref().and_then(|w| w.is_minimized()) == Some(true);
+
+                let scale_factor = win.window.as_ref().map_or(1.0, |w| w.scale_factor());
+
+                if let Some(event) = win.update_minimized(minimized)
+                {
+                    self.emit(event);
+                }
+
+                self.emit(PlatformEvent::WindowSizeChange(
+                    id,
+                    WindowSize::new(size.width, size.height, scale_factor),
+                ));
+            }
+
+            WindowEvent::Occluded(occluded) =>
+            {
+                self.emit(PlatformEvent::WindowOccluded(id, occluded))
             }
 
+            WindowEvent::ScaleFactorChanged { scale_factor, .. } =>
+            {
+                self.emit(PlatformEvent::ScaleFactorChanged(id, scale_factor))
+            }
+
+            WindowEvent::ThemeChanged(theme) =>
+            {
+                self.emit(PlatformEvent::ThemeChanged(
+                    id,
+                    match theme
+                    {
+                        winit::window::Theme::Light => Theme::Light,
+                        winit::window::Theme::Dark => Theme::Dark,
+                    },
+                ))
+            }
+
+            WindowEvent::HoveredFile(path) => self.emit(PlatformEvent::FileHovered(id, path)),
+            WindowEvent::HoveredFileCancelled => self.emit(PlatformEvent::FileHoverCancelled(id)),
+            WindowEvent::DroppedFile(path) => self.emit(PlatformEvent::FileDropped(id, path)),
+
             WindowEvent::Moved(pos) =>
             {
-                self.event_emitter
-                    .write()
-                    .unwrap()
-                    .emit(PlatformEvent::WindowPositionChange(pos.x, pos.y));
+                self.emit(PlatformEvent::WindowPositionChange(id, pos.x, pos.y));
             }
 
             WindowEvent::CloseRequested =>
             {
-                self.event_emitter
-                    .write()
-                    .unwrap()
-                    .emit(PlatformEvent::WindowClose);
+                self.emit(PlatformEvent::WindowClose(id));
             }
 
             WindowEvent::Focused(true) =>
             {
-                self.event_emitter
-                    .write()
-                    .unwrap()
-                    .emit(PlatformEvent::WindowGotFocus)
+                // Not every platform resizes when restoring a minimized window
+                if let Some(event) = self
+                    .windows
+                    .get_mut(&id)
+                    .and_then(|w| w.update_minimized(false))
+                {
+                    self.emit(event);
+                }
+
+                self.emit(PlatformEvent::WindowGotFocus(id))
             }
 
-            WindowEvent::Focused(false) =>
+            WindowEvent::Focused(false) => self.emit(PlatformEvent::WindowLostFocus(id)),
+
+            WindowEvent::KeyboardInput { event, .. } =>
             {
-                self.event_emitter
-                    .write()
-                    .unwrap()
-                    .emit(PlatformEvent::WindowLostFocus)
+                // Text repeats while the key is held, unlike the key itself
+                if let Some(text) = &event.text
+                    && event.state == ElementState::Pressed
+                    && let Some(text) = self
+                        .windows
+                        .get_mut(&id)
+                        .and_then(|w| w.text_input.key_text(text))
+                {
+                    self.emit(text);
+                }
+
+                if !event.repeat
+                {
+                    let key = winit_input::key(event.physical_key);
+                    self.emit(match event.state
+                    {
+                        ElementState::Pressed => PlatformEvent::KeyPressed(key),
+                        ElementState::Released => PlatformEvent::KeyReleased(key),
+                    })
+                }
+            }
+
+            WindowEvent::Ime(ime) =>
+            {
+                let ime = match ime
+                {
+                    Ime::Enabled => ImeEvent::Enabled,
+                    Ime::Preedit(text, cursor) => ImeEvent::Preedit(text, cursor),
+                    Ime::Commit(text) => ImeEvent::Commit(text),
+                    Ime::Disabled => ImeEvent::Disabled,
+                };
+
+                if let Some(text) = self
+                    .windows
+                    .get_mut(&id)
+                    .and_then(|w| w.text_input.ime(ime))
+                {
+                    self.emit(text);
+                }
+            }
+
+            WindowEvent::ModifiersChanged(modifiers) =>
+            {
+                self.emit(PlatformEvent::ModifiersChanged(winit_input::modifiers(
+                    modifiers.state(),
+                )))
+            }
+
+            WindowEvent::MouseInput { state, button, .. } =>
+            {
+                let button = winit_input::mouse_button(button);
+                self.emit(match state
+                {
+                    ElementState::Pressed => PlatformEvent::MouseButtonPressed(button),
+                    ElementState::Released => PlatformEvent::MouseButtonReleased(button),
+                })
+            }
+
+            WindowEvent::CursorMoved { position, .. } =>
+            {
+                self.emit(PlatformEvent::CursorMoved(id, position.x, position.y))
+            }
+
+            WindowEvent::CursorLeft { .. } => self.emit(PlatformEvent::CursorLeft(id)),
+
+            WindowEvent::MouseWheel { delta, .. } =>
+            {
+                // Touchpads scroll by pixels. Roughly what a line is worth in a browser.
+                const PIXELS_PER_LINE: f64 = 20.0;
+
+                let (x, y) = match delta
+                {
+                    MouseScrollDelta::LineDelta(x, y) => (x, y),
+                    MouseScrollDelta::PixelDelta(p) =>
+                    {
+                        (
+                            (p.x / PIXELS_PER_LINE) as f32,
+                            (p.y / PIXELS_PER_LINE) as f32,
+                        )
+                    }
+                };
+
+                self.emit(PlatformEvent::MouseScroll(x, y))
             }
 
             _ => (),
         }
     }
 
-    fn user_event(&mut self, event_loop: &ActiveEventLoop, event: WinitMsg)
+    fn device_event(
+        &mut self,
+        _event_loop: &ActiveEventLoop,
+        _device_id: DeviceId,
+        event: DeviceEvent,
+    )
+    {
+        if let DeviceEvent::MouseMotion { delta: (x, y) } = event
+        {
+            self.emit(PlatformEvent::MouseMotion(x, y));
+        }
+    }
+
+    fn user_event(&mut self, event_loop: &ActiveEventLoop, event: PlatformMsg)
     {
         match event
         {
-            WinitMsg::Terminate =>
+            PlatformMsg::Terminate =>
             {
                 log::info!("closing window");
                 event_loop.exit()
             }
+
+            PlatformMsg::OpenWindow(id, params) =>
+            {
+                self.windows.insert(id, ThWindow::new(id, params));
+
+                // Suspended platforms can not create windows, they are opened on resume
+                if !self.is_suspended
+                {
+                    self.create_window(event_loop, id);
+                    self.add_surface(id);
+                }
+            }
+
+            PlatformMsg::CloseWindow(WindowId::MAIN) =>
+            {
+                log::warn!("The main window stays open until the application terminates");
+            }
+
+            PlatformMsg::CloseWindow(id) => self.close_window(id),
+
+            PlatformMsg::Window(id, WindowCmd::SetVsync(vsync)) =>
+            {
+                if let Some(win) = self.windows.get_mut(&id)
+                {
+                    win.params.vsync = vsync;
+                    self.renderer.write().unwrap().set_vsync(id, vsync);
+                }
+            }
+
+            PlatformMsg::Window(id, cmd) => self.control_window(event_loop, id, cmd),
         }
     }
 }
@@ -121,44 +420,282 @@ impl ApplicationHandler<WinitMsg> for ThornWindow
 
 impl ThornWindow
 {
-    fn create_window(&mut self, event_loop: &ActiveEventLoop)
+    fn emit(&self, event: PlatformEvent)
     {
+        self.event_emitter.write().unwrap().emit(event);
+    }
+
+    fn create_window(&mut self, event_loop: &ActiveEventLoop, id: WindowId)
+    {
+        let Some(win) = self.windows.get_mut(&id)
+        else
+        {
+            return;
+        };
+
+        let params = &win.params;
         let mut attributes = Window::default_attributes()
-            .with_title(&self.params.title)
+            .with_title(&params.title)
             .with_visible(true)
-            .with_inner_size(LogicalSize::new(self.params.size.0, self.params.size.1));
+            .with_inner_size(LogicalSize::new(params.size.0, params.size.1));
 
-        if let Some((x, y)) = self.params.position
+        if let Some((x, y)) = params.position
         {
             attributes = attributes.with_position(LogicalPosition::new(x, y));
         };
 
+        if let Some((w, h)) = params.min_size
+        {
+            attributes = attributes.with_min_inner_size(LogicalSize::new(w, h));
+        }
+
+        if let Some((w, h)) = params.max_size
+        {
+            attributes = attributes.with_max_inner_size(LogicalSize::new(w, h));
+        }
+
+        attributes = attributes
+            .with_resizable(params.resizable)
+            .with_decorations(params.decorations)
+            .with_maximized(params.maximized)
+            .with_fullscreen(fullscreen(
+                params.fullscreen,
+                params.size,
+                event_loop.primary_monitor(),
+            ));
+
         match event_loop.create_window(attributes)
         {
             Ok(window) =>
             {
-                log::info!("Window created");
-                self.window = Some(window)
+                log::info!("{id} created");
+                window.set_ime_allowed(win.text_input.is_enabled());
+                self.ids.insert(window.id(), id);
+                win.window = Some(window);
+
+                let size = win.size();
+                self.emit(PlatformEvent::WindowOpen(id));
+
+                // Not every platform reports the initial size as a resize
+                if let Some(size) = size
+                {
+                    self.emit(PlatformEvent::WindowSizeChange(id, size));
+                }
             }
 
             Err(e) =>
             {
-                log::error!("Failed to create a window: {e}");
-                self.event_emitter
-                    .write()
-                    .unwrap()
-                    .emit(PlatformEvent::PlatformError(e.to_string()))
+                log::error!("Failed to create {id}: {e}");
+                self.windows.remove(&id);
+                self.emit(PlatformEvent::PlatformError(e.to_string()));
+
+                if id == WindowId::MAIN
+                {
+                    show_msg_box("ERROR: Failed to open a window");
+                    event_loop.exit();
+                }
             }
         }
+    }
+
+    fn add_surface(&mut self, id: WindowId)
+    {
+        let Some(win) = self.windows.get_mut(&id)
+        else
+        {
+            return;
+        };
 
-        if self.window.is_none()
+        let Some(surface) = win.surface()
+        else
         {
-            show_msg_box("ERROR: Failed to open a window");
-            event_loop.exit();
+            return;
+        };
+
+        match self.renderer.write().unwrap().add_window(surface)
+        {
+            Ok(_) => win.has_surface = true,
+
+            Err(e) if id == WindowId::MAIN =>
+            {
+                log::error!("Failed to initialize renderer: {e}");
+                show_msg_box("ERROR: Failed to initialize the renderer");
+            }
+
+            Err(e) =>
+            {
+                log::error!("Failed to render into {id}: {e}");
+                self.emit(PlatformEvent::PlatformError(e.to_string()));
+            }
+        }
+    }
+
+    fn close_window(&mut self, id: WindowId)
+    {
+        let Some(win) = self.windows.remove(&id)
+        else
+        {
+            return;
+        };
+
+        // The surface has to go before its window
+        if win.has_surface
+        {
+            self.renderer.write().unwrap().remove_window(id);
+        }
+
+        if let Some(window) = &win.window
+        {
+            self.ids.remove(&window.id());
         }
+
+        log::info!("{id} closed");
     }
 
-    pub fn prepare() -> ThResult<(EventLoop<WinitMsg>, EventLoopProxy<WinitMsg>)>
+    fn control_window(&mut self, event_loop: &ActiveEventLoop, id: WindowId, cmd: WindowCmd)
+    {
+        let Some(win) = self.windows.get_mut(&id)
+        else
+        {
+            log::warn!("Ignoring a command for {id}, which is not open");
+            return;
+        };
+
+        // Commands that arrive before the window exists only change the startup params
+        let Some(window) = &win.window
+        else
+        {
+            match cmd
+            {
+                WindowCmd::SetTitle(title) => win.params.title = title,
+                WindowCmd::Resize(w, h) => win.params.size = (w, h),
+                WindowCmd::Move(x, y) => win.params.position = Some((x, y)),
+                WindowCmd::SetFullscreen(f) => win.params.fullscreen = f,
+                WindowCmd::SetMaximized(m) => win.params.maximized = m,
+                WindowCmd::SetTextInput(enabled) =>
+                {
+                    win.text_input.set_enabled(enabled);
+                }
+                _ => log::warn!("Ignoring a command for {id}, as it is not created yet"),
+            }
+
+            return;
+        };
+
+        let mut event = None;
+
+        match cmd
+        {
+            WindowCmd::SetTitle(title) =>
+            {
+                window.set_title(&title);
+                win.params.title = title;
+            }
+
+            WindowCmd::Resize(w, h) =>
+            {
+                // The new size is reported through a resize event, if it changes immediately
+                let _ = window.request_inner_size(LogicalSize::new(w, h));
+            }
+
+            WindowCmd::Move(x, y) => window.set_outer_position(LogicalPosition::new(x, y)),
+
+            WindowCmd::SetFullscreen(mode) =>
+            {
+                let size = window.inner_size().to_logical(window.scale_factor());
+                window.set_fullscreen(fullscreen(
+                    mode,
+                    (size.width, size.height),
+                    window.current_monitor(),
+                ));
+                win.params.fullscreen = mode;
+            }
+
+            WindowCmd::SetMinimized(minimized) =>
+            {
+                window.set_minimized(minimized);
+                event = win.update_minimized(minimized);
+            }
+
+            WindowCmd::SetMaximized(maximized) => window.set_maximized(maximized),
+            WindowCmd::SetCursorVisible(visible) => window.set_cursor_visible(visible),
+
+            WindowCmd::SetCursorGrab(grab) =>
+            {
+                let result = match grab
+                {
+                    CursorGrab::None => window.set_cursor_grab(CursorGrabMode::None),
+                    CursorGrab::Confined => window.set_cursor_grab(CursorGrabMode::Confined),
+                    CursorGrab::Locked =>
+                    {
+                        window
+                            .set_cursor_grab(CursorGrabMode::Locked)
+                            .or_else(|_| window.set_cursor_grab(CursorGrabMode::Confined))
+                    }
+                };
+
+                if let Err(e) = result
+                {
+                    log::warn!("Failed to grab the cursor: {e}");
+                }
+            }
+
+            WindowCmd::SetCursor(Cursor::Custom { image, hotspot }) =>
+            {
+                let (Ok(w), Ok(h)) = (u16::try_from(image.width), u16::try_from(image.height))
+                else
+                {
+                    log::warn!("Cursor images can be at most {0}x{0} pixels", u16::MAX);
+                    return;
+                };
+
+                match CustomCursor::from_rgba(image.rgba, w, h, hotspot.0, hotspot.1)
+                {
+                    Ok(source) => window.set_cursor(event_loop.create_custom_cursor(source)),
+                    Err(e) => log::warn!("Invalid cursor image: {e}"),
+                }
+            }
+
+            WindowCmd::SetCursor(cursor) => window.set_cursor(cursor_icon(&cursor)),
+
+            WindowCmd::SetWindowIcon(icon) =>
+            {
+                let icon = icon.and_then(
+                    |Icon {
+                         width,
+                         height,
+                         rgba,
+                     }| {
+                        winit::window::Icon::from_rgba(rgba, width, height)
+                            .map_err(|e| log::warn!("Invalid window icon: {e}"))
+                            .ok()
+                    },
+                );
+
+                window.set_window_icon(icon);
+            }
+
+            WindowCmd::SetTextInput(enabled) =>
+            {
+                window.set_ime_allowed(enabled);
+                event = win.text_input.set_enabled(enabled);
+            }
+
+            WindowCmd::SetImeCursorArea((x, y), (w, h)) =>
+            {
+                window.set_ime_cursor_area(LogicalPosition::new(x, y), LogicalSize::new(w, h));
+            }
+
+            WindowCmd::SetVsync(_) => (),
+        }
+
+        if let Some(event) = event
+        {
+            self.emit(event);
+        }
+    }
+
+    pub fn prepare() -> ThResult<(EventLoop<PlatformMsg>, EventLoopProxy<PlatformMsg>)>
     {
         let event_loop = EventLoop::with_user_event()
             .build()
@@ -169,6 +706,7 @@ impl ThornWindow
         Ok((event_loop, proxy))
     }
 
+    /// Opens the main window with `params` once the event loop runs.
     pub fn new(
         params: WindowParams,
         event_emitter: Layer<EventEmitter<PlatformEvent>>,
@@ -176,15 +714,15 @@ impl ThornWindow
     ) -> Self
     {
         Self {
-            params,
+            windows: BTreeMap::from([(WindowId::MAIN, ThWindow::new(WindowId::MAIN, params))]),
+            ids: HashMap::new(),
             event_emitter,
             renderer,
-            is_renderer_initialized: false,
-            window: None,
+            is_suspended: false,
         }
     }
 
-    pub fn run(mut self, event_loop: EventLoop<WinitMsg>)
+    pub fn run(mut self, event_loop: EventLoop<PlatformMsg>)
     {
         if let Err(e) = event_loop.run_app(&mut self)
         {
@@ -192,3 +730,65 @@ impl ThornWindow
         }
     }
 }
+
+
+/// Exclusive fullscreen uses the monitor's video mode that is closest to the window's size,
+/// with the highest refresh rate.
+fn fullscreen(
+    mode: Fullscreen,
+    size: (u32, u32),
+    monitor: Option<MonitorHandle>,
+) -> Option<winit::window::Fullscreen>
+{
+    match mode
+    {
+        Fullscreen::Windowed => None,
+        Fullscreen::Borderless => Some(winit::window::Fullscreen::Borderless(monitor)),
+
+        Fullscreen::Exclusive =>
+        {
+            let distance = |w: u32, h: u32| w.abs_diff(size.0) + h.abs_diff(size.1);
+
+            let video_mode = monitor.and_then(|monitor| {
+                monitor.video_modes().min_by_key(|m| {
+                    (
+                        distance(m.size().width, m.size().height),
+                        u32::MAX - m.refresh_rate_millihertz(),
+                    )
+                })
+            });
+
+            match video_mode
+            {
+                Some(m) => Some(winit::window::Fullscreen::Exclusive(m)),
+                None =>
+                {
+                    log::warn!("No video mode for exclusive fullscreen, using borderless instead");
+                    Some(winit::window::Fullscreen::Borderless(None))
+                }
+            }
+        }
+    }
+}
+
+
+fn cursor_icon(cursor: &Cursor) -> CursorIcon
+{
+    match cursor
+    {
+        Cursor::Default | Cursor::Custom { .. } => CursorIcon::Default,
+        Cursor::Pointer => CursorIcon::Pointer,
+        Cursor::Text => CursorIcon::Text,
+        Cursor::Crosshair => CursorIcon::Crosshair,
+        Cursor::Move => CursorIcon::Move,
+        Cursor::Wait => CursorIcon::Wait,
+        Cursor::Progress => CursorIcon::Progress,
+        Cursor::NotAllowed => CursorIcon::NotAllowed,
+        Cursor::Grab => CursorIcon::Grab,
+        Cursor::Grabbing => CursorIcon::Grabbing,
+        Cursor::ResizeHorizontal => CursorIcon::EwResize,
+        Cursor::ResizeVertical => CursorIcon::NsResize,
+        Cursor::ResizeDiagonal => CursorIcon::NwseResize,
+        Cursor::ResizeAntiDiagonal => CursorIcon::NeswResize,
+    }
+}
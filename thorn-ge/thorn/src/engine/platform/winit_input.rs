@@ -0,0 +1,171 @@
+use crate::engine::input::{Key, Modifiers, MouseButton};
+use winit::event::MouseButton as WinitButton;
+use winit::keyboard::{KeyCode, ModifiersState, PhysicalKey};
+#[cfg(any(target_os = "windows", target_os = "macos", target_os = "linux"))]
+use winit::platform::scancode::PhysicalKeyExtScancode;
+
+
+pub fn key(key: PhysicalKey) -> Key
+{
+    let code = match key
+    {
+        PhysicalKey::Code(code) => code,
+        PhysicalKey::Unidentified(_) => return Key::Unknown(scancode(key)),
+    };
+
+    match code
+    {
+        KeyCode::KeyA => Key::A,
+        KeyCode::KeyB => Key::B,
+        KeyCode::KeyC => Key::C,
+        KeyCode::KeyD => Key::D,
+        KeyCode::KeyE => Key::E,
+        KeyCode::KeyF => Key::F,
+        KeyCode::KeyG => Key::G,
+        KeyCode::KeyH => Key::H,
+        KeyCode::KeyI => Key::I,
+        KeyCode::KeyJ => Key::J,
+        KeyCode::KeyK => Key::K,
+        KeyCode::KeyL => Key::L,
+        KeyCode::KeyM => Key::M,
+        KeyCode::KeyN => Key::N,
+        KeyCode::KeyO => Key::O,
+        KeyCode::KeyP => Key::P,
+        KeyCode::KeyQ => Key::Q,
+        KeyCode::KeyR => Key::R,
+        KeyCode::KeyS => Key::S,
+        KeyCode::KeyT => Key::T,
+        KeyCode::KeyU => Key::U,
+        KeyCode::KeyV => Key::V,
+        KeyCode::KeyW => Key::W,
+        KeyCode::KeyX => Key::X,
+        KeyCode::KeyY => Key::Y,
+        KeyCode::KeyZ => Key::Z,
+        KeyCode::Digit0 => Key::Digit0,
+        KeyCode::Digit1 => Key::Digit1,
+        KeyCode::Digit2 => Key::Digit2,
+        KeyCode::Digit3 => Key::Digit3,
+        KeyCode::Digit4 => Key::Digit4,
+        KeyCode::Digit5 => Key::Digit5,
+        KeyCode::Digit6 => Key::Digit6,
+        KeyCode::Digit7 => Key::Digit7,
+        KeyCode::Digit8 => Key::Digit8,
+        KeyCode::Digit9 => Key::Digit9,
+        KeyCode::F1 => Key::F1,
+        KeyCode::F2 => Key::F2,
+        KeyCode::F3 => Key::F3,
+        KeyCode::F4 => Key::F4,
+        KeyCode::F5 => Key::F5,
+        KeyCode::F6 => Key::F6,
+        KeyCode::F7 => Key::F7,
+        KeyCode::F8 => Key::F8,
+        KeyCode::F9 => Key::F9,
+        KeyCode::F10 => Key::F10,
+        KeyCode::F11 => Key::F11,
+        KeyCode::F12 => Key::F12,
+        KeyCode::ArrowUp => Key::Up,
+        KeyCode::ArrowDown => Key::Down,
+        KeyCode::ArrowLeft => Key::Left,
+        KeyCode::ArrowRight => Key::Right,
+        KeyCode::Escape => Key::Escape,
+        KeyCode::Enter => Key::Enter,
+        KeyCode::Space => Key::Space,
+        KeyCode::Tab => Key::Tab,
+        KeyCode::Backspace => Key::Backspace,
+        KeyCode::Delete => Key::Delete,
+        KeyCode::Insert => Key::Insert,
+        KeyCode::Home => Key::Home,
+        KeyCode::End => Key::End,
+        KeyCode::PageUp => Key::PageUp,
+        KeyCode::PageDown => Key::PageDown,
+        KeyCode::CapsLock => Key::CapsLock,
+        KeyCode::PrintScreen => Key::PrintScreen,
+        KeyCode::Pause => Key::Pause,
+        KeyCode::ShiftLeft => Key::ShiftLeft,
+        KeyCode::ShiftRight => Key::ShiftRight,
+        KeyCode::ControlLeft => Key::ControlLeft,
+        KeyCode::ControlRight => Key::ControlRight,
+        KeyCode::AltLeft => Key::AltLeft,
+        KeyCode::AltRight => Key::AltRight,
+        KeyCode::SuperLeft => Key::SuperLeft,
+        KeyCode::SuperRight => Key::SuperRight,
+        KeyCode::Backquote => Key::Backquote,
+        KeyCode::Minus => Key::Minus,
+        KeyCode::Equal => Key::Equal,
+        KeyCode::BracketLeft => Key::BracketLeft,
+        KeyCode::BracketRight => Key::BracketRight,
+        KeyCode::Backslash => Key::Backslash,
+        KeyCode::Semicolon => Key::Semicolon,
+        KeyCode::Quote => Key::Quote,
+        KeyCode::Comma => Key::Comma,
+        KeyCode::Period => Key::Period,
+        KeyCode::Slash => Key::Slash,
+        KeyCode::Numpad0 => Key::Numpad0,
+        KeyCode::Numpad1 => Key::Numpad1,
+        KeyCode::Numpad2 => Key::Numpad2,
+        KeyCode::Numpad3 => Key::Numpad3,
+        KeyCode::Numpad4 => Key::Numpad4,
+        KeyCode::Numpad5 => Key::Numpad5,
+        KeyCode::Numpad6 => Key::Numpad6,
+        KeyCode::Numpad7 => Key::Numpad7,
+        KeyCode::Numpad8 => Key::Numpad8,
+        KeyCode::Numpad9 => Key::Numpad9,
+        KeyCode::NumpadAdd => Key::NumpadAdd,
+        KeyCode::NumpadSubtract => Key::NumpadSubtract,
+        KeyCode::NumpadMultiply => Key::NumpadMultiply,
+        KeyCode::NumpadDivide => Key::NumpadDivide,
+        KeyCode::NumpadDecimal => Key::NumpadDecimal,
+        KeyCode::NumpadEnter => Key::NumpadEnter,
+        KeyCode::NumLock => Key::NumLock,
+        _ => Key::Unknown(scancode(key)),
+    }
+}
+
+
+// Keys without a variant are told apart by their platform scancode, the same one for
+// identified and unidentified keys, so bindings of a key always name the same code
+#[cfg(any(target_os = "windows", target_os = "macos", target_os = "linux"))]
+fn scancode(key: PhysicalKey) -> u32
+{
+    key.to_scancode().unwrap_or(0)
+}
+
+
+// Only unidentified keys carry a code here
+#[cfg(not(any(target_os = "windows", target_os = "macos", target_os = "linux")))]
+fn scancode(key: PhysicalKey) -> u32
+{
+    use winit::keyboard::NativeKeyCode;
+
+    match key
+    {
+        PhysicalKey::Unidentified(NativeKeyCode::Android(c) | NativeKeyCode::Xkb(c)) => c,
+        PhysicalKey::Unidentified(NativeKeyCode::MacOS(c) | NativeKeyCode::Windows(c)) => c as u32,
+        _ => 0,
+    }
+}
+
+
+pub fn mouse_button(button: WinitButton) -> MouseButton
+{
+    match button
+    {
+        WinitButton::Left => MouseButton::Left,
+        WinitButton::Right => MouseButton::Right,
+        WinitButton::Middle => MouseButton::Middle,
+        WinitButton::Back => MouseButton::Back,
+        WinitButton::Forward => MouseButton::Forward,
+        WinitButton::Other(b) => MouseButton::Other(b),
+    }
+}
+
+
+pub fn modifiers(state: ModifiersState) -> Modifiers
+{
+    Modifiers {
+        shift: state.shift_key(),
+        ctrl: state.control_key(),
+        alt: state.alt_key(),
+        logo: state.super_key(),
+    }
+}
@@ -1,4 +1,7 @@
+use super::{CommandList, MaterialId, MeshId, TextureId, pipeline::PipelineDesc};
+use crate::engine::platform::{WindowId, WindowSize};
 use crate::error::ThResult;
+use std::collections::BTreeMap;
 use winit::raw_window_handle::{RawDisplayHandle, RawWindowHandle};
 
 
@@ -11,18 +14,94 @@ pub enum FrameStatus
 }
 
 
+/// A window for the renderer to draw into. The handles have to stay valid
+/// until the window's surface is removed or lost.
+#[derive(Debug, Clone, Copy)]
+pub struct WindowSurface
+{
+    pub id: WindowId,
+    pub display: RawDisplayHandle,
+    pub window: RawWindowHandle,
+    pub size: WindowSize,
+    pub vsync: bool,
+}
+
+
+/// Vertices as they are laid out in the vertex buffer, with optional indices into them.
+/// Meshes without vertex data are generated in the vertex shader from the vertex index.
+pub(crate) struct MeshData
+{
+    pub vertices: Vec<u8>,
+    pub vertex_count: u32,
+    pub indices: Vec<u32>,
+}
+
+
+/// RGBA pixels in sRGB, row by row.
+pub(crate) struct TextureData
+{
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+}
+
+
+/// Device memory in bytes. Reserved memory is allocated from the device in blocks,
+/// used memory is what resources take up of it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MemoryStats
+{
+    pub used: u64,
+    pub reserved: u64,
+    pub blocks: usize,
+    pub allocations: usize,
+}
+
+
 pub(crate) trait RenderAPI: Send + Sync
 {
-    fn initialize(
+    /// The first surface also initializes the device.
+    fn add_surface(&mut self, surface: WindowSurface) -> ThResult<()>;
+    fn remove_surface(&mut self, id: WindowId);
+    fn destroy(&mut self);
+    fn surface_size_changed(&mut self, id: WindowId, w: u32, h: u32) -> ThResult<()>;
+    fn set_surface_hidden(&mut self, id: WindowId, hidden: bool);
+    fn surface_lost(&mut self);
+    fn surface_recreated(&mut self, surface: WindowSurface) -> ThResult<()>;
+    fn set_vsync(&mut self, id: WindowId, vsync: bool);
+
+    /// Fails if the pipeline's shaders are missing. Everything else is checked on first use.
+    fn create_material(&mut self, pipeline: PipelineDesc) -> ThResult<MaterialId>;
+
+    /// Replaces the data of one of the material's declared bindings. Windows pick it up
+    /// on their next frames.
+    fn set_material_data(&mut self, id: MaterialId, binding: u32, data: Vec<u8>) -> ThResult<()>;
+
+    /// Sets the texture one of the material's texture bindings samples.
+    fn set_material_texture(
         &mut self,
-        rdh: RawDisplayHandle,
-        rwh: RawWindowHandle,
-        w: u32,
-        h: u32,
+        id: MaterialId,
+        binding: u32,
+        texture: TextureId,
     ) -> ThResult<()>;
-    fn destroy(&mut self);
-    fn surface_size_changed(&mut self, w: u32, h: u32) -> ThResult<()>;
+
+    /// Meshes created before the device exists are uploaded along with it.
+    fn create_mesh(&mut self, mesh: MeshData) -> ThResult<MeshId>;
+    fn destroy_mesh(&mut self, id: MeshId);
+
+    /// Textures created before the device exists are uploaded along with it.
+    fn create_texture(&mut self, texture: TextureData) -> ThResult<TextureId>;
+
+    /// Materials sampling the texture are not drawn until they get another one.
+    fn destroy_texture(&mut self, id: TextureId);
+
+    /// Moves mesh buffers out of sparsely used memory blocks and frees the emptied blocks.
+    /// Texture images are not moved, blocks holding one are kept. Waits for the device.
+    fn defragment(&mut self);
+    fn memory_stats(&self) -> MemoryStats;
     fn frame_prepare(&mut self) -> FrameStatus;
-    fn frame_render(&mut self) -> FrameStatus;
+
+    /// Records the windows' command lists. Windows without one are only cleared.
+    fn frame_render(&mut self, commands: &BTreeMap<WindowId, CommandList>) -> FrameStatus;
     fn frame_finish(&mut self) -> FrameStatus;
 }
@@ -0,0 +1,152 @@
+use crate::math::*;
+
+
+/// Mesh data held by the renderer backend.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct MeshId(pub u64);
+
+
+/// An image held by the renderer backend, for materials to sample.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct TextureId(pub u64);
+
+
+/// The shaders and parameters a mesh is drawn with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct MaterialId(pub u64);
+
+
+#[derive(Clone, Copy, PartialEq)]
+pub struct Camera
+{
+    pub view: Mat4,
+    pub projection: Mat4,
+}
+
+
+impl Camera
+{
+    pub fn new(view: Mat4, projection: Mat4) -> Self
+    {
+        Self { view, projection }
+    }
+
+    pub fn view_projection(&self) -> Mat4
+    {
+        self.projection * self.view
+    }
+}
+
+
+impl Default for Camera
+{
+    fn default() -> Self
+    {
+        Self::new(Mat4::new(), Mat4::new())
+    }
+}
+
+
+/// Draws a mesh with a material, placed in the world by `transform`.
+#[derive(Clone, Copy, PartialEq)]
+pub struct DrawCommand
+{
+    pub mesh: MeshId,
+    pub material: MaterialId,
+    pub transform: Mat4,
+}
+
+
+impl DrawCommand
+{
+    pub fn new(mesh: MeshId, material: MaterialId, transform: Mat4) -> Self
+    {
+        Self {
+            mesh,
+            material,
+            transform,
+        }
+    }
+}
+
+
+/// What is drawn into one window. The clear color and camera stay until they are changed,
+/// draws only last for the frame they were submitted in.
+#[derive(Clone, PartialEq)]
+pub struct CommandList
+{
+    pub clear_color: Vec4,
+    pub camera: Camera,
+    pub draws: Vec<DrawCommand>,
+}
+
+
+impl CommandList
+{
+    pub fn new() -> Self
+    {
+        Self::default()
+    }
+
+    pub fn set_clear_color(&mut self, color: Vec4)
+    {
+        self.clear_color = color;
+    }
+
+    pub fn set_camera(&mut self, camera: Camera)
+    {
+        self.camera = camera;
+    }
+
+    pub fn draw(&mut self, command: DrawCommand)
+    {
+        self.draws.push(command);
+    }
+
+    /// Drops the frame's draws, after they were rendered.
+    pub fn next_frame(&mut self)
+    {
+        self.draws.clear();
+    }
+}
+
+
+impl Default for CommandList
+{
+    fn default() -> Self
+    {
+        Self {
+            clear_color: Vec4::new(0.0, 0.0, 0.0, 1.0),
+            camera: Camera::default(),
+            draws: vec![],
+        }
+    }
+}
+
+
+/// Plain data that is uploaded to the GPU byte for byte, like vertices.
+///
+/// # Safety
+/// The type must not contain padding bytes, or anything but plain numbers,
+/// e.g. a `#[repr(C)]` struct of `f32`s and [`Vec4`]s. [`Vec3`] is padded to 16 bytes.
+pub unsafe trait Pod: Copy + 'static {}
+
+
+macro_rules! impl_pod {
+    ($($t:ty),*) => {
+        $(unsafe impl Pod for $t {})*
+    };
+}
+
+impl_pod!(
+    u8, u16, u32, u64, i8, i16, i32, i64, f32, f64, Vec2, Vec4, Mat2, Mat4
+);
+
+unsafe impl<T: Pod, const N: usize> Pod for [T; N] {}
+
+
+/// The raw bytes of plain data like vertices and matrices, for uploading to the GPU.
+pub(crate) fn as_bytes<T: Pod>(data: &[T]) -> &[u8]
+{
+    unsafe { std::slice::from_raw_parts(data.as_ptr().cast(), size_of_val(data)) }
+}
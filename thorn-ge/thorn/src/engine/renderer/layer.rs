@@ -1,13 +1,30 @@
 use super::{
-    api::{FrameStatus, RenderAPI},
+    Camera,
+    CommandList,
+    DrawCommand,
+    MaterialId,
+    MemoryStats,
+    MeshId,
+    PipelineDesc,
+    Pod,
+    TextureId,
+    api::{FrameStatus, MeshData, RenderAPI, TextureData, WindowSurface},
+    as_bytes,
     vulkan::VulkanRenderer,
 };
-use crate::prelude::*;
-use winit::raw_window_handle::{RawDisplayHandle, RawWindowHandle};
+use crate::engine::platform::{WindowId, WindowSize};
+use crate::{math::Vec4, prelude::*};
+use serde::Deserialize;
+use shader_utils::ShaderBundle;
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
 
 
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum Backend
 {
+    #[default]
     Vulkan,
 }
 
@@ -23,7 +40,45 @@ enum FrameState
 }
 
 
-pub struct RendererPlugin(pub Backend);
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RendererConfig
+{
+    // The compiled shaders materials are built from
+    pub shader_bundle: PathBuf,
+}
+
+
+impl Default for RendererConfig
+{
+    fn default() -> Self
+    {
+        Self {
+            shader_bundle: "shaders/thorn.shader_bundle".into(),
+        }
+    }
+}
+
+
+pub struct RendererPlugin
+{
+    backend: Backend,
+    config: RendererConfig,
+}
+
+
+impl RendererPlugin
+{
+    pub fn new(backend: Backend) -> Self
+    {
+        Self {
+            backend,
+            config: RendererConfig::default(),
+        }
+    }
+}
+
+
 impl Plugin<LayerEvent> for RendererPlugin
 {
     fn info(&self) -> PluginInfo
@@ -33,22 +88,35 @@ impl Plugin<LayerEvent> for RendererPlugin
             .dep::<EventReceiver<PlatformEvent>>()
     }
 
+    fn configure(&mut self, config: &PluginConfig) -> Result<(), Box<dyn std::error::Error>>
+    {
+        self.config = config.get()?;
+        Ok(())
+    }
+
     fn load(
         &mut self,
-        reg: &LayerReg<LayerEvent>,
+        ctx: &PluginContext<LayerEvent>,
     ) -> Result<AnyLayer<LayerEvent>, Box<dyn std::error::Error>>
     {
+        // Drawing works without shaders, only materials can not be created
+        let path = &self.config.shader_bundle;
+        let shaders = ShaderBundle::load(path).unwrap_or_else(|e| {
+            log::warn!("Failed to load the shader bundle {path:?}: {e}");
+            ShaderBundle::new()
+        });
+
         #[allow(clippy::single_match)]
-        let api = match self.0
+        let api = match self.backend
         {
-            Backend::Vulkan => Box::new(VulkanRenderer::new()),
+            Backend::Vulkan => Box::new(VulkanRenderer::new(shaders)),
         };
 
-        let tasks = reg
+        let tasks = ctx
             .get()
             .ok_or(ThError::Error("Failed to fetch core layer".into()))?;
 
-        let event_receiver = reg.get().ok_or(ThError::Error(
+        let event_receiver = ctx.get().ok_or(ThError::Error(
             "Failed to fetch platform event receiver".into(),
         ))?;
 
@@ -57,26 +125,64 @@ impl Plugin<LayerEvent> for RendererPlugin
             tasks,
             event_receiver,
             frame_state: FrameState::Finish,
+            windows: HashMap::new(),
+            commands: BTreeMap::new(),
+            suspended: false,
+            skip_frame: false,
         }))
     }
 
-    fn notify_loaded(&mut self, reg: &LayerReg<LayerEvent>)
+    fn notify_loaded(&mut self, ctx: &PluginContext<LayerEvent>)
     {
-        let me = reg.get::<Renderer>().unwrap();
+        let me = ctx.get::<Renderer>().unwrap();
         me.write().unwrap().init(me.clone());
     }
 
-    fn notify_unloaded(&mut self, reg: &LayerReg<LayerEvent>)
+    fn notify_unloaded(&mut self, ctx: &PluginContext<LayerEvent>)
+    {
+        ctx.get::<Renderer>().unwrap().write().unwrap().destroy();
+    }
+}
+
+
+// A window being drawn to. A minimized or hidden window has nothing to present to.
+#[derive(Clone, Copy)]
+struct RenderWindow
+{
+    minimized: bool,
+    occluded: bool,
+    size: WindowSize,
+}
+
+
+impl RenderWindow
+{
+    fn new(size: WindowSize) -> Self
+    {
+        Self {
+            minimized: false,
+            occluded: false,
+            size,
+        }
+    }
+
+    fn is_hidden(&self) -> bool
     {
-        reg.get::<Renderer>().unwrap().write().unwrap().destroy();
+        self.minimized || self.occluded
     }
 }
 
 
+/// Draws into the windows. Plugins submit what to draw during the frame, through
+/// [`Self::draw`] and friends, and it is rendered once the frame finishes.
 pub struct Renderer
 {
     api: Box<dyn RenderAPI>,
     frame_state: FrameState,
+    windows: HashMap<WindowId, RenderWindow>,
+    commands: BTreeMap<WindowId, CommandList>,
+    suspended: bool,
+    skip_frame: bool,
     tasks: Layer<Tasks>,
     event_receiver: Layer<EventReceiver<PlatformEvent>>,
 }
@@ -96,15 +202,243 @@ impl Renderer
         //self.api.destroy();
     }
 
-    pub fn initialize(
+    /// Starts drawing into a window. The first window also initializes the renderer.
+    pub fn add_window(&mut self, surface: WindowSurface) -> ThResult<()>
+    {
+        self.api.add_surface(surface)?;
+        self.windows
+            .insert(surface.id, RenderWindow::new(surface.size));
+        self.commands.entry(surface.id).or_default();
+        Ok(())
+    }
+
+    /// Releases the window's surface. Has to happen before the window is closed.
+    pub fn remove_window(&mut self, id: WindowId)
+    {
+        self.api.remove_surface(id);
+        self.windows.remove(&id);
+        self.commands.remove(&id);
+    }
+
+    /// Finishes the frame in flight and destroys all surfaces, as the windows are about to
+    /// lose them. Rendering pauses until [`Self::resume`] is called.
+    pub fn suspend(&mut self)
+    {
+        if self.suspended
+        {
+            return;
+        }
+
+        if self.frame_state == FrameState::Begin
+        {
+            self.api.frame_render(&self.commands);
+            self.frame_state = FrameState::Render;
+        }
+
+        if self.frame_state == FrameState::Render
+        {
+            self.api.frame_finish();
+            self.frame_state = FrameState::Finish;
+        }
+
+        self.api.surface_lost();
+        self.suspended = true;
+    }
+
+    /// Recreates the surfaces for the windows' new handles, and continues rendering.
+    pub fn resume(&mut self, surfaces: &[WindowSurface]) -> ThResult<()>
+    {
+        if !self.suspended
+        {
+            return Ok(());
+        }
+
+        for surface in surfaces
+        {
+            self.api.surface_recreated(*surface)?;
+            self.windows
+                .entry(surface.id)
+                .and_modify(|w| w.size = surface.size)
+                .or_insert(RenderWindow::new(surface.size));
+        }
+
+        self.suspended = false;
+        Ok(())
+    }
+
+    /// Whether no frames are drawn, as the application is suspended or every window is hidden.
+    pub fn is_paused(&self) -> bool
+    {
+        self.suspended
+            || (!self.windows.is_empty() && self.windows.values().all(RenderWindow::is_hidden))
+    }
+
+    /// Prefer a present mode that waits for the display's refresh. Takes effect on the next frame.
+    pub fn set_vsync(&mut self, id: WindowId, vsync: bool)
+    {
+        self.api.set_vsync(id, vsync);
+    }
+
+    /// Draws a mesh into the window during the current frame. Windows that are not drawn
+    /// into are ignored.
+    pub fn draw(&mut self, window: WindowId, command: DrawCommand)
+    {
+        if let Some(commands) = self.commands.get_mut(&window)
+        {
+            commands.draw(command);
+        }
+    }
+
+    /// The camera the window's meshes are seen through, until it is changed.
+    pub fn set_camera(&mut self, window: WindowId, camera: Camera)
+    {
+        if let Some(commands) = self.commands.get_mut(&window)
+        {
+            commands.set_camera(camera);
+        }
+    }
+
+    pub fn set_clear_color(&mut self, window: WindowId, color: Vec4)
+    {
+        if let Some(commands) = self.commands.get_mut(&window)
+        {
+            commands.set_clear_color(color);
+        }
+    }
+
+    /// A material draws meshes with a pipeline built from the description.
+    pub fn create_material(&mut self, pipeline: PipelineDesc) -> ThResult<MaterialId>
+    {
+        self.api.create_material(pipeline)
+    }
+
+    /// Sets what the material's shaders read at `binding`, like colors or light positions.
+    pub fn set_material_data<T: Pod>(
+        &mut self,
+        material: MaterialId,
+        binding: u32,
+        data: &[T],
+    ) -> ThResult<()>
+    {
+        self.api
+            .set_material_data(material, binding, as_bytes(data).to_vec())
+    }
+
+    /// Sets the texture the material's shaders sample at `binding`.
+    pub fn set_material_texture(
         &mut self,
-        rdh: RawDisplayHandle,
-        rwh: RawWindowHandle,
-        w: u32,
-        h: u32,
+        material: MaterialId,
+        binding: u32,
+        texture: TextureId,
     ) -> ThResult<()>
     {
-        self.api.initialize(rdh, rwh, w, h)
+        self.api.set_material_texture(material, binding, texture)
+    }
+
+    /// Uploads vertices, and optionally indices into them, for drawing with a material
+    /// whose vertex layout matches `V`.
+    pub fn create_mesh<V: Pod>(&mut self, vertices: &[V], indices: &[u32]) -> ThResult<MeshId>
+    {
+        self.api.create_mesh(MeshData {
+            vertices: as_bytes(vertices).to_vec(),
+            vertex_count: vertices.len() as u32,
+            indices: indices.to_vec(),
+        })
+    }
+
+    /// A mesh without vertex data, its vertex shader generates the vertices by index.
+    pub fn create_generated_mesh(&mut self, vertex_count: u32) -> ThResult<MeshId>
+    {
+        self.api.create_mesh(MeshData {
+            vertices: vec![],
+            vertex_count,
+            indices: vec![],
+        })
+    }
+
+    /// Waits for the device, so better not done every frame.
+    pub fn destroy_mesh(&mut self, id: MeshId)
+    {
+        self.api.destroy_mesh(id);
+    }
+
+    /// Uploads `width` times `height` RGBA pixels in sRGB, row by row.
+    pub fn create_texture(&mut self, width: u32, height: u32, pixels: &[u8])
+    -> ThResult<TextureId>
+    {
+        self.api.create_texture(TextureData {
+            width,
+            height,
+            pixels: pixels.to_vec(),
+        })
+    }
+
+    /// Waits for the device, so better not done every frame. Materials sampling
+    /// the texture are not drawn until they get another one.
+    pub fn destroy_texture(&mut self, id: TextureId)
+    {
+        self.api.destroy_texture(id);
+    }
+
+    /// Moves mesh buffers out of sparsely used memory blocks and frees the emptied blocks.
+    /// Texture images stay where they are, so blocks holding one are kept. Waits for the
+    /// device, so better done while loading than every frame.
+    pub fn defragment(&mut self)
+    {
+        self.api.defragment();
+    }
+
+    /// Device memory reserved by the renderer and how much of it is in use.
+    pub fn memory_stats(&self) -> MemoryStats
+    {
+        self.api.memory_stats()
+    }
+
+    /// What is drawn into the window this frame.
+    pub fn commands(&self, window: WindowId) -> Option<&CommandList>
+    {
+        self.commands.get(&window)
+    }
+
+    /// The size of the surface drawn into, and the scale factor to draw at.
+    pub fn window_size(&self, id: WindowId) -> Option<WindowSize>
+    {
+        self.windows.get(&id).map(|w| w.size)
+    }
+
+    fn end_frame(&mut self)
+    {
+        if self.frame_state != FrameState::Begin
+        {
+            log::warn!("Tried to render frame after begining it failed");
+            self.frame_state = FrameState::Failed;
+            return;
+        }
+
+        if let FrameStatus::Success = self.api.frame_render(&self.commands)
+        {
+            self.frame_state = FrameState::Render;
+        }
+        else
+        {
+            log::warn!("Tried to end frame after rendering failed");
+            self.frame_state = FrameState::Failed;
+            return;
+        }
+
+        if let FrameStatus::Success = self.api.frame_finish()
+        {
+            self.frame_state = FrameState::Finish;
+        }
+    }
+
+    fn update_visibility(&mut self, id: WindowId, update: impl FnOnce(&mut RenderWindow))
+    {
+        if let Some(window) = self.windows.get_mut(&id)
+        {
+            update(window);
+            self.api.set_surface_hidden(id, window.is_hidden());
+        }
     }
 }
 
@@ -128,47 +462,35 @@ impl CoreHook for Renderer
 {
     fn prepare(&mut self)
     {
-        if self.frame_state != FrameState::Finish
+        // Decided once per frame, so a pause never cuts a started frame short
+        self.skip_frame = self.is_paused();
+        if self.skip_frame
         {
-            log::warn!("Tried to begin frame after last frame didnt finish properly");
-            self.frame_state = FrameState::Failed;
             return;
         }
 
-        if let FrameStatus::Success = self.api.frame_prepare()
-        {
-            self.frame_state = FrameState::Begin;
-        }
-    }
-
-    fn tick(&mut self, _frame_info: &FrameInfo)
-    {
-        if self.frame_state != FrameState::Begin
+        if self.frame_state != FrameState::Finish
         {
-            log::warn!("Tried to render frame after begining it failed");
+            log::warn!("Tried to begin frame after last frame didnt finish properly");
             self.frame_state = FrameState::Failed;
             return;
         }
 
-        if let FrameStatus::Success = self.api.frame_render()
+        if let FrameStatus::Success = self.api.frame_prepare()
         {
-            self.frame_state = FrameState::Render;
+            self.frame_state = FrameState::Begin;
         }
     }
 
+    // Rendering waits until the end of the frame, so everything submitted during it is drawn
     fn finish(&mut self)
     {
-        if self.frame_state != FrameState::Render
+        if !self.skip_frame
         {
-            log::warn!("Tried to end frame after rendering failed");
-            self.frame_state = FrameState::Failed;
-            return;
+            self.end_frame();
         }
 
-        if let FrameStatus::Success = self.api.frame_finish()
-        {
-            self.frame_state = FrameState::Finish;
-        }
+        self.commands.values_mut().for_each(CommandList::next_frame);
     }
 }
 
@@ -182,17 +504,49 @@ impl EventSubscriber<PlatformEvent> for Renderer
             return;
         }
 
-        #[allow(clippy::single_match)]
         match event
         {
-            PlatformEvent::WindowSizeChange(w, h) =>
+            // A zero sized swapchain can not be created. The window is minimized anyway.
+            PlatformEvent::WindowSizeChange(_, size) if size.is_empty() => (),
+
+            PlatformEvent::WindowSizeChange(id, size) =>
             {
-                if let Err(e) = self.api.surface_size_changed(*w, *h)
+                let Some(window) = self.windows.get_mut(id)
+                else
+                {
+                    return;
+                };
+
+                window.size = *size;
+
+                if let Err(e) = self.api.surface_size_changed(*id, size.width, size.height)
                 {
                     log::error!("{e}");
                 }
             }
 
+            PlatformEvent::ScaleFactorChanged(id, scale_factor) =>
+            {
+                if let Some(window) = self.windows.get_mut(id)
+                {
+                    window.size.scale_factor = *scale_factor;
+                }
+            }
+
+            PlatformEvent::WindowMinimized(id) =>
+            {
+                self.update_visibility(*id, |v| v.minimized = true)
+            }
+            PlatformEvent::WindowRestored(id) =>
+            {
+                self.update_visibility(*id, |v| v.minimized = false)
+            }
+
+            PlatformEvent::WindowOccluded(id, occluded) =>
+            {
+                self.update_visibility(*id, |v| v.occluded = *occluded)
+            }
+
             _ => (),
         }
     }
@@ -1,5 +1,12 @@
 pub mod api;
 pub mod vulkan;
 
+mod draw;
 mod layer;
+pub mod pipeline;
+pub use api::{MemoryStats, WindowSurface};
+pub(crate) use draw::as_bytes;
+pub use draw::{Camera, CommandList, DrawCommand, MaterialId, MeshId, Pod, TextureId};
+pub use layer::RendererConfig;
 pub use layer::{Backend, Renderer, RendererPlugin};
+pub use pipeline::PipelineDesc;
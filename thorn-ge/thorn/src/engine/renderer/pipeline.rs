@@ -0,0 +1,281 @@
+/// An entry point of a shader in the shader bundle, like `main_fs` in `blank.spv`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ShaderStage
+{
+    pub shader: String,
+    pub entry: String,
+}
+
+
+impl ShaderStage
+{
+    pub fn new(shader: impl Into<String>, entry: impl Into<String>) -> Self
+    {
+        Self {
+            shader: shader.into(),
+            entry: entry.into(),
+        }
+    }
+}
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum VertexFormat
+{
+    Float,
+    Vec2,
+    Vec3,
+    Vec4,
+}
+
+
+impl VertexFormat
+{
+    /// Size in bytes
+    pub fn size(&self) -> u32
+    {
+        match self
+        {
+            Self::Float => 4,
+            Self::Vec2 => 8,
+            Self::Vec3 => 12,
+            Self::Vec4 => 16,
+        }
+    }
+}
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct VertexAttribute
+{
+    pub location: u32,
+    pub format: VertexFormat,
+    pub offset: u32,
+}
+
+
+/// How the vertex shader's inputs are laid out in a vertex buffer, one vertex after another.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct VertexLayout
+{
+    pub stride: u32,
+    pub attributes: Vec<VertexAttribute>,
+}
+
+
+impl VertexLayout
+{
+    /// Tightly packed attributes, at locations counting up from 0.
+    pub fn packed(formats: &[VertexFormat]) -> Self
+    {
+        let mut layout = Self::default();
+
+        for (location, format) in formats.iter().enumerate()
+        {
+            layout.attributes.push(VertexAttribute {
+                location: location as u32,
+                format: *format,
+                offset: layout.stride,
+            });
+
+            layout.stride += format.size();
+        }
+
+        layout
+    }
+}
+
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Topology
+{
+    #[default]
+    Triangles,
+    Lines,
+    Points,
+}
+
+
+/// Which faces are not drawn. Front faces are wound counter clockwise.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum CullMode
+{
+    None,
+    Front,
+    #[default]
+    Back,
+}
+
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum BlendMode
+{
+    #[default]
+    Opaque,
+
+    /// Blends by the fragment's alpha
+    Alpha,
+    Additive,
+}
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct DepthState
+{
+    pub test: bool,
+    pub write: bool,
+}
+
+
+impl Default for DepthState
+{
+    fn default() -> Self
+    {
+        Self {
+            test: true,
+            write: true,
+        }
+    }
+}
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BindingKind
+{
+    Uniform,
+    Storage,
+
+    /// A texture with its sampler.
+    Texture,
+}
+
+
+/// The shaders a binding is visible to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BindingStages
+{
+    Vertex,
+    Fragment,
+    Both,
+}
+
+
+/// A buffer or texture the shaders read at `binding` in descriptor set 0. Uniform buffers
+/// hold `size` bytes, storage buffers start at `size` and grow with their data.
+/// Textures have no size.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct BindingDesc
+{
+    pub binding: u32,
+    pub kind: BindingKind,
+    pub stages: BindingStages,
+    pub size: u32,
+}
+
+
+impl BindingDesc
+{
+    pub fn uniform(binding: u32, size: u32, stages: BindingStages) -> Self
+    {
+        Self {
+            binding,
+            kind: BindingKind::Uniform,
+            stages,
+            size,
+        }
+    }
+
+    pub fn storage(binding: u32, size: u32, stages: BindingStages) -> Self
+    {
+        Self {
+            binding,
+            kind: BindingKind::Storage,
+            stages,
+            size,
+        }
+    }
+
+    pub fn texture(binding: u32, stages: BindingStages) -> Self
+    {
+        Self {
+            binding,
+            kind: BindingKind::Texture,
+            stages,
+            size: 0,
+        }
+    }
+}
+
+
+/// Everything a graphics pipeline is built from. Backends cache pipelines by it,
+/// so materials with the same description share one.
+///
+/// Shaders get the camera's view projection and the draw's transform as two matrices
+/// in their push constants. Everything else is passed through the declared bindings,
+/// filled per material.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PipelineDesc
+{
+    pub vertex: ShaderStage,
+    pub fragment: ShaderStage,
+    pub vertex_layout: VertexLayout,
+    pub topology: Topology,
+    pub cull: CullMode,
+    pub blend: BlendMode,
+    pub depth: DepthState,
+    pub bindings: Vec<BindingDesc>,
+}
+
+
+impl PipelineDesc
+{
+    pub fn new(vertex: ShaderStage, fragment: ShaderStage) -> Self
+    {
+        Self {
+            vertex,
+            fragment,
+            vertex_layout: VertexLayout::default(),
+            topology: Topology::default(),
+            cull: CullMode::default(),
+            blend: BlendMode::default(),
+            depth: DepthState::default(),
+            bindings: vec![],
+        }
+    }
+
+    pub fn vertex_layout(mut self, layout: VertexLayout) -> Self
+    {
+        self.vertex_layout = layout;
+        self
+    }
+
+    pub fn topology(mut self, topology: Topology) -> Self
+    {
+        self.topology = topology;
+        self
+    }
+
+    pub fn cull(mut self, cull: CullMode) -> Self
+    {
+        self.cull = cull;
+        self
+    }
+
+    pub fn blend(mut self, blend: BlendMode) -> Self
+    {
+        self.blend = blend;
+        self
+    }
+
+    pub fn depth(mut self, depth: DepthState) -> Self
+    {
+        self.depth = depth;
+        self
+    }
+
+    pub fn binding(mut self, binding: BindingDesc) -> Self
+    {
+        self.bindings.push(binding);
+        self
+    }
+}
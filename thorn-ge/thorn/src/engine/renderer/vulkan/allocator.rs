@@ -0,0 +1,448 @@
+use super::logical_device::LogicalDevice;
+use crate::engine::renderer::MemoryStats;
+use crate::prelude::*;
+use ash::vk;
+use std::collections::HashMap;
+
+
+/// Size of the memory blocks allocations are placed in. Larger allocations get a block
+/// of their own, and small heaps get smaller blocks.
+pub const BLOCK_SIZE: vk::DeviceSize = 64 * 1024 * 1024;
+
+// Blocks used less than this are emptied by defragmentation, if possible
+const DEFRAG_USAGE: f64 = 0.5;
+
+
+/// Buffers and images never share a block, so the buffer image granularity does not apply.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ResourceKind
+{
+    Buffer,
+    Image,
+}
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Strategy
+{
+    /// Allocations are placed one after another and only reused once the whole block
+    /// is freed. For short lived data like staging buffers.
+    Linear,
+
+    /// Allocations take the first free range they fit in, freed ranges are merged again.
+    FreeList,
+}
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct PoolKey
+{
+    memtype: u32,
+    kind: ResourceKind,
+    strategy: Strategy,
+}
+
+
+/// A range of a memory block. Has to be freed through the allocator it came from.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Allocation
+{
+    pub memory: vk::DeviceMemory,
+    pub offset: vk::DeviceSize,
+    pub size: vk::DeviceSize,
+
+    // Host visible blocks stay mapped, this points to the allocation's offset
+    mapped: Option<*mut u8>,
+    key: Option<PoolKey>,
+    block: u64,
+}
+
+
+// The mapped memory is only written through whoever owns the allocation
+unsafe impl Send for Allocation {}
+unsafe impl Sync for Allocation {}
+
+
+impl Allocation
+{
+    pub fn mapped(&self) -> Option<*mut u8>
+    {
+        self.mapped
+    }
+}
+
+
+struct Block
+{
+    id: u64,
+    memory: vk::DeviceMemory,
+    size: vk::DeviceSize,
+    used: vk::DeviceSize,
+    count: usize,
+    mapped: Option<*mut u8>,
+
+    // Free ranges sorted by offset for free lists, the next offset for linear blocks
+    free: Vec<(vk::DeviceSize, vk::DeviceSize)>,
+    head: vk::DeviceSize,
+}
+
+
+// The mapping is only written through the allocations
+unsafe impl Send for Block {}
+unsafe impl Sync for Block {}
+
+
+impl Block
+{
+    fn alloc(
+        &mut self,
+        strategy: Strategy,
+        size: vk::DeviceSize,
+        alignment: vk::DeviceSize,
+    ) -> Option<vk::DeviceSize>
+    {
+        let offset = match strategy
+        {
+            Strategy::Linear =>
+            {
+                let offset = self.head.next_multiple_of(alignment);
+                if offset + size > self.size
+                {
+                    return None;
+                }
+
+                self.head = offset + size;
+                offset
+            }
+
+            Strategy::FreeList =>
+            {
+                let (i, offset) = self.free.iter().enumerate().find_map(|(i, (start, len))| {
+                    let offset = start.next_multiple_of(alignment);
+                    (offset + size <= start + len).then_some((i, offset))
+                })?;
+
+                // Keep the padding before and the rest after as free ranges
+                let (start, len) = self.free.remove(i);
+                let end = offset + size;
+
+                if end < start + len
+                {
+                    self.free.insert(i, (end, start + len - end));
+                }
+
+                if offset > start
+                {
+                    self.free.insert(i, (start, offset - start));
+                }
+
+                offset
+            }
+        };
+
+        self.used += size;
+        self.count += 1;
+        Some(offset)
+    }
+
+    fn free(&mut self, strategy: Strategy, offset: vk::DeviceSize, size: vk::DeviceSize)
+    {
+        self.used -= size;
+        self.count -= 1;
+
+        match strategy
+        {
+            Strategy::Linear if self.count == 0 => self.head = 0,
+            Strategy::Linear =>
+            {}
+
+            Strategy::FreeList =>
+            {
+                let i = self.free.partition_point(|(start, _)| *start < offset);
+                self.free.insert(i, (offset, size));
+
+                // Merge with the following range, then with the previous one
+                if i + 1 < self.free.len() && offset + size == self.free[i + 1].0
+                {
+                    self.free[i].1 += self.free.remove(i + 1).1;
+                }
+
+                if i > 0 && self.free[i - 1].0 + self.free[i - 1].1 == offset
+                {
+                    self.free[i - 1].1 += self.free.remove(i).1;
+                }
+            }
+        }
+    }
+
+    fn usage(&self) -> f64
+    {
+        self.used as f64 / self.size as f64
+    }
+}
+
+
+/// Sub-allocates device memory from large blocks, kept in pools per memory type,
+/// resource kind and strategy.
+pub struct Allocator
+{
+    pools: HashMap<PoolKey, Vec<Block>>,
+    next_block: u64,
+    device: Layer<LogicalDevice>,
+}
+
+
+impl Allocator
+{
+    pub fn new(reg: &LayerReg<()>) -> Self
+    {
+        log::info!("Vulkan memory allocator created");
+
+        Self {
+            pools: HashMap::new(),
+            next_block: 0,
+            device: reg.get_unchecked(),
+        }
+    }
+
+    pub fn allocate(
+        &mut self,
+        reqs: vk::MemoryRequirements,
+        flags: vk::MemoryPropertyFlags,
+        kind: ResourceKind,
+        strategy: Strategy,
+    ) -> ThResult<Allocation>
+    {
+        let memtype = self
+            .device
+            .read()
+            .unwrap()
+            .find_memtype_index(reqs.memory_type_bits, flags)?;
+
+        let key = PoolKey {
+            memtype,
+            kind,
+            strategy,
+        };
+
+        if let Some(allocation) = self.allocate_in(key, reqs, |_| true)
+        {
+            return Ok(allocation);
+        }
+
+        self.new_block(key, reqs.size)?;
+
+        self.allocate_in(key, reqs, |_| true)
+            .ok_or(ThError::RendererError(format!(
+                "Failed to allocate {} bytes in a new memory block",
+                reqs.size
+            )))
+    }
+
+    /// Frees the allocation's range. Its block is kept for later allocations.
+    pub fn free(&mut self, allocation: &Allocation)
+    {
+        let Some(key) = allocation.key
+        else
+        {
+            return;
+        };
+
+        let block = self
+            .pools
+            .get_mut(&key)
+            .and_then(|blocks| blocks.iter_mut().find(|b| b.id == allocation.block));
+
+        match block
+        {
+            Some(block) => block.free(key.strategy, allocation.offset, allocation.size),
+            None => log::warn!("Tried to free an allocation from an unknown memory block"),
+        }
+    }
+
+    /// Whether the allocation sits in a sparsely used block that could be emptied by
+    /// moving it into another one.
+    pub fn is_fragmented(&self, allocation: &Allocation) -> bool
+    {
+        let Some(key) = allocation.key.filter(|k| k.strategy == Strategy::FreeList)
+        else
+        {
+            return false;
+        };
+
+        let Some(blocks) = self.pools.get(&key)
+        else
+        {
+            return false;
+        };
+
+        blocks.len() > 1
+            && blocks
+                .iter()
+                .any(|b| b.id == allocation.block && b.usage() < DEFRAG_USAGE)
+    }
+
+    /// An allocation with the same requirements in a fuller block of the same pool,
+    /// for moving a fragmented allocation. Blocks are never created for this.
+    pub fn reallocate(
+        &mut self,
+        allocation: &Allocation,
+        reqs: vk::MemoryRequirements,
+    ) -> Option<Allocation>
+    {
+        let key = allocation.key?;
+        let used = self
+            .pools
+            .get(&key)?
+            .iter()
+            .find(|b| b.id == allocation.block)?
+            .used;
+
+        self.allocate_in(key, reqs, |b| b.id != allocation.block && b.used >= used)
+    }
+
+    /// Frees the device memory of blocks without allocations. Returns how many were freed.
+    pub fn release_empty_blocks(&mut self) -> usize
+    {
+        let device = &self.device.read().unwrap().logical_device;
+        let mut released = 0;
+
+        for blocks in self.pools.values_mut()
+        {
+            blocks.retain(|b| {
+                if b.count > 0
+                {
+                    return true;
+                }
+
+                unsafe { device.free_memory(b.memory, None) };
+                released += 1;
+                false
+            });
+        }
+
+        self.pools.retain(|_, blocks| !blocks.is_empty());
+        released
+    }
+
+    pub fn stats(&self) -> MemoryStats
+    {
+        let mut stats = MemoryStats::default();
+
+        for block in self.pools.values().flatten()
+        {
+            stats.used += block.used;
+            stats.reserved += block.size;
+            stats.blocks += 1;
+            stats.allocations += block.count;
+        }
+
+        stats
+    }
+
+    pub fn destroy(&mut self)
+    {
+        let stats = self.stats();
+        if stats.allocations > 0
+        {
+            log::warn!("{} memory allocations were not freed", stats.allocations);
+        }
+
+        let device = &self.device.read().unwrap().logical_device;
+
+        for block in self.pools.drain().flat_map(|(_, blocks)| blocks)
+        {
+            unsafe { device.free_memory(block.memory, None) };
+        }
+
+        log::info!("Vulkan memory allocator destroyed");
+    }
+
+    fn allocate_in(
+        &mut self,
+        key: PoolKey,
+        reqs: vk::MemoryRequirements,
+        usable: impl Fn(&Block) -> bool,
+    ) -> Option<Allocation>
+    {
+        let alignment = reqs.alignment.max(1);
+
+        self.pools
+            .get_mut(&key)?
+            .iter_mut()
+            .filter(|b| usable(b))
+            .find_map(|block| {
+                let offset = block.alloc(key.strategy, reqs.size, alignment)?;
+
+                Some(Allocation {
+                    memory: block.memory,
+                    offset,
+                    size: reqs.size,
+                    mapped: block.mapped.map(|ptr| unsafe { ptr.add(offset as usize) }),
+                    key: Some(key),
+                    block: block.id,
+                })
+            })
+    }
+
+    fn new_block(&mut self, key: PoolKey, min_size: vk::DeviceSize) -> ThResult<()>
+    {
+        let device = self.device.read().unwrap();
+        let size = BLOCK_SIZE
+            .min(device.heap_size(key.memtype) / 8)
+            .max(min_size);
+
+        let allocate_info = vk::MemoryAllocateInfo::default()
+            .allocation_size(size)
+            .memory_type_index(key.memtype);
+
+        let memory = unsafe { device.logical_device.allocate_memory(&allocate_info, None) }?;
+
+        // Memory can only be mapped once, so the whole block stays mapped
+        let mapped = match device
+            .memtype_flags(key.memtype)
+            .contains(vk::MemoryPropertyFlags::HOST_VISIBLE)
+        {
+            false => None,
+            true =>
+            {
+                let ptr = unsafe {
+                    device.logical_device.map_memory(
+                        memory,
+                        0,
+                        vk::WHOLE_SIZE,
+                        vk::MemoryMapFlags::empty(),
+                    )
+                };
+
+                match ptr
+                {
+                    Ok(ptr) => Some(ptr.cast()),
+                    Err(e) =>
+                    {
+                        unsafe { device.logical_device.free_memory(memory, None) };
+                        return Err(e.into());
+                    }
+                }
+            }
+        };
+
+        self.pools.entry(key).or_default().push(Block {
+            id: self.next_block,
+            memory,
+            size,
+            used: 0,
+            count: 0,
+            mapped,
+            free: vec![(0, size)],
+            head: 0,
+        });
+
+        self.next_block += 1;
+        log::debug!("Vulkan memory block of {size} bytes allocated for {key:?}");
+        Ok(())
+    }
+}
+
+
+impl LayerDispatch<()> for Allocator {}
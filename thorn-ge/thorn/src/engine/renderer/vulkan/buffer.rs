@@ -0,0 +1,362 @@
+use super::{
+    allocator::{Allocation, Allocator, ResourceKind, Strategy},
+    command_pool::CommandPools,
+    logical_device::LogicalDevice,
+    physical_device::PhysicalDevice,
+};
+use crate::prelude::*;
+use ash::vk;
+
+
+/// A buffer in memory from the allocator. Buffers in host visible memory are mapped,
+/// so dynamic data can be written every frame.
+pub struct VkBuffer
+{
+    pub buffer: vk::Buffer,
+    pub allocation: Allocation,
+    pub size: vk::DeviceSize,
+    pub usage: vk::BufferUsageFlags,
+    device: Layer<LogicalDevice>,
+    allocator: Layer<Allocator>,
+}
+
+
+impl VkBuffer
+{
+    pub fn new(
+        reg: &LayerReg<()>,
+        size: vk::DeviceSize,
+        usage: vk::BufferUsageFlags,
+        memflags: vk::MemoryPropertyFlags,
+    ) -> ThResult<Self>
+    {
+        Self::create(reg, size, usage, memflags, Strategy::FreeList)
+    }
+
+    /// A host visible buffer for data that changes often, like per frame uniforms.
+    pub fn new_mapped(
+        reg: &LayerReg<()>,
+        size: vk::DeviceSize,
+        usage: vk::BufferUsageFlags,
+    ) -> ThResult<Self>
+    {
+        Self::new(
+            reg,
+            size,
+            usage,
+            vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
+        )
+    }
+
+    /// A host visible buffer holding `data`, to copy it into device local memory.
+    /// Staging buffers only live for the upload, so they come from linear blocks.
+    pub fn new_staging(reg: &LayerReg<()>, data: &[u8]) -> ThResult<Self>
+    {
+        let mut staging = Self::create(
+            reg,
+            data.len() as vk::DeviceSize,
+            vk::BufferUsageFlags::TRANSFER_SRC,
+            vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
+            Strategy::Linear,
+        )?;
+
+        if let Err(e) = staging.write(0, data)
+        {
+            staging.destroy();
+            return Err(e);
+        }
+
+        Ok(staging)
+    }
+
+    /// A device local buffer filled with `data` through a staging buffer on the transfer
+    /// queue. Blocks until the upload is done.
+    pub fn new_device_local(
+        reg: &LayerReg<()>,
+        usage: vk::BufferUsageFlags,
+        data: &[u8],
+    ) -> ThResult<Self>
+    {
+        let size = data.len() as vk::DeviceSize;
+        let mut staging = Self::new_staging(reg, data)?;
+
+        // Can be copied from, so defragmentation can move it
+        let buffer = Self::new(
+            reg,
+            size,
+            usage | vk::BufferUsageFlags::TRANSFER_DST | vk::BufferUsageFlags::TRANSFER_SRC,
+            vk::MemoryPropertyFlags::DEVICE_LOCAL,
+        );
+
+        let result = buffer.and_then(|mut buffer| {
+            match buffer.copy_from(reg, &staging)
+            {
+                Ok(_) => Ok(buffer),
+                Err(e) =>
+                {
+                    buffer.destroy();
+                    Err(e)
+                }
+            }
+        });
+
+        staging.destroy();
+        result
+    }
+
+    fn create(
+        reg: &LayerReg<()>,
+        size: vk::DeviceSize,
+        usage: vk::BufferUsageFlags,
+        memflags: vk::MemoryPropertyFlags,
+        strategy: Strategy,
+    ) -> ThResult<Self>
+    {
+        let buffer = create_buffer(reg, size, usage)?;
+        let device = reg.get_unchecked::<LogicalDevice>();
+        let allocator = reg.get_unchecked::<Allocator>();
+
+        let reqs = unsafe {
+            device
+                .read()
+                .unwrap()
+                .logical_device
+                .get_buffer_memory_requirements(buffer)
+        };
+
+        let allocation =
+            allocator
+                .write()
+                .unwrap()
+                .allocate(reqs, memflags, ResourceKind::Buffer, strategy);
+
+        let mut me = Self {
+            buffer,
+            allocation: Allocation::default(),
+            size,
+            usage,
+            device,
+            allocator,
+        };
+
+        match allocation.and_then(|allocation| {
+            me.allocation = allocation;
+            me.bind()
+        })
+        {
+            Ok(_) => Ok(me),
+            Err(e) =>
+            {
+                me.destroy();
+                Err(e)
+            }
+        }
+    }
+
+    fn bind(&self) -> ThResult<()>
+    {
+        unsafe {
+            self.device
+                .read()
+                .unwrap()
+                .logical_device
+                .bind_buffer_memory(self.buffer, self.allocation.memory, self.allocation.offset)
+        }?;
+
+        Ok(())
+    }
+
+    /// Moves a device local buffer out of a sparsely used memory block, so the block can
+    /// be freed. Expects the device to be idle. Returns whether the buffer was moved.
+    pub fn relocate(&mut self, reg: &LayerReg<()>) -> ThResult<bool>
+    {
+        if !self.usage.contains(vk::BufferUsageFlags::TRANSFER_SRC)
+            || !self
+                .allocator
+                .read()
+                .unwrap()
+                .is_fragmented(&self.allocation)
+        {
+            return Ok(false);
+        }
+
+        let buffer = create_buffer(reg, self.size, self.usage)?;
+        let device = self.device.clone();
+        let reqs = unsafe {
+            device
+                .read()
+                .unwrap()
+                .logical_device
+                .get_buffer_memory_requirements(buffer)
+        };
+
+        let allocation = self
+            .allocator
+            .write()
+            .unwrap()
+            .reallocate(&self.allocation, reqs);
+
+        let Some(allocation) = allocation
+        else
+        {
+            unsafe {
+                device
+                    .read()
+                    .unwrap()
+                    .logical_device
+                    .destroy_buffer(buffer, None)
+            };
+            return Ok(false);
+        };
+
+        let mut moved = Self {
+            buffer,
+            allocation,
+            size: self.size,
+            usage: self.usage,
+            device,
+            allocator: self.allocator.clone(),
+        };
+
+        if let Err(e) = moved.bind().and_then(|_| moved.copy_from(reg, self))
+        {
+            moved.destroy();
+            return Err(e);
+        }
+
+        std::mem::swap(self, &mut moved);
+        moved.destroy();
+        Ok(true)
+    }
+
+    /// Copies the whole of `src` to the start of this buffer on the transfer queue.
+    /// Blocks until the copy is done.
+    pub fn copy_from(&mut self, reg: &LayerReg<()>, src: &VkBuffer) -> ThResult<()>
+    {
+        if src.size > self.size
+        {
+            return Err(ThError::RendererError(format!(
+                "Can not copy {} bytes into a buffer of {} bytes",
+                src.size, self.size
+            )));
+        }
+
+        let queue = reg_read!(reg, LogicalDevice)
+            .transfer_queue
+            .ok_or(ThError::RendererError("No Transfer Queue".into()))?;
+
+        let mut cbuffer = reg_read!(reg, CommandPools)
+            .transfer
+            .new_buffer_single_use()?;
+
+        let region = vk::BufferCopy::default().size(src.size);
+
+        unsafe {
+            self.device.read().unwrap().logical_device.cmd_copy_buffer(
+                cbuffer.buffer,
+                src.buffer,
+                self.buffer,
+                &[region],
+            );
+        }
+
+        cbuffer.end_single_use(queue);
+        Ok(())
+    }
+
+    /// Writes into a mapped buffer at `offset` bytes.
+    pub fn write(&mut self, offset: vk::DeviceSize, data: &[u8]) -> ThResult<()>
+    {
+        let Some(mapped) = self.allocation.mapped()
+        else
+        {
+            return Err(ThError::RendererError(
+                "Tried to write to a buffer that is not mapped".into(),
+            ));
+        };
+
+        if offset + data.len() as vk::DeviceSize > self.size
+        {
+            return Err(ThError::RendererError(format!(
+                "Write of {} bytes at {offset} overflows a buffer of {} bytes",
+                data.len(),
+                self.size
+            )));
+        }
+
+        unsafe {
+            std::ptr::copy_nonoverlapping(data.as_ptr(), mapped.add(offset as usize), data.len());
+        }
+
+        Ok(())
+    }
+
+    pub fn is_mapped(&self) -> bool
+    {
+        self.allocation.mapped().is_some()
+    }
+
+    pub fn destroy(&mut self)
+    {
+        unsafe {
+            self.device
+                .read()
+                .unwrap()
+                .logical_device
+                .destroy_buffer(self.buffer, None)
+        };
+
+        self.allocator.write().unwrap().free(&self.allocation);
+        self.allocation = Allocation::default();
+    }
+}
+
+
+fn create_buffer(
+    reg: &LayerReg<()>,
+    size: vk::DeviceSize,
+    usage: vk::BufferUsageFlags,
+) -> ThResult<vk::Buffer>
+{
+    if size == 0
+    {
+        return Err(ThError::RendererError(
+            "Tried to create an empty buffer".into(),
+        ));
+    }
+
+    let (graphics, transfer) = {
+        let physical = reg.get_unchecked::<PhysicalDevice>();
+        let physical = physical.read().unwrap();
+        (physical.props.graphics_queue, physical.props.transfer_queue)
+    };
+
+    // Uploads run on the transfer queue, so buffers are shared with it if it is separate
+    let families = [graphics, transfer]
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>();
+
+    let create_info = vk::BufferCreateInfo::default().size(size).usage(usage);
+
+    let create_info = match families.len() > 1 && families[0] != families[1]
+    {
+        true =>
+        {
+            create_info
+                .sharing_mode(vk::SharingMode::CONCURRENT)
+                .queue_family_indices(&families)
+        }
+        false => create_info.sharing_mode(vk::SharingMode::EXCLUSIVE),
+    };
+
+    let buffer = unsafe {
+        reg.get_unchecked::<LogicalDevice>()
+            .read()
+            .unwrap()
+            .logical_device
+            .create_buffer(&create_info, None)
+    }?;
+
+    Ok(buffer)
+}
@@ -117,6 +117,9 @@ impl CommandPool
 pub struct CommandPools
 {
     pub graphics: CommandPool,
+
+    // Uploads, on the dedicated transfer family if there is one
+    pub transfer: CommandPool,
 }
 
 
@@ -124,18 +127,31 @@ impl CommandPools
 {
     pub fn new(reg: &LayerReg<()>) -> ThResult<Self>
     {
-        let graphics_family = reg
-            .get_unchecked::<PhysicalDevice>()
-            .read()
-            .unwrap()
-            .props
+        let physical = reg.get_unchecked::<PhysicalDevice>();
+        let physical = physical.read().unwrap();
+        let props = &physical.props;
+
+        let graphics_family = props
             .graphics_queue
             .ok_or(ThError::RendererError("No Graphics Queue".into()))?;
 
-        let me = Self {
-            graphics: CommandPool::new(reg, graphics_family)?,
+        let transfer_family = props
+            .transfer_queue
+            .ok_or(ThError::RendererError("No Transfer Queue".into()))?;
+
+        let mut graphics = CommandPool::new(reg, graphics_family)?;
+        let transfer = match CommandPool::new(reg, transfer_family)
+        {
+            Ok(pool) => pool,
+            Err(e) =>
+            {
+                graphics.destroy();
+                return Err(e);
+            }
         };
 
+        let me = Self { graphics, transfer };
+
         log::info!("Vulkan command pools created");
 
         Ok(me)
@@ -144,6 +160,7 @@ impl CommandPools
     pub fn destroy(&mut self)
     {
         self.graphics.destroy();
+        self.transfer.destroy();
         log::info!("Vulkan command pools destroyed");
     }
 }
@@ -0,0 +1,560 @@
+use super::{buffer::VkBuffer, logical_device::LogicalDevice, texture::Textures};
+use crate::engine::renderer::{
+    MaterialId,
+    TextureId,
+    pipeline::{BindingDesc, BindingKind, BindingStages, PipelineDesc},
+};
+use crate::prelude::*;
+use ash::vk;
+use std::collections::{BTreeMap, HashMap};
+
+
+// Sets per descriptor pool. More pools are created as they fill up.
+const SETS_PER_POOL: u32 = 64;
+
+
+/// A material's pipeline and the data and textures for its bindings. Both are versioned,
+/// so every frame's descriptor set picks up changes once it is used again.
+pub struct Material
+{
+    pub desc: PipelineDesc,
+    data: BTreeMap<u32, Vec<u8>>,
+    textures: BTreeMap<u32, TextureId>,
+    version: u64,
+}
+
+
+impl Material
+{
+    pub fn new(desc: PipelineDesc) -> ThResult<Self>
+    {
+        for (i, binding) in desc.bindings.iter().enumerate()
+        {
+            if binding.kind != BindingKind::Texture && binding.size == 0
+            {
+                return Err(ThError::RendererError(format!(
+                    "Binding {} has a size of 0",
+                    binding.binding
+                )));
+            }
+
+            if desc.bindings[..i]
+                .iter()
+                .any(|b| b.binding == binding.binding)
+            {
+                return Err(ThError::RendererError(format!(
+                    "Binding {} is declared twice",
+                    binding.binding
+                )));
+            }
+        }
+
+        Ok(Self {
+            desc,
+            data: BTreeMap::new(),
+            textures: BTreeMap::new(),
+            version: 0,
+        })
+    }
+
+    pub fn set_data(&mut self, binding: u32, data: Vec<u8>) -> ThResult<()>
+    {
+        let Some(desc) = self.desc.bindings.iter().find(|b| b.binding == binding)
+        else
+        {
+            return Err(ThError::RendererError(format!(
+                "The material has no binding {binding}"
+            )));
+        };
+
+        if desc.kind == BindingKind::Texture
+        {
+            return Err(ThError::RendererError(format!(
+                "Binding {binding} is a texture, it takes no data"
+            )));
+        }
+
+        if desc.kind == BindingKind::Uniform && data.len() > desc.size as usize
+        {
+            return Err(ThError::RendererError(format!(
+                "{} bytes do not fit into uniform binding {binding} of {} bytes",
+                data.len(),
+                desc.size
+            )));
+        }
+
+        self.data.insert(binding, data);
+        self.version += 1;
+        Ok(())
+    }
+
+    pub fn set_texture(&mut self, binding: u32, texture: TextureId) -> ThResult<()>
+    {
+        if !self
+            .desc
+            .bindings
+            .iter()
+            .any(|b| b.binding == binding && b.kind == BindingKind::Texture)
+        {
+            return Err(ThError::RendererError(format!(
+                "The material has no texture binding {binding}"
+            )));
+        }
+
+        self.textures.insert(binding, texture);
+        self.version += 1;
+        Ok(())
+    }
+
+    /// Unsets a destroyed texture, so the material is not drawn until it gets another one.
+    pub fn remove_texture(&mut self, texture: TextureId)
+    {
+        let count = self.textures.len();
+        self.textures.retain(|_, t| *t != texture);
+
+        if self.textures.len() != count
+        {
+            self.version += 1;
+        }
+    }
+}
+
+
+/// Descriptor set layouts by the bindings they are built from, shared by all targets.
+pub struct DescriptorLayouts
+{
+    layouts: HashMap<Vec<BindingDesc>, vk::DescriptorSetLayout>,
+    device: Layer<LogicalDevice>,
+}
+
+
+impl DescriptorLayouts
+{
+    pub fn new(reg: &LayerReg<()>) -> Self
+    {
+        Self {
+            layouts: HashMap::new(),
+            device: reg.get_unchecked(),
+        }
+    }
+
+    pub fn get(&mut self, bindings: &[BindingDesc]) -> ThResult<vk::DescriptorSetLayout>
+    {
+        if let Some(layout) = self.layouts.get(bindings)
+        {
+            return Ok(*layout);
+        }
+
+        let layout_bindings = bindings
+            .iter()
+            .map(|b| {
+                vk::DescriptorSetLayoutBinding::default()
+                    .binding(b.binding)
+                    .descriptor_type(descriptor_type(b.kind))
+                    .descriptor_count(1)
+                    .stage_flags(match b.stages
+                    {
+                        BindingStages::Vertex => vk::ShaderStageFlags::VERTEX,
+                        BindingStages::Fragment => vk::ShaderStageFlags::FRAGMENT,
+                        BindingStages::Both =>
+                        {
+                            vk::ShaderStageFlags::VERTEX | vk::ShaderStageFlags::FRAGMENT
+                        }
+                    })
+            })
+            .collect::<Vec<_>>();
+
+        let create_info = vk::DescriptorSetLayoutCreateInfo::default().bindings(&layout_bindings);
+
+        let layout = unsafe {
+            self.device
+                .read()
+                .unwrap()
+                .logical_device
+                .create_descriptor_set_layout(&create_info, None)
+        }?;
+
+        self.layouts.insert(bindings.to_vec(), layout);
+        Ok(layout)
+    }
+
+    pub fn destroy(&mut self)
+    {
+        let device = &self.device.read().unwrap().logical_device;
+
+        for (_, layout) in self.layouts.drain()
+        {
+            unsafe { device.destroy_descriptor_set_layout(layout, None) };
+        }
+
+        log::info!("Vulkan descriptor set layouts destroyed");
+    }
+}
+
+
+impl LayerDispatch<()> for DescriptorLayouts {}
+
+
+struct FrameSet
+{
+    set: vk::DescriptorSet,
+    pool: vk::DescriptorPool,
+    buffers: BTreeMap<u32, VkBuffer>,
+
+    // The part of each buffer the set describes, the size of the data written last
+    ranges: BTreeMap<u32, vk::DeviceSize>,
+    version: Option<u64>,
+}
+
+
+/// Descriptor sets for one render target, one per material and buffered frame, so a set
+/// is only updated once the frame that used it last has finished.
+pub struct DescriptorSets
+{
+    pools: Vec<vk::DescriptorPool>,
+    sets: HashMap<(MaterialId, usize), FrameSet>,
+    buffered_frames: usize,
+    device: Layer<LogicalDevice>,
+    layouts: Layer<DescriptorLayouts>,
+}
+
+
+impl DescriptorSets
+{
+    pub fn new(reg: &LayerReg<()>, buffered_frames: u32) -> Self
+    {
+        Self {
+            pools: vec![],
+            sets: HashMap::new(),
+            buffered_frames: buffered_frames as usize,
+            device: reg.get_unchecked(),
+            layouts: reg.get_unchecked(),
+        }
+    }
+
+    /// The material's set for the frame, with the material's current data.
+    /// Expects the frame's fence to be signaled.
+    pub fn get(
+        &mut self,
+        reg: &LayerReg<()>,
+        id: MaterialId,
+        material: &Material,
+        frame: usize,
+    ) -> ThResult<vk::DescriptorSet>
+    {
+        if frame >= self.buffered_frames
+        {
+            return Err(ThError::RendererError(format!(
+                "Frame {frame} is not buffered, only {} are",
+                self.buffered_frames
+            )));
+        }
+
+        if !self.sets.contains_key(&(id, frame))
+        {
+            let layout = self.layouts.write().unwrap().get(&material.desc.bindings)?;
+            let (set, pool) = self.allocate(layout)?;
+
+            self.sets.insert(
+                (id, frame),
+                FrameSet {
+                    set,
+                    pool,
+                    buffers: BTreeMap::new(),
+                    ranges: BTreeMap::new(),
+                    version: None,
+                },
+            );
+        }
+
+        let frame_set = self.sets.get_mut(&(id, frame)).unwrap();
+        if frame_set.version != Some(material.version)
+        {
+            Self::update(&self.device, reg, frame_set, material)?;
+        }
+
+        Ok(frame_set.set)
+    }
+
+    /// Drops the sets of frames that are no longer buffered. Expects the device to be idle.
+    pub fn set_buffered_frames(&mut self, buffered_frames: u32)
+    {
+        self.buffered_frames = buffered_frames as usize;
+
+        let removed = self
+            .sets
+            .extract_if(|(_, frame), _| *frame >= self.buffered_frames)
+            .collect::<Vec<_>>();
+
+        for (_, frame_set) in removed
+        {
+            self.free(frame_set);
+        }
+    }
+
+    pub fn destroy(&mut self)
+    {
+        for (_, frame_set) in std::mem::take(&mut self.sets)
+        {
+            self.free(frame_set);
+        }
+
+        let device = &self.device.read().unwrap().logical_device;
+
+        for pool in self.pools.drain(..)
+        {
+            unsafe { device.destroy_descriptor_pool(pool, None) };
+        }
+
+        log::info!("Vulkan descriptor sets destroyed");
+    }
+
+    // Writes the material's data, replacing buffers that are missing or too small,
+    // and its textures. Fails if a texture binding has no texture.
+    fn update(
+        device: &Layer<LogicalDevice>,
+        reg: &LayerReg<()>,
+        frame_set: &mut FrameSet,
+        material: &Material,
+    ) -> ThResult<()>
+    {
+        // Textures first, so nothing is replaced if one is missing
+        let mut image_infos = vec![];
+        for binding in material
+            .desc
+            .bindings
+            .iter()
+            .filter(|b| b.kind == BindingKind::Texture)
+        {
+            let texture = material.textures.get(&binding.binding).and_then(|id| {
+                reg.get::<Textures>()?
+                    .read()
+                    .unwrap()
+                    .get(*id)
+                    .map(|t| t.descriptor_info())
+            });
+
+            let Some(texture) = texture
+            else
+            {
+                return Err(ThError::RendererError(format!(
+                    "No texture for binding {}",
+                    binding.binding
+                )));
+            };
+
+            image_infos.push((binding.binding, [texture]));
+        }
+
+        let mut infos = vec![];
+        for binding in material
+            .desc
+            .bindings
+            .iter()
+            .filter(|b| b.kind != BindingKind::Texture)
+        {
+            let data = material.data.get(&binding.binding);
+            let size = data.map_or(0, Vec::len).max(binding.size as usize) as vk::DeviceSize;
+
+            if size == 0
+            {
+                return Err(ThError::RendererError(format!(
+                    "No data for binding {}",
+                    binding.binding
+                )));
+            }
+
+            let buffer = match frame_set.buffers.get_mut(&binding.binding)
+            {
+                Some(buffer) if buffer.size >= size => buffer,
+                _ =>
+                {
+                    let usage = match binding.kind
+                    {
+                        BindingKind::Uniform => vk::BufferUsageFlags::UNIFORM_BUFFER,
+                        _ => vk::BufferUsageFlags::STORAGE_BUFFER,
+                    };
+
+                    let buffer = VkBuffer::new_mapped(reg, size, usage)?;
+
+                    if let Some(mut old) = frame_set.buffers.insert(binding.binding, buffer)
+                    {
+                        old.destroy();
+                    }
+
+                    frame_set.ranges.remove(&binding.binding);
+                    frame_set.buffers.get_mut(&binding.binding).unwrap()
+                }
+            };
+
+            // A reused buffer may be larger than the data, shaders must not see what is left
+            // of older data behind it
+            if frame_set.ranges.get(&binding.binding) != Some(&size)
+            {
+                infos.push((
+                    binding.binding,
+                    descriptor_type(binding.kind),
+                    [vk::DescriptorBufferInfo::default()
+                        .buffer(buffer.buffer)
+                        .offset(0)
+                        .range(size)],
+                ));
+            }
+
+            if let Some(data) = data.filter(|d| !d.is_empty())
+            {
+                buffer.write(0, data)?;
+            }
+        }
+
+        if !infos.is_empty() || !image_infos.is_empty()
+        {
+            let buffer_writes = infos.iter().map(|(binding, kind, info)| {
+                vk::WriteDescriptorSet::default()
+                    .dst_set(frame_set.set)
+                    .dst_binding(*binding)
+                    .descriptor_type(*kind)
+                    .buffer_info(info)
+            });
+
+            let image_writes = image_infos.iter().map(|(binding, info)| {
+                vk::WriteDescriptorSet::default()
+                    .dst_set(frame_set.set)
+                    .dst_binding(*binding)
+                    .descriptor_type(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+                    .image_info(info)
+            });
+
+            let writes = buffer_writes.chain(image_writes).collect::<Vec<_>>();
+
+            unsafe {
+                device
+                    .read()
+                    .unwrap()
+                    .logical_device
+                    .update_descriptor_sets(&writes, &[])
+            };
+
+            let described = infos
+                .iter()
+                .map(|(binding, _, [info])| (*binding, info.range));
+            frame_set.ranges.extend(described);
+        }
+
+        frame_set.version = Some(material.version);
+        Ok(())
+    }
+
+    fn allocate(
+        &mut self,
+        layout: vk::DescriptorSetLayout,
+    ) -> ThResult<(vk::DescriptorSet, vk::DescriptorPool)>
+    {
+        let layouts = [layout];
+
+        if let Some(pool) = self.pools.last().copied()
+        {
+            let allocate_info = vk::DescriptorSetAllocateInfo::default()
+                .descriptor_pool(pool)
+                .set_layouts(&layouts);
+
+            let sets = unsafe {
+                self.device
+                    .read()
+                    .unwrap()
+                    .logical_device
+                    .allocate_descriptor_sets(&allocate_info)
+            };
+
+            match sets
+            {
+                Ok(sets) => return Ok((sets[0], pool)),
+
+                // Full, continue with a new pool
+                Err(vk::Result::ERROR_OUT_OF_POOL_MEMORY | vk::Result::ERROR_FRAGMENTED_POOL) =>
+                {}
+
+                Err(e) => return Err(e.into()),
+            }
+        }
+
+        let pool = self.new_pool()?;
+        let allocate_info = vk::DescriptorSetAllocateInfo::default()
+            .descriptor_pool(pool)
+            .set_layouts(&layouts);
+
+        let sets = unsafe {
+            self.device
+                .read()
+                .unwrap()
+                .logical_device
+                .allocate_descriptor_sets(&allocate_info)
+        }?;
+
+        Ok((sets[0], pool))
+    }
+
+    fn new_pool(&mut self) -> ThResult<vk::DescriptorPool>
+    {
+        let sizes = [
+            vk::DescriptorPoolSize::default()
+                .ty(vk::DescriptorType::UNIFORM_BUFFER)
+                .descriptor_count(SETS_PER_POOL * 4),
+            vk::DescriptorPoolSize::default()
+                .ty(vk::DescriptorType::STORAGE_BUFFER)
+                .descriptor_count(SETS_PER_POOL * 2),
+            vk::DescriptorPoolSize::default()
+                .ty(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+                .descriptor_count(SETS_PER_POOL * 4),
+        ];
+
+        // Sets are freed when the buffered frame count drops
+        let create_info = vk::DescriptorPoolCreateInfo::default()
+            .flags(vk::DescriptorPoolCreateFlags::FREE_DESCRIPTOR_SET)
+            .max_sets(SETS_PER_POOL)
+            .pool_sizes(&sizes);
+
+        let pool = unsafe {
+            self.device
+                .read()
+                .unwrap()
+                .logical_device
+                .create_descriptor_pool(&create_info, None)
+        }?;
+
+        self.pools.push(pool);
+        log::info!("Vulkan descriptor pool created");
+        Ok(pool)
+    }
+
+    fn free(&self, mut frame_set: FrameSet)
+    {
+        for buffer in frame_set.buffers.values_mut()
+        {
+            buffer.destroy();
+        }
+
+        unsafe {
+            let _ = self
+                .device
+                .read()
+                .unwrap()
+                .logical_device
+                .free_descriptor_sets(frame_set.pool, &[frame_set.set]);
+        }
+    }
+}
+
+
+impl LayerDispatch<()> for DescriptorSets {}
+
+
+fn descriptor_type(kind: BindingKind) -> vk::DescriptorType
+{
+    match kind
+    {
+        BindingKind::Uniform => vk::DescriptorType::UNIFORM_BUFFER,
+        BindingKind::Storage => vk::DescriptorType::STORAGE_BUFFER,
+        BindingKind::Texture => vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+    }
+}
@@ -2,7 +2,10 @@ use ash::vk;
 
 use crate::{error::ThResult, layer::Layer};
 
-use super::logical_device::LogicalDevice;
+use super::{
+    allocator::{Allocation, Allocator, ResourceKind, Strategy},
+    logical_device::LogicalDevice,
+};
 
 
 #[derive(Default)]
@@ -13,7 +16,7 @@ pub struct VkImage2D
     pub width: u32,
     pub height: u32,
     pub format: vk::Format,
-    pub memory: vk::DeviceMemory,
+    pub allocation: Allocation,
 }
 
 
@@ -22,6 +25,7 @@ impl VkImage2D
     #[allow(clippy::too_many_arguments)]
     pub fn new(
         device: Layer<LogicalDevice>,
+        allocator: Layer<Allocator>,
         width: u32,
         height: u32,
         format: vk::Format,
@@ -33,6 +37,7 @@ impl VkImage2D
     {
         let mut me = Self::new_image_only(
             device.clone(),
+            allocator,
             width,
             height,
             format,
@@ -46,8 +51,10 @@ impl VkImage2D
         Ok(me)
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub fn new_image_only(
         device: Layer<LogicalDevice>,
+        allocator: Layer<Allocator>,
         width: u32,
         height: u32,
         format: vk::Format,
@@ -76,21 +83,41 @@ impl VkImage2D
 
         let image = unsafe { device.logical_device.create_image(&img_create_info, None) }?;
         let memreqs = unsafe { device.logical_device.get_image_memory_requirements(image) };
-        let memtype: u32 = device.find_memtype_index(memreqs.memory_type_bits, memflags)?;
-
-        let allocate_info = vk::MemoryAllocateInfo::default()
-            .allocation_size(memreqs.size)
-            .memory_type_index(memtype);
+        let allocation = allocator.write().unwrap().allocate(
+            memreqs,
+            memflags,
+            ResourceKind::Image,
+            Strategy::FreeList,
+        );
 
-        let memory = unsafe { device.logical_device.allocate_memory(&allocate_info, None) }?;
-        unsafe { device.logical_device.bind_image_memory(image, memory, 0) }?;
+        let allocation = match allocation
+        {
+            Ok(allocation) => allocation,
+            Err(e) =>
+            {
+                unsafe { device.logical_device.destroy_image(image, None) };
+                return Err(e);
+            }
+        };
+
+        let bound = unsafe {
+            device
+                .logical_device
+                .bind_image_memory(image, allocation.memory, allocation.offset)
+        };
 
+        if let Err(e) = bound
+        {
+            unsafe { device.logical_device.destroy_image(image, None) };
+            allocator.write().unwrap().free(&allocation);
+            return Err(e.into());
+        }
 
         Ok(Self {
             image,
             width,
             height,
-            memory,
+            allocation,
             format,
             view: None,
         })
@@ -126,7 +153,7 @@ impl VkImage2D
         Ok(())
     }
 
-    pub fn destroy(&mut self, device: Layer<LogicalDevice>)
+    pub fn destroy(&mut self, device: Layer<LogicalDevice>, allocator: Layer<Allocator>)
     {
         let device = &device.read().unwrap().logical_device;
 
@@ -135,7 +162,9 @@ impl VkImage2D
             unsafe { device.destroy_image_view(view, None) };
         }
 
-        unsafe { device.free_memory(self.memory, None) };
         unsafe { device.destroy_image(self.image, None) };
+        allocator.write().unwrap().free(&self.allocation);
+        self.allocation = Allocation::default();
+        self.image = vk::Image::null();
     }
 }
@@ -141,6 +141,17 @@ impl LogicalDevice
         ))
     }
 
+    pub fn memtype_flags(&self, memtype: u32) -> vk::MemoryPropertyFlags
+    {
+        self.memory.memory_types[memtype as usize].property_flags
+    }
+
+    pub fn heap_size(&self, memtype: u32) -> vk::DeviceSize
+    {
+        let heap = self.memory.memory_types[memtype as usize].heap_index;
+        self.memory.memory_heaps[heap as usize].size
+    }
+
     pub fn destroy(&mut self)
     {
         unsafe {
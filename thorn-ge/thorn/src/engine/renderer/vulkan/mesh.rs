@@ -0,0 +1,196 @@
+use super::{buffer::VkBuffer, logical_device::LogicalDevice};
+use crate::engine::renderer::{MeshId, api::MeshData, as_bytes};
+use crate::prelude::*;
+use ash::vk;
+use std::collections::BTreeMap;
+
+
+/// A mesh's vertices and indices in device local memory.
+pub struct Mesh
+{
+    vertices: Option<VkBuffer>,
+    indices: Option<VkBuffer>,
+    vertex_count: u32,
+    index_count: u32,
+}
+
+
+impl Mesh
+{
+    pub fn new(reg: &LayerReg<()>, data: &MeshData) -> ThResult<Self>
+    {
+        let vertices = match data.vertices.is_empty()
+        {
+            true => None,
+            false =>
+            {
+                Some(VkBuffer::new_device_local(
+                    reg,
+                    vk::BufferUsageFlags::VERTEX_BUFFER,
+                    &data.vertices,
+                )?)
+            }
+        };
+
+        let indices = match data.indices.is_empty()
+        {
+            true => None,
+            false =>
+            {
+                let indices = VkBuffer::new_device_local(
+                    reg,
+                    vk::BufferUsageFlags::INDEX_BUFFER,
+                    as_bytes(&data.indices),
+                );
+
+                match indices
+                {
+                    Ok(indices) => Some(indices),
+                    Err(e) =>
+                    {
+                        if let Some(mut vertices) = vertices
+                        {
+                            vertices.destroy();
+                        }
+
+                        return Err(e);
+                    }
+                }
+            }
+        };
+
+        Ok(Self {
+            vertices,
+            indices,
+            vertex_count: data.vertex_count,
+            index_count: data.indices.len() as u32,
+        })
+    }
+
+    /// Whether the mesh has a vertex buffer, generated meshes have none.
+    pub fn has_vertices(&self) -> bool
+    {
+        self.vertices.is_some()
+    }
+
+    /// Records the draw. Expects a pipeline to be bound.
+    pub fn draw(&self, device: &ash::Device, cbuffer: vk::CommandBuffer)
+    {
+        unsafe {
+            if let Some(vertices) = &self.vertices
+            {
+                device.cmd_bind_vertex_buffers(cbuffer, 0, &[vertices.buffer], &[0]);
+            }
+
+            match &self.indices
+            {
+                Some(indices) =>
+                {
+                    device.cmd_bind_index_buffer(cbuffer, indices.buffer, 0, vk::IndexType::UINT32);
+                    device.cmd_draw_indexed(cbuffer, self.index_count, 1, 0, 0, 0);
+                }
+
+                None => device.cmd_draw(cbuffer, self.vertex_count, 1, 0, 0),
+            }
+        }
+    }
+
+    /// Expects the device to be idle. Returns how many buffers were moved.
+    pub fn relocate(&mut self, reg: &LayerReg<()>) -> ThResult<usize>
+    {
+        let mut moved = 0;
+
+        for buffer in [&mut self.vertices, &mut self.indices]
+            .into_iter()
+            .flatten()
+        {
+            moved += buffer.relocate(reg)? as usize;
+        }
+
+        Ok(moved)
+    }
+
+    pub fn destroy(&mut self)
+    {
+        if let Some(mut vertices) = self.vertices.take()
+        {
+            vertices.destroy();
+        }
+
+        if let Some(mut indices) = self.indices.take()
+        {
+            indices.destroy();
+        }
+    }
+}
+
+
+/// Every uploaded mesh, shared by all render targets.
+pub struct Meshes
+{
+    meshes: BTreeMap<MeshId, Mesh>,
+    device: Layer<LogicalDevice>,
+}
+
+
+impl Meshes
+{
+    pub fn new(reg: &LayerReg<()>) -> Self
+    {
+        Self {
+            meshes: BTreeMap::new(),
+            device: reg.get_unchecked(),
+        }
+    }
+
+    pub fn insert(&mut self, id: MeshId, mesh: Mesh)
+    {
+        self.meshes.insert(id, mesh);
+    }
+
+    pub fn get(&self, id: MeshId) -> Option<&Mesh>
+    {
+        self.meshes.get(&id)
+    }
+
+    /// Expects the device to be idle.
+    pub fn remove(&mut self, id: MeshId)
+    {
+        if let Some(mut mesh) = self.meshes.remove(&id)
+        {
+            mesh.destroy();
+        }
+    }
+
+    /// Moves mesh data out of sparsely used memory blocks. Expects the device to be idle.
+    /// Returns how many buffers were moved.
+    pub fn defragment(&mut self, reg: &LayerReg<()>) -> usize
+    {
+        let mut moved = 0;
+
+        for (id, mesh) in self.meshes.iter_mut()
+        {
+            match mesh.relocate(reg)
+            {
+                Ok(count) => moved += count,
+                Err(e) => log::error!("Failed to move {id:?} while defragmenting: {e}"),
+            }
+        }
+
+        moved
+    }
+
+    pub fn destroy(&mut self)
+    {
+        for mesh in self.meshes.values_mut()
+        {
+            mesh.destroy();
+        }
+
+        self.meshes.clear();
+        log::info!("Vulkan meshes destroyed");
+    }
+}
+
+
+impl LayerDispatch<()> for Meshes {}
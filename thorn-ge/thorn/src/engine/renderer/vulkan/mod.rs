@@ -2,33 +2,44 @@
 #![allow(unused)]
 
 
+mod allocator;
+mod buffer;
 mod command_buffer;
 mod command_pool;
+mod descriptor;
 mod framebuffer;
 mod image;
 mod instance;
 mod logical_device;
+mod mesh;
 mod physical_device;
+mod pipeline;
 mod renderpass;
+mod shader;
 mod surface;
 mod swapchain;
 mod sync;
+mod target;
+mod texture;
 
 
-use super::api::{FrameStatus, RenderAPI};
-use crate::{math::*, prelude::*, reg_inspect};
-use ash::vk;
-use command_buffer::CommandBuffers;
+use super::api::{FrameStatus, MemoryStats, MeshData, RenderAPI, TextureData, WindowSurface};
+use super::{CommandList, MaterialId, MeshId, TextureId, pipeline::PipelineDesc};
+use crate::engine::platform::WindowId;
+use crate::{prelude::*, reg_inspect};
+use allocator::Allocator;
 use command_pool::CommandPools;
-use framebuffer::FrameBuffers;
-use instance::Instance;
+use descriptor::{DescriptorLayouts, Material};
+use instance::{Instance, RawHandles};
 use logical_device::LogicalDevice;
+use mesh::{Mesh, Meshes};
 use physical_device::PhysicalDevice;
-use renderpass::Renderpass;
+use shader::ShaderModules;
+use shader_utils::ShaderBundle;
+use std::collections::BTreeMap;
 use surface::Surface;
-use swapchain::Swapchain;
-use sync::VkSync;
-use winit::raw_window_handle::{RawDisplayHandle, RawWindowHandle};
+use target::RenderTarget;
+use texture::{Texture, Textures};
 
 
 // 10 FPS
@@ -38,63 +49,111 @@ const FENCE_WAIT: u64 = 100_000_000;
 pub(crate) struct VulkanRenderer
 {
     reg: LayerReg<()>,
-    surface_width: u32,
-    surface_height: u32,
-    buffered_frames: u32,
-    frame: usize,
-    prev_frame: usize,
-    prev_image_index: usize,
-    image_index: usize,
+    targets: BTreeMap<WindowId, RenderTarget>,
+
+    // Shader modules are created from it along with the device
+    shaders: ShaderBundle,
+    materials: BTreeMap<MaterialId, Material>,
+
+    // Meshes created before the device, uploaded once it exists
+    pending_meshes: BTreeMap<MeshId, MeshData>,
+    next_mesh: u64,
+
+    // Same for textures
+    pending_textures: BTreeMap<TextureId, TextureData>,
+    next_texture: u64,
 }
 
 
 impl VulkanRenderer
 {
-    pub(crate) fn new() -> Self
+    pub(crate) fn new(shaders: ShaderBundle) -> Self
     {
         Self {
             reg: LayerReg::new(),
-            surface_width: 0,
-            surface_height: 0,
-            buffered_frames: 3,
-            frame: 0,
-            prev_frame: 0,
-            prev_image_index: 0,
-            image_index: 0,
+            targets: BTreeMap::new(),
+            shaders,
+            materials: BTreeMap::new(),
+            pending_meshes: BTreeMap::new(),
+            next_mesh: 0,
+            pending_textures: BTreeMap::new(),
+            next_texture: 0,
         }
     }
+
+    fn wait_idle(&self)
+    {
+        reg_inspect!(self.reg, d=LogicalDevice => unsafe {
+            let _ = d.logical_device.device_wait_idle();
+        });
+    }
+
+    /// Runs one frame step on the targets it applies to. Only fails if every target failed,
+    /// so one broken window does not stop the others.
+    fn each_target(
+        targets: &mut BTreeMap<WindowId, RenderTarget>,
+        applies: impl Fn(&RenderTarget) -> bool,
+        mut step: impl FnMut(WindowId, &mut RenderTarget) -> FrameStatus,
+    ) -> FrameStatus
+    {
+        let mut status = None;
+
+        for (id, target) in targets.iter_mut().filter(|(_, t)| applies(t))
+        {
+            match (step(*id, target), status)
+            {
+                (FrameStatus::Fatal, _) => return FrameStatus::Fatal,
+                (result, None | Some(FrameStatus::Failed)) => status = Some(result),
+                _ => (),
+            }
+        }
+
+        status.unwrap_or(FrameStatus::Success)
+    }
 }
 
 
 impl RenderAPI for VulkanRenderer
 {
-    fn initialize(
-        &mut self,
-        rdh: RawDisplayHandle,
-        rwh: RawWindowHandle,
-        w: u32,
-        h: u32,
-    ) -> crate::prelude::ThResult<()>
+    fn add_surface(&mut self, surface: WindowSurface) -> ThResult<()>
     {
-        self.surface_width = w;
-        self.surface_height = h;
-
-        if self.reg.get::<Instance>().is_none()
+        if self.targets.contains_key(&surface.id)
         {
-            self.reg.insert(Instance::new(rdh, rwh, &[])?);
+            return Err(ThError::RendererError(format!(
+                "{} already has a surface",
+                surface.id
+            )));
         }
 
-        if self.reg.get::<Surface>().is_none()
+        let handles = RawHandles {
+            display: surface.display,
+            window: surface.window,
+        };
+
+        // The first window decides on the instance and the GPU
+        if self.reg.get::<Instance>().is_none()
         {
-            let surf = Surface::new(&self.reg)?;
-            self.reg.insert(surf);
+            self.reg
+                .insert(Instance::new(surface.display, surface.window, &[])?);
         }
 
+        let vk_surface = Surface::new(&self.reg, handles)?;
+
         if self.reg.get::<PhysicalDevice>().is_none()
         {
-            let device = PhysicalDevice::new(&self.reg)?;
+            let device = PhysicalDevice::new(&self.reg, &vk_surface)?;
             self.reg.insert(device);
         }
+        else if !reg_read!(self.reg, PhysicalDevice).supports(&vk_surface)
+        {
+            let mut vk_surface = vk_surface;
+            vk_surface.destroy();
+
+            return Err(ThError::RendererError(format!(
+                "The GPU can not present to {}",
+                surface.id
+            )));
+        }
 
         if self.reg.get::<LogicalDevice>().is_none()
         {
@@ -102,75 +161,106 @@ impl RenderAPI for VulkanRenderer
             self.reg.insert(device);
         }
 
-        if self.reg.get::<VkSync>().is_none()
+        if self.reg.get::<Allocator>().is_none()
         {
-            let sync = VkSync::new(&self.reg)?;
-            self.reg.insert(sync);
+            let allocator = Allocator::new(&self.reg);
+            self.reg.insert(allocator);
         }
 
-        if self.reg.get::<Swapchain>().is_none()
+        if self.reg.get::<CommandPools>().is_none()
         {
-            let swapchain = Swapchain::new(&self.reg, w, h)?;
-
-            self.buffered_frames = swapchain.max_buffered_frames;
-
-            log::info!("Total Buffered Frames: {}", self.buffered_frames);
-
-            reg_inspect!(self.reg, sync=VkSync => {
-                sync.update_for_image_count(self.buffered_frames)?;
-            });
+            let pool = CommandPools::new(&self.reg)?;
+            self.reg.insert(pool);
+        }
 
-            self.reg.insert(swapchain);
+        if self.reg.get::<ShaderModules>().is_none()
+        {
+            let shaders = ShaderModules::new(&self.reg, &self.shaders);
+            self.reg.insert(shaders);
         }
 
-        if self.reg.get::<CommandPools>().is_none()
+        if self.reg.get::<DescriptorLayouts>().is_none()
         {
-            let pool = CommandPools::new(&self.reg)?;
-            self.reg.insert(pool);
+            self.reg.insert(DescriptorLayouts::new(&self.reg));
         }
 
-        if self.reg.get::<CommandBuffers>().is_none()
+        if self.reg.get::<Meshes>().is_none()
         {
-            let cbuffer = CommandBuffers::new(&self.reg)?;
-            self.reg.insert(cbuffer);
+            self.reg.insert(Meshes::new(&self.reg));
         }
 
-        if self.reg.get::<Renderpass>().is_none()
+        // A mesh that fails to upload is dropped, draws of it are skipped
+        for (id, data) in std::mem::take(&mut self.pending_meshes)
         {
-            let pass = Renderpass::new_default(&self.reg, w, h)?;
-            self.reg.insert(pass);
+            match Mesh::new(&self.reg, &data)
+            {
+                Ok(mesh) => reg_inspect!(self.reg, meshes=Meshes => meshes.insert(id, mesh)),
+                Err(e) => log::error!("Failed to upload {id:?}: {e}"),
+            }
         }
 
-        if self.reg.get::<FrameBuffers>().is_none()
+        if self.reg.get::<Textures>().is_none()
         {
-            let fbuffers = FrameBuffers::new(&self.reg)?;
-            self.reg.insert(fbuffers);
+            self.reg.insert(Textures::new());
+        }
 
-            reg_inspect!(self.reg, pass = Renderpass => {
-                pass.frame_buffer = self.reg.get();
-            });
+        for (id, data) in std::mem::take(&mut self.pending_textures)
+        {
+            match Texture::new(&self.reg, &data)
+            {
+                Ok(texture) =>
+                {
+                    reg_inspect!(self.reg, textures=Textures => textures.insert(id, texture))
+                }
+                Err(e) => log::error!("Failed to upload {id:?}: {e}"),
+            }
         }
 
-        log::info!("Vulkan Renderer Initialized");
+        let target = RenderTarget::new(
+            &self.reg,
+            vk_surface,
+            surface.size.width,
+            surface.size.height,
+            surface.vsync,
+        )?;
+
+        self.targets.insert(surface.id, target);
+
+        log::info!("Vulkan surface for {} initialized", surface.id);
         Ok(())
     }
 
+    fn remove_surface(&mut self, id: WindowId)
+    {
+        if let Some(mut target) = self.targets.remove(&id)
+        {
+            self.wait_idle();
+            target.destroy();
+
+            log::info!("Vulkan surface for {id} removed");
+        }
+    }
+
     fn destroy(&mut self)
     {
         // Wait until we are ready to shut down.
-        reg_inspect!(self.reg, d=LogicalDevice => unsafe {
-            let _ = d.logical_device.device_wait_idle();
-        });
+        self.wait_idle();
+
+        for target in self.targets.values_mut()
+        {
+            target.destroy();
+        }
+
+        self.targets.clear();
 
-        reg_inspect!(self.reg, l=FrameBuffers => l.destroy());
-        reg_inspect!(self.reg, l=Renderpass => l.destroy());
-        reg_inspect!(self.reg, l=CommandBuffers => l.destroy());
+        reg_inspect!(self.reg, l=Meshes => l.destroy());
+        reg_inspect!(self.reg, l=Textures => l.destroy());
+        reg_inspect!(self.reg, l=DescriptorLayouts => l.destroy());
+        reg_inspect!(self.reg, l=ShaderModules => l.destroy());
         reg_inspect!(self.reg, l=CommandPools => l.destroy());
-        reg_inspect!(self.reg, l=Swapchain => l.destroy());
-        reg_inspect!(self.reg, l=VkSync => l.destroy());
+        reg_inspect!(self.reg, l=Allocator => l.destroy());
         reg_inspect!(self.reg, l=LogicalDevice => l.destroy());
         reg_inspect!(self.reg, l=PhysicalDevice => l.destroy());
-        reg_inspect!(self.reg, l=Surface => l.destroy());
         reg_inspect!(self.reg, l=Instance => l.destroy());
 
         log::info!("Vulkan Renderer Destroyed");
@@ -178,173 +268,283 @@ impl RenderAPI for VulkanRenderer
 
     fn frame_prepare(&mut self) -> FrameStatus
     {
-        let mut swapchain_dirty = false;
-        reg_inspect!(self.reg, swapchain = Swapchain => {
+        Self::each_target(
+            &mut self.targets,
+            |t| !t.hidden,
+            |_, t| {
+                let status = t.frame_prepare();
+                t.in_frame = status == FrameStatus::Success;
+                status
+            },
+        )
+    }
+
+    fn frame_render(&mut self, commands: &BTreeMap<WindowId, CommandList>) -> FrameStatus
+    {
+        let empty = CommandList::default();
+        let materials = &self.materials;
+
+        Self::each_target(
+            &mut self.targets,
+            |t| t.in_frame,
+            |id, t| t.frame_render(commands.get(&id).unwrap_or(&empty), materials),
+        )
+    }
+
+    fn frame_finish(&mut self) -> FrameStatus
+    {
+        Self::each_target(
+            &mut self.targets,
+            |t| t.in_frame,
+            |_, t| {
+                t.in_frame = false;
+                t.frame_finish()
+            },
+        )
+    }
+
+    fn surface_size_changed(&mut self, id: WindowId, w: u32, h: u32) -> ThResult<()>
+    {
+        if let Some(target) = self.targets.get_mut(&id)
+        {
+            target.size_changed(w, h);
+        }
+
+        Ok(())
+    }
+
+    fn set_surface_hidden(&mut self, id: WindowId, hidden: bool)
+    {
+        if let Some(target) = self.targets.get_mut(&id)
+        {
+            target.hidden = hidden;
+        }
+    }
+
+    fn surface_lost(&mut self)
+    {
+        self.wait_idle();
 
-            // Fetch current frame index.
-            self.prev_frame = self.frame;
-            self.frame = swapchain.current_frame;
+        for target in self.targets.values_mut()
+        {
+            target.surface_lost();
+        }
+    }
 
-            if swapchain.is_dirty()
+    fn surface_recreated(&mut self, surface: WindowSurface) -> ThResult<()>
+    {
+        let handles = RawHandles {
+            display: surface.display,
+            window: surface.window,
+        };
+
+        match self.targets.get_mut(&surface.id)
+        {
+            Some(target) =>
             {
-                reg_inspect!(self.reg, d=LogicalDevice => unsafe {
-                   if let Err(e) = d.logical_device.device_wait_idle()
-                   {
-                       log::error!("Failed to wait until device is idle");
-                       return FrameStatus::Failed;
-                   }
-                });
+                target.surface_recreated(handles, surface.size.width, surface.size.height)
             }
+            None => self.add_surface(surface),
+        }
+    }
 
-            // Recreate swapchain if necessary
-            if let Ok(result) = swapchain.recreate_if_dirty()
+    fn create_material(&mut self, pipeline: PipelineDesc) -> ThResult<MaterialId>
+    {
+        for stage in [&pipeline.vertex, &pipeline.fragment]
+        {
+            if self.shaders.get(&stage.shader).is_none()
             {
-                swapchain_dirty = result;
+                return Err(ThError::RendererError(format!(
+                    "No shader {} in the shader bundle",
+                    stage.shader
+                )));
             }
-            else
+
+            // Modules exist once a surface was added, shaders that failed to load have none
+            if let Some(modules) = self.reg.get::<ShaderModules>()
+                && modules.read().unwrap().get(&stage.shader).is_none()
             {
-                log::error!("Swapchain recreation failed");
-                return FrameStatus::Failed;
+                return Err(ThError::RendererError(format!(
+                    "Shader {} failed to load",
+                    stage.shader
+                )));
             }
+        }
 
-            self.surface_width = swapchain.width;
-            self.surface_height = swapchain.height;
-            self.buffered_frames = swapchain.max_buffered_frames;
-        });
+        let id = self
+            .materials
+            .last_key_value()
+            .map_or(MaterialId(0), |(id, _)| MaterialId(id.0 + 1));
 
-        // Set new framebuffer dimensions
-        reg_inspect!(self.reg, pass = Renderpass => {
-            pass.width = self.surface_width;
-            pass.height = self.surface_height;
-        });
+        // Pipelines are created per window, on the material's first draw
+        self.materials.insert(id, Material::new(pipeline)?);
+        Ok(id)
+    }
 
-        // Update the Framebuffer if the swapchain was dirty
-        if swapchain_dirty
+    fn set_material_data(&mut self, id: MaterialId, binding: u32, data: Vec<u8>) -> ThResult<()>
+    {
+        self.materials
+            .get_mut(&id)
+            .ok_or(ThError::RendererError(format!("No material {id:?}")))?
+            .set_data(binding, data)
+    }
+
+    fn set_material_texture(
+        &mut self,
+        id: MaterialId,
+        binding: u32,
+        texture: TextureId,
+    ) -> ThResult<()>
+    {
+        if !self.pending_textures.contains_key(&texture)
+            && self
+                .reg
+                .get::<Textures>()
+                .is_none_or(|t| t.read().unwrap().get(texture).is_none())
         {
-            reg_inspect!(self.reg, fb = FrameBuffers => {
-                if let Err(e) = fb.regenerate()
-                {
-                    log::error!("Framebuffer regeneration failed: {e}");
-                    return FrameStatus::Failed;
-                }
-            });
+            return Err(ThError::RendererError(format!("No texture {texture:?}")));
         }
 
-        // Wait for the current image to be available...
-        reg_inspect!(self.reg, sync=VkSync => {
-            if let Err(e) = sync.frame_fences[self.frame].wait(FENCE_WAIT)
-            {
-                log::error!("Fence for this frame timed out, skipping");
-                return FrameStatus::Failed;
-            }
-        });
+        self.materials
+            .get_mut(&id)
+            .ok_or(ThError::RendererError(format!("No material {id:?}")))?
+            .set_texture(binding, texture)
+    }
+
+    fn create_mesh(&mut self, data: MeshData) -> ThResult<MeshId>
+    {
+        if data.vertex_count == 0
+        {
+            return Err(ThError::RendererError(
+                "Tried to create an empty mesh".into(),
+            ));
+        }
 
+        if let Some(&index) = data.indices.iter().find(|i| **i >= data.vertex_count)
+        {
+            return Err(ThError::RendererError(format!(
+                "Mesh index {index} is out of bounds for {} vertices",
+                data.vertex_count
+            )));
+        }
 
-        // Setup new image for this frame
-        reg_inspect!(self.reg, swapchain=Swapchain => {
-            match swapchain.get_next_image_index(FENCE_WAIT, self.frame)
-            {
-                Ok(index) => {
-                    self.prev_image_index = self.image_index;
-                    self.image_index = index as usize;
-                }
+        let id = MeshId(self.next_mesh);
 
-                Err(e) => {
-                    log::error!("Failed to aquire new image index to render to...");
-                     return FrameStatus::Failed;
-                }
+        match self.reg.get::<Meshes>()
+        {
+            Some(meshes) =>
+            {
+                let mesh = Mesh::new(&self.reg, &data)?;
+                meshes.write().unwrap().insert(id, mesh);
             }
-        });
 
-        reg_inspect!(self.reg, buffers=CommandBuffers => {
-            let cbuffer = &mut buffers.graphics[self.image_index];
-            cbuffer.reset();
-            cbuffer.begin(false, false);
+            None =>
+            {
+                self.pending_meshes.insert(id, data);
+            }
+        }
 
-            let viewport = vk::Viewport::default()
-                .x(0.0)
-                .y(0.0)
-                .width(self.surface_width as _)
-                .height(self.surface_height as _)
-                .min_depth(0.0)
-                .max_depth(1.0);
+        self.next_mesh += 1;
+        Ok(id)
+    }
 
-            let scissor = vk::Rect2D::default()
-                .offset(vk::Offset2D { x: 0, y: 0 })
-                .extent(vk::Extent2D { width: self.surface_width, height: self.surface_height });
+    fn destroy_mesh(&mut self, id: MeshId)
+    {
+        if self.pending_meshes.remove(&id).is_none()
+        {
+            // The mesh may still be in use by frames in flight
+            self.wait_idle();
+            reg_inspect!(self.reg, meshes=Meshes => meshes.remove(id));
+        }
+    }
 
-            cbuffer.set_viewport(viewport, scissor);
-        });
+    fn create_texture(&mut self, data: TextureData) -> ThResult<TextureId>
+    {
+        if data.width == 0 || data.height == 0
+        {
+            return Err(ThError::RendererError(
+                "Tried to create an empty texture".into(),
+            ));
+        }
 
-        reg_inspect!(self.reg, pass=Renderpass => {
-            pass.begin(self.image_index);
-        });
+        if data.pixels.len() != data.width as usize * data.height as usize * 4
+        {
+            return Err(ThError::RendererError(format!(
+                "{} bytes are not {}x{} RGBA pixels",
+                data.pixels.len(),
+                data.width,
+                data.height
+            )));
+        }
 
-        FrameStatus::Success
-    }
+        let id = TextureId(self.next_texture);
 
-    fn frame_render(&mut self) -> FrameStatus
-    {
-        reg_inspect!(self.reg, pass=Renderpass => {
-            pass.clear_color += Vec3::new(0.0, 0.001, 0.0);
+        match self.reg.get::<Textures>()
+        {
+            Some(textures) =>
+            {
+                let texture = Texture::new(&self.reg, &data)?;
+                textures.write().unwrap().insert(id, texture);
+            }
 
-            if pass.clear_color[G] > 1.0
+            None =>
             {
-                pass.clear_color[G] = 0.0;
+                self.pending_textures.insert(id, data);
             }
-        });
+        }
 
-        FrameStatus::Success
+        self.next_texture += 1;
+        Ok(id)
     }
 
-    fn frame_finish(&mut self) -> FrameStatus
+    fn destroy_texture(&mut self, id: TextureId)
     {
-        reg_inspect!(self.reg, pass=Renderpass => {
-            pass.end(self.image_index);
-        });
+        for material in self.materials.values_mut()
+        {
+            material.remove_texture(id);
+        }
 
-        reg_inspect!(self.reg, buffers=CommandBuffers => {
-            let mut cbuffer = &mut buffers.graphics[self.image_index];
-            cbuffer.end();
-        });
+        if self.pending_textures.remove(&id).is_none()
+        {
+            // The texture may still be in use by frames in flight
+            self.wait_idle();
+            reg_inspect!(self.reg, textures=Textures => textures.remove(id));
+        }
+    }
 
-        reg_inspect!(self.reg, sync=VkSync => {
-            sync.image_frames[self.image_index] = self.frame;
-            //sync.frame_fences[self.frame].wait(FENCE_WAIT);
-            sync.frame_fences[self.frame].reset();
-
-            let wait = sync.image_available[self.frame];
-            let signal = sync.queue_complete[self.frame];
-            let fence = sync.frame_fences[self.frame].fence;
-            let queue = reg_read!(self.reg, LogicalDevice).graphics_queue.unwrap();
-
-            reg_inspect!(self.reg, buffers=CommandBuffers => {
-               if let Err(e) = buffers.graphics[self.image_index].submit(queue, wait, signal, fence)
-               {
-                   log::error!("Failed to submit buffer to queue {e}");
-                   return FrameStatus::Failed;
-               }
-            });
-        });
+    fn defragment(&mut self)
+    {
+        if self.reg.get::<Meshes>().is_none()
+        {
+            return;
+        }
 
-        reg_inspect!(self.reg, swapchain=Swapchain => {
-           if let Err(e) = swapchain.present(self.image_index, self.frame)
-           {
-               log::error!("Failed to present swapchain {e}");
-               return FrameStatus::Failed;
-           }
-        });
+        self.wait_idle();
 
+        let moved = reg_write!(self.reg, Meshes).defragment(&self.reg);
+        let released = reg_write!(self.reg, Allocator).release_empty_blocks();
+        let stats = self.memory_stats();
 
-        FrameStatus::Success
+        log::info!(
+            "Vulkan memory defragmented: {moved} buffers moved, {released} blocks released, {} of {} bytes used",
+            stats.used,
+            stats.reserved
+        );
     }
 
-    fn surface_size_changed(&mut self, w: u32, h: u32) -> ThResult<()>
+    fn memory_stats(&self) -> MemoryStats
     {
-        reg_inspect!(self.reg, sc = Swapchain => {
-            sc.mark_dirty(w, h);
-        });
+        self.reg
+            .get::<Allocator>()
+            .map(|a| a.read().unwrap().stats())
+            .unwrap_or_default()
+    }
 
-        Ok(())
+    fn set_vsync(&mut self, id: WindowId, vsync: bool)
+    {
+        if let Some(target) = self.targets.get_mut(&id)
+        {
+            target.set_vsync(vsync);
+        }
     }
 }
@@ -330,20 +330,17 @@ pub struct PhysicalDevice
 {
     pub device: vk::PhysicalDevice,
     pub props: PhysicalDeviceProps,
-    surface: Layer<Surface>,
 }
 
 
 impl PhysicalDevice
 {
-    pub fn new(reg: &LayerReg<()>) -> ThResult<Self>
+    /// Picks the best GPU that can present to `surface`.
+    pub fn new(reg: &LayerReg<()>, surface: &Surface) -> ThResult<Self>
     {
         let instance = reg.get_unchecked::<Instance>();
         let instance = instance.read().unwrap();
 
-        let surface = reg.get_unchecked::<Surface>();
-        let surface = surface.read().unwrap();
-
         let requirements = PhysicalDeviceProps::default();
 
         let devices = unsafe { instance.instance.enumerate_physical_devices()? };
@@ -351,7 +348,7 @@ impl PhysicalDevice
         // Filter out devices that do not meet the requirements
         let mut useable_devices = devices
             .iter()
-            .filter_map(|d| requirements.check_device(*d, &instance, &surface))
+            .filter_map(|d| requirements.check_device(*d, &instance, surface))
             .collect::<Vec<_>>();
 
         // Rank the available devices
@@ -364,26 +361,25 @@ impl PhysicalDevice
             .ok_or(ThError::RendererError("No useable GPU found".into()))?;
 
         log::info!("Vulkan physical device created");
-        Ok(Self {
-            device,
-            props,
-            surface: reg.get_unchecked(),
-        })
+        Ok(Self { device, props })
     }
 
-    pub fn update_capabilities(&mut self)
+    /// Whether the present queue can show images on a surface other than the one
+    /// this device was picked for.
+    pub fn supports(&self, surface: &Surface) -> bool
     {
-        let surface = self.surface.read().unwrap();
+        let Some(queue) = self.props.present_queue
+        else
+        {
+            return false;
+        };
 
-        // I dont really expect this to fail, at this point
-        if let Ok(caps) = unsafe {
+        unsafe {
             surface
                 .loader
-                .get_physical_device_surface_capabilities(self.device, surface.surface)
-        }
-        {
-            self.props.surface_capabilities = caps;
+                .get_physical_device_surface_support(self.device, queue, surface.surface)
         }
+        .unwrap_or(false)
     }
 
     pub fn destroy(&mut self)
@@ -0,0 +1,325 @@
+use super::{
+    descriptor::DescriptorLayouts,
+    logical_device::LogicalDevice,
+    renderpass::Renderpass,
+    shader::ShaderModules,
+};
+use crate::engine::renderer::pipeline::{
+    BlendMode,
+    CullMode,
+    PipelineDesc,
+    ShaderStage,
+    Topology,
+    VertexFormat,
+};
+use crate::{math::Mat4, prelude::*};
+use ash::vk;
+use std::collections::{HashMap, HashSet};
+use std::ffi::CString;
+
+
+/// Size of the push constants every pipeline takes: the view projection and the transform.
+pub const PUSH_CONSTANTS_SIZE: u32 = 2 * size_of::<Mat4>() as u32;
+
+
+#[derive(Clone, Copy)]
+pub struct Pipeline
+{
+    pub pipeline: vk::Pipeline,
+    pub layout: vk::PipelineLayout,
+}
+
+
+/// Graphics pipelines for one renderpass, created on first use and cached by their description.
+pub struct Pipelines
+{
+    cache: HashMap<PipelineDesc, Pipeline>,
+
+    // Descriptions that could not be turned into a pipeline, which are not tried again
+    failed: HashSet<PipelineDesc>,
+    device: Layer<LogicalDevice>,
+    shaders: Layer<ShaderModules>,
+    renderpass: Layer<Renderpass>,
+    layouts: Layer<DescriptorLayouts>,
+}
+
+
+impl Pipelines
+{
+    pub fn new(reg: &LayerReg<()>) -> Self
+    {
+        Self {
+            cache: HashMap::new(),
+            failed: HashSet::new(),
+            device: reg.get_unchecked(),
+            shaders: reg.get_unchecked(),
+            renderpass: reg.get_unchecked(),
+            layouts: reg.get_unchecked(),
+        }
+    }
+
+    /// The pipeline for the description, or `None` if it can not be created.
+    /// The failure is logged the first time only.
+    pub fn get(&mut self, desc: &PipelineDesc) -> Option<Pipeline>
+    {
+        if let Some(pipeline) = self.cache.get(desc)
+        {
+            return Some(*pipeline);
+        }
+
+        if self.failed.contains(desc)
+        {
+            return None;
+        }
+
+        let pipeline = match self.create(desc)
+        {
+            Ok(pipeline) => pipeline,
+            Err(e) =>
+            {
+                log::error!(
+                    "Failed to create pipeline for {}:{} and {}:{}: {e}",
+                    desc.vertex.shader,
+                    desc.vertex.entry,
+                    desc.fragment.shader,
+                    desc.fragment.entry
+                );
+
+                self.failed.insert(desc.clone());
+                return None;
+            }
+        };
+
+        self.cache.insert(desc.clone(), pipeline);
+
+        log::info!(
+            "Vulkan pipeline created for {}:{} and {}:{}",
+            desc.vertex.shader,
+            desc.vertex.entry,
+            desc.fragment.shader,
+            desc.fragment.entry
+        );
+
+        Some(pipeline)
+    }
+
+    fn create(&self, desc: &PipelineDesc) -> ThResult<Pipeline>
+    {
+        let device = &self.device.read().unwrap().logical_device;
+        let shaders = self.shaders.read().unwrap();
+
+        let module = |stage: &ShaderStage| {
+            shaders
+                .get(&stage.shader)
+                .ok_or(ThError::RendererError(format!(
+                    "No shader module for {}",
+                    stage.shader
+                )))
+        };
+
+        let entry = |stage: &ShaderStage| {
+            CString::new(stage.entry.as_str()).map_err(|_| {
+                ThError::RendererError(format!("Invalid entry point {:?}", stage.entry))
+            })
+        };
+
+        let vertex_entry = entry(&desc.vertex)?;
+        let fragment_entry = entry(&desc.fragment)?;
+
+        let stages = [
+            vk::PipelineShaderStageCreateInfo::default()
+                .stage(vk::ShaderStageFlags::VERTEX)
+                .module(module(&desc.vertex)?)
+                .name(&vertex_entry),
+            vk::PipelineShaderStageCreateInfo::default()
+                .stage(vk::ShaderStageFlags::FRAGMENT)
+                .module(module(&desc.fragment)?)
+                .name(&fragment_entry),
+        ];
+
+        // Meshes without vertex attributes are generated in the vertex shader
+        let layout = &desc.vertex_layout;
+        let bindings = match layout.attributes.is_empty()
+        {
+            true => vec![],
+            false =>
+            {
+                vec![
+                    vk::VertexInputBindingDescription::default()
+                        .binding(0)
+                        .stride(layout.stride)
+                        .input_rate(vk::VertexInputRate::VERTEX),
+                ]
+            }
+        };
+
+        let attributes = layout
+            .attributes
+            .iter()
+            .map(|a| {
+                vk::VertexInputAttributeDescription::default()
+                    .binding(0)
+                    .location(a.location)
+                    .format(vertex_format(a.format))
+                    .offset(a.offset)
+            })
+            .collect::<Vec<_>>();
+
+        let vertex_input = vk::PipelineVertexInputStateCreateInfo::default()
+            .vertex_binding_descriptions(&bindings)
+            .vertex_attribute_descriptions(&attributes);
+
+        let input_assembly =
+            vk::PipelineInputAssemblyStateCreateInfo::default().topology(match desc.topology
+            {
+                Topology::Triangles => vk::PrimitiveTopology::TRIANGLE_LIST,
+                Topology::Lines => vk::PrimitiveTopology::LINE_LIST,
+                Topology::Points => vk::PrimitiveTopology::POINT_LIST,
+            });
+
+        // Viewport and scissor are set per frame, so pipelines outlive resizes
+        let viewport = vk::PipelineViewportStateCreateInfo::default()
+            .viewport_count(1)
+            .scissor_count(1);
+
+        let dynamic_states = [vk::DynamicState::VIEWPORT, vk::DynamicState::SCISSOR];
+        let dynamic = vk::PipelineDynamicStateCreateInfo::default().dynamic_states(&dynamic_states);
+
+        let rasterization = vk::PipelineRasterizationStateCreateInfo::default()
+            .polygon_mode(vk::PolygonMode::FILL)
+            .cull_mode(match desc.cull
+            {
+                CullMode::None => vk::CullModeFlags::NONE,
+                CullMode::Front => vk::CullModeFlags::FRONT,
+                CullMode::Back => vk::CullModeFlags::BACK,
+            })
+            .front_face(vk::FrontFace::COUNTER_CLOCKWISE)
+            .line_width(1.0);
+
+        let multisample = vk::PipelineMultisampleStateCreateInfo::default()
+            .rasterization_samples(vk::SampleCountFlags::TYPE_1);
+
+        let depth_stencil = vk::PipelineDepthStencilStateCreateInfo::default()
+            .depth_test_enable(desc.depth.test)
+            .depth_write_enable(desc.depth.write)
+            .depth_compare_op(vk::CompareOp::LESS);
+
+        let blend_attachment = blend_attachment(desc.blend);
+        let blend = vk::PipelineColorBlendStateCreateInfo::default()
+            .attachments(std::slice::from_ref(&blend_attachment));
+
+        let push_constants = [vk::PushConstantRange::default()
+            .stage_flags(vk::ShaderStageFlags::VERTEX)
+            .offset(0)
+            .size(PUSH_CONSTANTS_SIZE)];
+
+        // The declared bindings are all in set 0
+        let set_layouts = match desc.bindings.is_empty()
+        {
+            true => vec![],
+            false => vec![self.layouts.write().unwrap().get(&desc.bindings)?],
+        };
+
+        let layout_info = vk::PipelineLayoutCreateInfo::default()
+            .push_constant_ranges(&push_constants)
+            .set_layouts(&set_layouts);
+
+        let layout = unsafe { device.create_pipeline_layout(&layout_info, None) }?;
+
+        let create_info = vk::GraphicsPipelineCreateInfo::default()
+            .stages(&stages)
+            .vertex_input_state(&vertex_input)
+            .input_assembly_state(&input_assembly)
+            .viewport_state(&viewport)
+            .rasterization_state(&rasterization)
+            .multisample_state(&multisample)
+            .depth_stencil_state(&depth_stencil)
+            .color_blend_state(&blend)
+            .dynamic_state(&dynamic)
+            .layout(layout)
+            .render_pass(self.renderpass.read().unwrap().renderpass)
+            .subpass(0);
+
+        let pipeline = unsafe {
+            device.create_graphics_pipelines(vk::PipelineCache::null(), &[create_info], None)
+        };
+
+        match pipeline
+        {
+            Ok(pipelines) =>
+            {
+                Ok(Pipeline {
+                    pipeline: pipelines[0],
+                    layout,
+                })
+            }
+
+            Err((_, e)) =>
+            {
+                unsafe { device.destroy_pipeline_layout(layout, None) };
+                Err(e.into())
+            }
+        }
+    }
+
+    pub fn destroy(&mut self)
+    {
+        let device = &self.device.read().unwrap().logical_device;
+
+        for (_, pipeline) in self.cache.drain()
+        {
+            unsafe {
+                device.destroy_pipeline(pipeline.pipeline, None);
+                device.destroy_pipeline_layout(pipeline.layout, None);
+            }
+        }
+
+        self.failed.clear();
+
+        log::info!("Vulkan pipelines destroyed");
+    }
+}
+
+
+impl LayerDispatch<()> for Pipelines {}
+
+
+fn vertex_format(format: VertexFormat) -> vk::Format
+{
+    match format
+    {
+        VertexFormat::Float => vk::Format::R32_SFLOAT,
+        VertexFormat::Vec2 => vk::Format::R32G32_SFLOAT,
+        VertexFormat::Vec3 => vk::Format::R32G32B32_SFLOAT,
+        VertexFormat::Vec4 => vk::Format::R32G32B32A32_SFLOAT,
+    }
+}
+
+
+fn blend_attachment(mode: BlendMode) -> vk::PipelineColorBlendAttachmentState
+{
+    let attachment = vk::PipelineColorBlendAttachmentState::default()
+        .color_write_mask(vk::ColorComponentFlags::RGBA);
+
+    let (src, dst) = match mode
+    {
+        BlendMode::Opaque => return attachment.blend_enable(false),
+        BlendMode::Alpha =>
+        {
+            (
+                vk::BlendFactor::SRC_ALPHA,
+                vk::BlendFactor::ONE_MINUS_SRC_ALPHA,
+            )
+        }
+        BlendMode::Additive => (vk::BlendFactor::ONE, vk::BlendFactor::ONE),
+    };
+
+    attachment
+        .blend_enable(true)
+        .src_color_blend_factor(src)
+        .dst_color_blend_factor(dst)
+        .color_blend_op(vk::BlendOp::ADD)
+        .src_alpha_blend_factor(vk::BlendFactor::ONE)
+        .dst_alpha_blend_factor(vk::BlendFactor::ONE_MINUS_SRC_ALPHA)
+        .alpha_blend_op(vk::BlendOp::ADD)
+}
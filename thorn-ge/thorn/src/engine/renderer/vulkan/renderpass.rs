@@ -1,4 +1,4 @@
-use crate::{math::Vec3, prelude::*};
+use crate::{math::Vec4, prelude::*};
 use ash::vk::{self};
 
 use super::{
@@ -26,7 +26,7 @@ pub struct Renderpass
     pub pos_y: u32,
     pub width: u32,
     pub height: u32,
-    pub clear_color: Vec3,
+    pub clear_color: Vec4,
     pub state: State,
     pub depth: f32,
     pub stencil: u32,
@@ -49,7 +49,7 @@ impl Renderpass
         let swapchain = reg.get_unchecked::<Swapchain>();
         let swapchain = &swapchain.read().unwrap();
 
-        let clear_color = Vec3::new(0.47, 0.0, 0.16);
+        let clear_color = Vec4::new(0.0, 0.0, 0.0, 1.0);
 
         let mut attachments = [vk::AttachmentDescription::default(); 2];
 
@@ -73,7 +73,7 @@ impl Renderpass
         attachments[1] = vk::AttachmentDescription::default()
             .format(swapchain.depth_buffer_format)
             .samples(vk::SampleCountFlags::TYPE_1)
-            .load_op(vk::AttachmentLoadOp::DONT_CARE)
+            .load_op(vk::AttachmentLoadOp::CLEAR)
             .store_op(vk::AttachmentStoreOp::DONT_CARE)
             .stencil_load_op(vk::AttachmentLoadOp::CLEAR)
             .stencil_store_op(vk::AttachmentStoreOp::STORE)
@@ -143,7 +143,7 @@ impl Renderpass
         })
     }
 
-    pub fn set_clear_color(&mut self, color: Vec3)
+    pub fn set_clear_color(&mut self, color: Vec4)
     {
         self.clear_color = color;
     }
@@ -171,7 +171,7 @@ impl Renderpass
             // Color clear value
             vk::ClearValue {
                 color: vk::ClearColorValue {
-                    float32: [*col.x, *col.y, *col.z, 1.0],
+                    float32: [*col.x, *col.y, *col.z, *col.w],
                 },
             },
             // Depth clear value
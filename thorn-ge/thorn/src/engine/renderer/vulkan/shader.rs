@@ -0,0 +1,81 @@
+use super::logical_device::LogicalDevice;
+use crate::prelude::*;
+use ash::vk;
+use shader_utils::ShaderBundle;
+use std::collections::HashMap;
+use std::io::Cursor;
+
+
+/// A shader module for every valid shader in the bundle, by the shader's name.
+pub struct ShaderModules
+{
+    modules: HashMap<String, vk::ShaderModule>,
+    device: Layer<LogicalDevice>,
+}
+
+
+impl ShaderModules
+{
+    /// Shaders that fail to load are skipped, so only materials using them fail.
+    pub fn new(reg: &LayerReg<()>, bundle: &ShaderBundle) -> Self
+    {
+        let mut me = Self {
+            modules: HashMap::new(),
+            device: reg.get_unchecked(),
+        };
+
+        for shader in bundle.iter()
+        {
+            match me.create_module(shader.data())
+            {
+                Ok(module) =>
+                {
+                    me.modules.insert(shader.name().into(), module);
+                }
+
+                Err(e) => log::error!("Skipping shader {}: {e}", shader.name()),
+            }
+        }
+
+        log::info!("Vulkan shader modules created: {}", me.modules.len());
+        me
+    }
+
+    fn create_module(&self, data: &[u8]) -> ThResult<vk::ShaderModule>
+    {
+        let code = ash::util::read_spv(&mut Cursor::new(data))
+            .map_err(|e| ThError::RendererError(format!("Invalid SPIR-V: {e}")))?;
+
+        let create_info = vk::ShaderModuleCreateInfo::default().code(&code);
+
+        let module = unsafe {
+            self.device
+                .read()
+                .unwrap()
+                .logical_device
+                .create_shader_module(&create_info, None)
+        }?;
+
+        Ok(module)
+    }
+
+    pub fn get(&self, name: &str) -> Option<vk::ShaderModule>
+    {
+        self.modules.get(name).copied()
+    }
+
+    pub fn destroy(&mut self)
+    {
+        let device = &self.device.read().unwrap().logical_device;
+
+        for (_, module) in self.modules.drain()
+        {
+            unsafe { device.destroy_shader_module(module, None) };
+        }
+
+        log::info!("Vulkan shader modules destroyed");
+    }
+}
+
+
+impl LayerDispatch<()> for ShaderModules {}
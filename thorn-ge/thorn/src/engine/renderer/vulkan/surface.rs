@@ -1,46 +1,78 @@
-use super::instance::Instance;
+use super::instance::{Instance, RawHandles};
 use crate::prelude::*;
-use ash::{khr::surface::Instance as SurfaceLoader, vk};
+use ash::{
+    khr::surface::Instance as SurfaceLoader,
+    vk::{self, Handle},
+};
 
 
 pub struct Surface
 {
     pub surface: vk::SurfaceKHR,
     pub loader: SurfaceLoader,
+    pub handles: RawHandles,
 }
 
 
 impl Surface
 {
-    pub fn new(reg: &LayerReg<()>) -> ThResult<Self>
+    pub fn new(reg: &LayerReg<()>, handles: RawHandles) -> ThResult<Self>
     {
         let instance = reg.get_unchecked::<Instance>();
         let instance = instance.read().unwrap();
 
-        let surface = unsafe {
-            ash_window::create_surface(
-                &instance.entry,
-                &instance.instance,
-                instance.handles.display,
-                instance.handles.window,
-                None,
-            )?
-        };
-
+        let surface = Self::create(&instance, handles)?;
         let loader = SurfaceLoader::new(&instance.entry, &instance.instance);
 
         log::info!("Vulkan surface created");
-        Ok(Self { surface, loader })
+        Ok(Self {
+            surface,
+            loader,
+            handles,
+        })
+    }
+
+    /// Creates a new surface for the window, after the old one was destroyed.
+    /// The window's handles might have changed in the meantime.
+    pub fn recreate(&mut self, instance: &Instance, handles: RawHandles) -> ThResult<()>
+    {
+        self.destroy();
+        self.surface = Self::create(instance, handles)?;
+        self.handles = handles;
+
+        log::info!("Vulkan surface recreated");
+        Ok(())
     }
 
     pub fn destroy(&mut self)
     {
+        if self.surface.is_null()
+        {
+            return;
+        }
+
         unsafe {
             self.loader.destroy_surface(self.surface, None);
         }
 
+        self.surface = vk::SurfaceKHR::null();
         log::info!("Vulkan surface destroyed");
     }
+
+    fn create(instance: &Instance, handles: RawHandles) -> ThResult<vk::SurfaceKHR>
+    {
+        let surface = unsafe {
+            ash_window::create_surface(
+                &instance.entry,
+                &instance.instance,
+                handles.display,
+                handles.window,
+                None,
+            )?
+        };
+
+        Ok(surface)
+    }
 }
 
 
@@ -1,4 +1,5 @@
 use super::{
+    allocator::Allocator,
     image::VkImage2D,
     instance::Instance,
     logical_device::LogicalDevice,
@@ -26,16 +27,18 @@ pub struct Swapchain
     pub current_frame: usize,
 
     device: Layer<LogicalDevice>,
+    allocator: Layer<Allocator>,
     physical_device: Layer<PhysicalDevice>,
     surface: Layer<Surface>,
     dirty: Option<(u32, u32)>,
     sync: Layer<VkSync>,
+    vsync: bool,
 }
 
 
 impl Swapchain
 {
-    pub fn new(reg: &LayerReg<()>, width: u32, height: u32) -> ThResult<Self>
+    pub fn new(reg: &LayerReg<()>, width: u32, height: u32, vsync: bool) -> ThResult<Self>
     {
         let swapchain_device = ash::khr::swapchain::Device::new(
             &reg.get_unchecked::<Instance>().read().unwrap().instance,
@@ -58,10 +61,12 @@ impl Swapchain
             images: vec![],
             views: vec![],
             device: reg.get_unchecked(),
+            allocator: reg.get_unchecked(),
             physical_device: reg.get_unchecked(),
             surface: reg.get_unchecked(),
             sync: reg.get_unchecked(),
             dirty: None,
+            vsync,
         };
 
         me.create()?;
@@ -75,6 +80,20 @@ impl Swapchain
         self.dirty.is_some()
     }
 
+    /// Recreates the swapchain with a different present mode, if it changed.
+    pub fn set_vsync(&mut self, vsync: bool)
+    {
+        if self.vsync != vsync
+        {
+            self.vsync = vsync;
+
+            if !self.is_dirty()
+            {
+                self.mark_dirty(self.width, self.height);
+            }
+        }
+    }
+
     pub fn mark_dirty(&mut self, w: u32, h: u32)
     {
         self.dirty = Some((w, h));
@@ -196,11 +215,21 @@ impl Swapchain
         log::info!("Vulkan swapchain destroyed");
     }
 
+    /// Destroys the swapchain before its surface goes away. Marking it dirty creates it again.
+    pub fn release(&mut self)
+    {
+        let _ = self.soft_destroy();
+        self.swapchain = vk::SwapchainKHR::null();
+        self.images.clear();
+
+        log::info!("Vulkan swapchain released");
+    }
+
     fn create(&mut self) -> ThResult<()>
     {
         // Choose a Format and present mode. Preferences are:
         // * B8G8R8A8_UNORM | SRGB_NONLINEAR
-        // * MAILBOX
+        // * FIFO with vsync, otherwise MAILBOX, then IMMEDIATE. FIFO is always supported.
 
         let format = {
             let formats = &self.physical_device.read().unwrap().props.surface_formats;
@@ -216,26 +245,36 @@ impl Swapchain
                 ))?)
         };
 
-        let present_mode = self
-            .physical_device
-            .read()
-            .unwrap()
-            .props
-            .present_modes
-            .iter()
-            .find(|m| **m == vk::PresentModeKHR::MAILBOX)
-            .copied()
-            .unwrap_or(vk::PresentModeKHR::FIFO);
+        let preferred: &[vk::PresentModeKHR] = if self.vsync
+        {
+            &[vk::PresentModeKHR::FIFO]
+        }
+        else
+        {
+            &[vk::PresentModeKHR::MAILBOX, vk::PresentModeKHR::IMMEDIATE]
+        };
 
-        self.physical_device.write().unwrap().update_capabilities();
+        let present_mode = {
+            let modes = &self.physical_device.read().unwrap().props.present_modes;
 
-        let caps = &self
-            .physical_device
-            .read()
-            .unwrap()
-            .props
-            .surface_capabilities
-            .clone();
+            preferred
+                .iter()
+                .find(|m| modes.contains(m))
+                .copied()
+                .unwrap_or(vk::PresentModeKHR::FIFO)
+        };
+
+        // Every window has its own surface, which the device's capabilities were not queried for
+        let caps = &{
+            let surface = self.surface.read().unwrap();
+            let device = self.physical_device.read().unwrap().device;
+
+            unsafe {
+                surface
+                    .loader
+                    .get_physical_device_surface_capabilities(device, surface.surface)
+            }?
+        };
 
         let extend = vk::Extent2D {
             width: self.width,
@@ -307,6 +346,7 @@ impl Swapchain
 
         let depth_buffer = VkImage2D::new(
             self.device.clone(),
+            self.allocator.clone(),
             self.width,
             self.height,
             depth_format,
@@ -401,7 +441,8 @@ impl Swapchain
 
             if !self.depth_buffer.image.is_null()
             {
-                self.depth_buffer.destroy(self.device.clone());
+                self.depth_buffer
+                    .destroy(self.device.clone(), self.allocator.clone());
             }
         }
 
@@ -0,0 +1,422 @@
+use super::{
+    FENCE_WAIT,
+    FrameStatus,
+    allocator::Allocator,
+    command_buffer::CommandBuffers,
+    command_pool::CommandPools,
+    descriptor::{DescriptorLayouts, DescriptorSets, Material},
+    framebuffer::FrameBuffers,
+    instance::Instance,
+    instance::RawHandles,
+    logical_device::LogicalDevice,
+    mesh::Meshes,
+    physical_device::PhysicalDevice,
+    pipeline::Pipelines,
+    renderpass::Renderpass,
+    shader::ShaderModules,
+    surface::Surface,
+    swapchain::Swapchain,
+    sync::VkSync,
+    texture::Textures,
+};
+use crate::engine::renderer::{CommandList, MaterialId, as_bytes};
+use crate::{math::*, prelude::*, reg_inspect};
+use ash::vk;
+use std::collections::BTreeMap;
+
+
+/// Everything needed to draw into one window: its surface, the swapchain and the resources
+/// sized after it. The device level layers are shared with the renderer through the
+/// target's own registry, so each resource finds its dependencies as usual.
+pub(super) struct RenderTarget
+{
+    reg: LayerReg<()>,
+    surface_width: u32,
+    surface_height: u32,
+    buffered_frames: u32,
+    frame: usize,
+    prev_frame: usize,
+    prev_image_index: usize,
+    image_index: usize,
+
+    // Hidden targets do not start new frames
+    pub hidden: bool,
+
+    // Whether a frame was started and still has to be finished
+    pub in_frame: bool,
+}
+
+
+impl RenderTarget
+{
+    pub fn new(
+        shared: &LayerReg<()>,
+        surface: Surface,
+        w: u32,
+        h: u32,
+        vsync: bool,
+    ) -> ThResult<Self>
+    {
+        let mut reg = LayerReg::new();
+        reg.insert_any(shared.get_unchecked::<Instance>().into());
+        reg.insert_any(shared.get_unchecked::<PhysicalDevice>().into());
+        reg.insert_any(shared.get_unchecked::<LogicalDevice>().into());
+        reg.insert_any(shared.get_unchecked::<Allocator>().into());
+        reg.insert_any(shared.get_unchecked::<CommandPools>().into());
+        reg.insert_any(shared.get_unchecked::<ShaderModules>().into());
+        reg.insert_any(shared.get_unchecked::<DescriptorLayouts>().into());
+        reg.insert_any(shared.get_unchecked::<Meshes>().into());
+        reg.insert_any(shared.get_unchecked::<Textures>().into());
+        reg.insert(surface);
+
+        reg.insert(VkSync::new(&reg)?);
+
+        let swapchain = Swapchain::new(&reg, w, h, vsync)?;
+        let buffered_frames = swapchain.max_buffered_frames;
+
+        log::info!("Total Buffered Frames: {buffered_frames}");
+
+        reg_inspect!(reg, sync=VkSync => {
+            sync.update_for_image_count(buffered_frames)?;
+        });
+
+        reg.insert(swapchain);
+
+        let cbuffer = CommandBuffers::new(&reg)?;
+        reg.insert(cbuffer);
+
+        let pass = Renderpass::new_default(&reg, w, h)?;
+        reg.insert(pass);
+
+        let fbuffers = FrameBuffers::new(&reg)?;
+        reg.insert(fbuffers);
+
+        reg_inspect!(reg, pass = Renderpass => {
+            pass.frame_buffer = reg.get();
+        });
+
+        let pipelines = Pipelines::new(&reg);
+        reg.insert(pipelines);
+
+        let sets = DescriptorSets::new(&reg, buffered_frames);
+        reg.insert(sets);
+
+        Ok(Self {
+            reg,
+            surface_width: w,
+            surface_height: h,
+            buffered_frames,
+            frame: 0,
+            prev_frame: 0,
+            prev_image_index: 0,
+            image_index: 0,
+            hidden: false,
+            in_frame: false,
+        })
+    }
+
+    /// Expects the device to be idle.
+    pub fn destroy(&mut self)
+    {
+        reg_inspect!(self.reg, l=DescriptorSets => l.destroy());
+        reg_inspect!(self.reg, l=Pipelines => l.destroy());
+        reg_inspect!(self.reg, l=FrameBuffers => l.destroy());
+        reg_inspect!(self.reg, l=Renderpass => l.destroy());
+        reg_inspect!(self.reg, l=CommandBuffers => l.destroy());
+        reg_inspect!(self.reg, l=Swapchain => l.destroy());
+        reg_inspect!(self.reg, l=VkSync => l.destroy());
+        reg_inspect!(self.reg, l=Surface => l.destroy());
+    }
+
+    pub fn size_changed(&mut self, w: u32, h: u32)
+    {
+        reg_inspect!(self.reg, sc = Swapchain => {
+            sc.mark_dirty(w, h);
+        });
+    }
+
+    pub fn set_vsync(&mut self, vsync: bool)
+    {
+        reg_inspect!(self.reg, sc = Swapchain => {
+            sc.set_vsync(vsync);
+        });
+    }
+
+    /// Expects the device to be idle.
+    pub fn surface_lost(&mut self)
+    {
+        reg_inspect!(self.reg, l=Swapchain => l.release());
+        reg_inspect!(self.reg, l=Surface => l.destroy());
+        self.in_frame = false;
+    }
+
+    pub fn surface_recreated(&mut self, handles: RawHandles, w: u32, h: u32) -> ThResult<()>
+    {
+        let instance = self.reg.get_unchecked::<Instance>();
+        let instance = instance.read().unwrap();
+
+        reg_inspect!(self.reg, l=Surface => l.recreate(&instance, handles)?);
+
+        // The swapchain and framebuffers follow on the next frame
+        self.size_changed(w, h);
+        Ok(())
+    }
+
+    pub fn frame_prepare(&mut self) -> FrameStatus
+    {
+        let mut swapchain_dirty = false;
+        reg_inspect!(self.reg, swapchain = Swapchain => {
+
+            // Fetch current frame index.
+            self.prev_frame = self.frame;
+            self.frame = swapchain.current_frame;
+
+            if swapchain.is_dirty()
+            {
+                reg_inspect!(self.reg, d=LogicalDevice => unsafe {
+                   if let Err(e) = d.logical_device.device_wait_idle()
+                   {
+                       log::error!("Failed to wait until device is idle");
+                       return FrameStatus::Failed;
+                   }
+                });
+            }
+
+            // Recreate swapchain if necessary
+            if let Ok(result) = swapchain.recreate_if_dirty()
+            {
+                swapchain_dirty = result;
+            }
+            else
+            {
+                log::error!("Swapchain recreation failed");
+                return FrameStatus::Failed;
+            }
+
+            self.surface_width = swapchain.width;
+            self.surface_height = swapchain.height;
+            self.buffered_frames = swapchain.max_buffered_frames;
+        });
+
+        // Set new framebuffer dimensions
+        reg_inspect!(self.reg, pass = Renderpass => {
+            pass.width = self.surface_width;
+            pass.height = self.surface_height;
+        });
+
+        // Update the Framebuffer if the swapchain was dirty
+        if swapchain_dirty
+        {
+            reg_inspect!(self.reg, fb = FrameBuffers => {
+                if let Err(e) = fb.regenerate()
+                {
+                    log::error!("Framebuffer regeneration failed: {e}");
+                    return FrameStatus::Failed;
+                }
+            });
+
+            reg_inspect!(self.reg, sets = DescriptorSets => {
+                sets.set_buffered_frames(self.buffered_frames);
+            });
+        }
+
+        // Wait for the current image to be available...
+        reg_inspect!(self.reg, sync=VkSync => {
+            if let Err(e) = sync.frame_fences[self.frame].wait(FENCE_WAIT)
+            {
+                log::error!("Fence for this frame timed out, skipping");
+                return FrameStatus::Failed;
+            }
+        });
+
+
+        // Setup new image for this frame
+        reg_inspect!(self.reg, swapchain=Swapchain => {
+            match swapchain.get_next_image_index(FENCE_WAIT, self.frame)
+            {
+                Ok(index) => {
+                    self.prev_image_index = self.image_index;
+                    self.image_index = index as usize;
+                }
+
+                Err(e) => {
+                    log::error!("Failed to aquire new image index to render to...");
+                     return FrameStatus::Failed;
+                }
+            }
+        });
+
+        reg_inspect!(self.reg, buffers=CommandBuffers => {
+            let cbuffer = &mut buffers.graphics[self.image_index];
+            cbuffer.reset();
+            cbuffer.begin(false, false);
+
+            let viewport = vk::Viewport::default()
+                .x(0.0)
+                .y(0.0)
+                .width(self.surface_width as _)
+                .height(self.surface_height as _)
+                .min_depth(0.0)
+                .max_depth(1.0);
+
+            let scissor = vk::Rect2D::default()
+                .offset(vk::Offset2D { x: 0, y: 0 })
+                .extent(vk::Extent2D { width: self.surface_width, height: self.surface_height });
+
+            cbuffer.set_viewport(viewport, scissor);
+        });
+
+        FrameStatus::Success
+    }
+
+    pub fn frame_render(
+        &mut self,
+        commands: &CommandList,
+        materials: &BTreeMap<MaterialId, Material>,
+    ) -> FrameStatus
+    {
+        // The clear color is applied when the pass begins
+        reg_inspect!(self.reg, pass=Renderpass => {
+            pass.set_clear_color(commands.clear_color);
+            pass.begin(self.image_index);
+        });
+
+        let cbuffer = reg_read!(self.reg, CommandBuffers).graphics[self.image_index].buffer;
+        let view_projection = commands.camera.view_projection();
+
+        let meshes = self.reg.get_unchecked::<Meshes>();
+        let meshes = meshes.read().unwrap();
+
+        reg_inspect!(self.reg, pipelines=Pipelines => {
+            for draw in &commands.draws
+            {
+                let Some(mesh) = meshes.get(draw.mesh)
+                else
+                {
+                    log::warn!("Skipping a draw with unknown mesh {:?}", draw.mesh);
+                    continue;
+                };
+
+                let Some(material) = materials.get(&draw.material)
+                else
+                {
+                    log::warn!("Skipping a draw with unknown material {:?}", draw.material);
+                    continue;
+                };
+
+                // Generated meshes need a material without vertex input and vice versa
+                if mesh.has_vertices() == material.desc.vertex_layout.attributes.is_empty()
+                {
+                    log::warn!(
+                        "Skipping a draw of {:?}, whose vertices do not match the vertex layout of {:?}",
+                        draw.mesh,
+                        draw.material
+                    );
+                    continue;
+                }
+
+                let Some(pipeline) = pipelines.get(&material.desc)
+                else
+                {
+                    continue;
+                };
+
+                let set = match material.desc.bindings.is_empty()
+                {
+                    true => None,
+                    false =>
+                    {
+                        let set = reg_write!(self.reg, DescriptorSets)
+                            .get(&self.reg, draw.material, material, self.frame);
+
+                        match set
+                        {
+                            Ok(set) => Some(set),
+                            Err(e) =>
+                            {
+                                log::error!("Failed to update descriptor set: {e}");
+                                continue;
+                            }
+                        }
+                    }
+                };
+
+                let push_constants = [view_projection, draw.transform];
+
+                reg_inspect!(self.reg, d=LogicalDevice => unsafe {
+                    d.logical_device.cmd_bind_pipeline(
+                        cbuffer,
+                        vk::PipelineBindPoint::GRAPHICS,
+                        pipeline.pipeline,
+                    );
+
+                    d.logical_device.cmd_push_constants(
+                        cbuffer,
+                        pipeline.layout,
+                        vk::ShaderStageFlags::VERTEX,
+                        0,
+                        as_bytes(&push_constants),
+                    );
+
+                    if let Some(set) = set
+                    {
+                        d.logical_device.cmd_bind_descriptor_sets(
+                            cbuffer,
+                            vk::PipelineBindPoint::GRAPHICS,
+                            pipeline.layout,
+                            0,
+                            &[set],
+                            &[],
+                        );
+                    }
+
+                    mesh.draw(&d.logical_device, cbuffer);
+                });
+            }
+        });
+
+        FrameStatus::Success
+    }
+
+    pub fn frame_finish(&mut self) -> FrameStatus
+    {
+        reg_inspect!(self.reg, pass=Renderpass => {
+            pass.end(self.image_index);
+        });
+
+        reg_inspect!(self.reg, buffers=CommandBuffers => {
+            let mut cbuffer = &mut buffers.graphics[self.image_index];
+            cbuffer.end();
+        });
+
+        reg_inspect!(self.reg, sync=VkSync => {
+            sync.image_frames[self.image_index] = self.frame;
+            //sync.frame_fences[self.frame].wait(FENCE_WAIT);
+            sync.frame_fences[self.frame].reset();
+
+            let wait = sync.image_available[self.frame];
+            let signal = sync.queue_complete[self.frame];
+            let fence = sync.frame_fences[self.frame].fence;
+            let queue = reg_read!(self.reg, LogicalDevice).graphics_queue.unwrap();
+
+            reg_inspect!(self.reg, buffers=CommandBuffers => {
+               if let Err(e) = buffers.graphics[self.image_index].submit(queue, wait, signal, fence)
+               {
+                   log::error!("Failed to submit buffer to queue {e}");
+                   return FrameStatus::Failed;
+               }
+            });
+        });
+
+        reg_inspect!(self.reg, swapchain=Swapchain => {
+           if let Err(e) = swapchain.present(self.image_index, self.frame)
+           {
+               log::error!("Failed to present swapchain {e}");
+               return FrameStatus::Failed;
+           }
+        });
+
+
+        FrameStatus::Success
+    }
+}
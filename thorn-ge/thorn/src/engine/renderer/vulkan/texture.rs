@@ -0,0 +1,261 @@
+use super::{
+    allocator::Allocator,
+    buffer::VkBuffer,
+    command_pool::CommandPools,
+    image::VkImage2D,
+    logical_device::LogicalDevice,
+};
+use crate::engine::renderer::{TextureId, api::TextureData};
+use crate::prelude::*;
+use ash::vk;
+use std::collections::BTreeMap;
+
+
+const FORMAT: vk::Format = vk::Format::R8G8B8A8_SRGB;
+
+
+/// An RGBA image in device local memory with a sampler to read it in shaders.
+/// Unlike mesh buffers, texture images are not moved by defragmentation.
+pub struct Texture
+{
+    pub image: VkImage2D,
+    pub sampler: vk::Sampler,
+    device: Layer<LogicalDevice>,
+    allocator: Layer<Allocator>,
+}
+
+
+impl Texture
+{
+    /// Uploads the pixels through a staging buffer on the graphics queue, which also
+    /// moves the image into its shader read layout. Blocks until the upload is done.
+    pub fn new(reg: &LayerReg<()>, data: &TextureData) -> ThResult<Self>
+    {
+        let device = reg.get_unchecked::<LogicalDevice>();
+        let allocator = reg.get_unchecked::<Allocator>();
+
+        let mut staging = VkBuffer::new_staging(reg, &data.pixels)?;
+
+        let image = VkImage2D::new(
+            device.clone(),
+            allocator.clone(),
+            data.width,
+            data.height,
+            FORMAT,
+            vk::ImageTiling::OPTIMAL,
+            vk::ImageUsageFlags::TRANSFER_DST | vk::ImageUsageFlags::SAMPLED,
+            vk::MemoryPropertyFlags::DEVICE_LOCAL,
+            vk::ImageAspectFlags::COLOR,
+        );
+
+        let mut me = match image
+        {
+            Ok(image) =>
+            {
+                Self {
+                    image,
+                    sampler: vk::Sampler::null(),
+                    device,
+                    allocator,
+                }
+            }
+            Err(e) =>
+            {
+                staging.destroy();
+                return Err(e);
+            }
+        };
+
+        let result = me.upload(reg, &staging).and_then(|_| me.create_sampler());
+
+        staging.destroy();
+
+        match result
+        {
+            Ok(_) => Ok(me),
+            Err(e) =>
+            {
+                me.destroy();
+                Err(e)
+            }
+        }
+    }
+
+    fn upload(&self, reg: &LayerReg<()>, staging: &VkBuffer) -> ThResult<()>
+    {
+        let queue = reg_read!(reg, LogicalDevice)
+            .graphics_queue
+            .ok_or(ThError::RendererError("No Graphics Queue".into()))?;
+
+        let mut cbuffer = reg_read!(reg, CommandPools)
+            .graphics
+            .new_buffer_single_use()?;
+
+        let range = vk::ImageSubresourceRange::default()
+            .aspect_mask(vk::ImageAspectFlags::COLOR)
+            .level_count(1)
+            .layer_count(1);
+
+        let to_transfer = vk::ImageMemoryBarrier::default()
+            .old_layout(vk::ImageLayout::UNDEFINED)
+            .new_layout(vk::ImageLayout::TRANSFER_DST_OPTIMAL)
+            .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .image(self.image.image)
+            .subresource_range(range)
+            .dst_access_mask(vk::AccessFlags::TRANSFER_WRITE);
+
+        let to_shader = to_transfer
+            .old_layout(vk::ImageLayout::TRANSFER_DST_OPTIMAL)
+            .new_layout(vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL)
+            .src_access_mask(vk::AccessFlags::TRANSFER_WRITE)
+            .dst_access_mask(vk::AccessFlags::SHADER_READ);
+
+        let region = vk::BufferImageCopy::default()
+            .image_subresource(
+                vk::ImageSubresourceLayers::default()
+                    .aspect_mask(vk::ImageAspectFlags::COLOR)
+                    .layer_count(1),
+            )
+            .image_extent(vk::Extent3D {
+                width: self.image.width,
+                height: self.image.height,
+                depth: 1,
+            });
+
+        // Released before submitting
+        {
+            let device = &self.device.read().unwrap().logical_device;
+
+            unsafe {
+                device.cmd_pipeline_barrier(
+                    cbuffer.buffer,
+                    vk::PipelineStageFlags::TOP_OF_PIPE,
+                    vk::PipelineStageFlags::TRANSFER,
+                    vk::DependencyFlags::empty(),
+                    &[],
+                    &[],
+                    &[to_transfer],
+                );
+
+                device.cmd_copy_buffer_to_image(
+                    cbuffer.buffer,
+                    staging.buffer,
+                    self.image.image,
+                    vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                    &[region],
+                );
+
+                device.cmd_pipeline_barrier(
+                    cbuffer.buffer,
+                    vk::PipelineStageFlags::TRANSFER,
+                    vk::PipelineStageFlags::VERTEX_SHADER | vk::PipelineStageFlags::FRAGMENT_SHADER,
+                    vk::DependencyFlags::empty(),
+                    &[],
+                    &[],
+                    &[to_shader],
+                );
+            }
+        }
+
+        cbuffer.end_single_use(queue);
+        Ok(())
+    }
+
+    fn create_sampler(&mut self) -> ThResult<()>
+    {
+        let create_info = vk::SamplerCreateInfo::default()
+            .mag_filter(vk::Filter::LINEAR)
+            .min_filter(vk::Filter::LINEAR)
+            .mipmap_mode(vk::SamplerMipmapMode::LINEAR)
+            .address_mode_u(vk::SamplerAddressMode::REPEAT)
+            .address_mode_v(vk::SamplerAddressMode::REPEAT)
+            .address_mode_w(vk::SamplerAddressMode::REPEAT)
+            .max_lod(vk::LOD_CLAMP_NONE);
+
+        self.sampler = unsafe {
+            self.device
+                .read()
+                .unwrap()
+                .logical_device
+                .create_sampler(&create_info, None)
+        }?;
+
+        Ok(())
+    }
+
+    pub fn descriptor_info(&self) -> vk::DescriptorImageInfo
+    {
+        vk::DescriptorImageInfo::default()
+            .sampler(self.sampler)
+            .image_view(self.image.view.unwrap_or_default())
+            .image_layout(vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL)
+    }
+
+    pub fn destroy(&mut self)
+    {
+        unsafe {
+            self.device
+                .read()
+                .unwrap()
+                .logical_device
+                .destroy_sampler(self.sampler, None)
+        };
+
+        self.sampler = vk::Sampler::null();
+        self.image
+            .destroy(self.device.clone(), self.allocator.clone());
+    }
+}
+
+
+/// Every uploaded texture, shared by all render targets.
+pub struct Textures
+{
+    textures: BTreeMap<TextureId, Texture>,
+}
+
+
+impl Textures
+{
+    #[allow(clippy::new_without_default)]
+    pub fn new() -> Self
+    {
+        Self {
+            textures: BTreeMap::new(),
+        }
+    }
+
+    pub fn insert(&mut self, id: TextureId, texture: Texture)
+    {
+        self.textures.insert(id, texture);
+    }
+
+    pub fn get(&self, id: TextureId) -> Option<&Texture>
+    {
+        self.textures.get(&id)
+    }
+
+    /// Expects the device to be idle.
+    pub fn remove(&mut self, id: TextureId)
+    {
+        if let Some(mut texture) = self.textures.remove(&id)
+        {
+            texture.destroy();
+        }
+    }
+
+    pub fn destroy(&mut self)
+    {
+        for texture in self.textures.values_mut()
+        {
+            texture.destroy();
+        }
+
+        self.textures.clear();
+        log::info!("Vulkan textures destroyed");
+    }
+}
+
+
+impl LayerDispatch<()> for Textures {}
@@ -1,13 +1,10 @@
 use std::{
     error::Error,
-    sync::{
-        Arc,
-        atomic::{AtomicU64, Ordering},
-    },
+    sync::Arc,
     time::{Duration, Instant},
 };
 
-use crate::engine::core::MainLoopTask;
+use crate::engine::core::{MainLoopSync, MainLoopTask};
 use crate::prelude::*;
 
 
@@ -24,10 +21,10 @@ impl Plugin<LayerEvent> for TasksPlugin
 
     fn load(
         &mut self,
-        reg: &LayerReg<LayerEvent>,
+        ctx: &PluginContext<LayerEvent>,
     ) -> Result<AnyLayer<LayerEvent>, Box<dyn std::error::Error>>
     {
-        let core = reg
+        let core = ctx
             .get::<Core>()
             .ok_or(ThError::Error("Failed to fetch core layer".into()))?;
         Ok(AnyLayer::new(Tasks::new(core)))
@@ -46,13 +43,10 @@ struct Task
 
 impl Task
 {
-    fn new<T>(repeats: Duration, task: T) -> Self
+    fn new<T>(id: u64, repeats: Duration, task: T) -> Self
     where
         T: Fn(&FrameInfo) -> Result<(), Box<dyn Error>> + Send + Sync + 'static,
     {
-        static ID: AtomicU64 = AtomicU64::new(0);
-        let id = ID.fetch_add(1, Ordering::Relaxed);
-
         Task {
             id,
             repeats,
@@ -68,6 +62,9 @@ pub struct Tasks
     tasks: Vec<Task>,
     oneshots: Vec<Task>,
     core: Layer<Core>,
+
+    // Handed out by the layer, plugins link their own copy of thorn and its statics
+    next_id: u64,
 }
 
 
@@ -79,14 +76,22 @@ impl Tasks
             core,
             tasks: vec![],
             oneshots: vec![],
+            next_id: 0,
         }
     }
 
+    fn next_id(&mut self) -> u64
+    {
+        let id = self.next_id;
+        self.next_id += 1;
+        id
+    }
+
     pub fn oneshot<T>(&mut self, delay: Duration, task: T) -> u64
     where
         T: Fn(&FrameInfo) -> Result<(), Box<dyn Error>> + Send + Sync + 'static,
     {
-        let task = Task::new(delay, task);
+        let task = Task::new(self.next_id(), delay, task);
         let id = task.id;
         self.oneshots.push(task);
         id
@@ -97,21 +102,34 @@ impl Tasks
     where
         T: Fn(&FrameInfo) -> Result<(), Box<dyn Error>> + Send + Sync + 'static,
     {
-        let task = Task::new(interval, task);
+        let task = Task::new(self.next_id(), interval, task);
         let id = task.id;
         self.tasks.push(task);
         id
     }
 
-    pub fn hook(&mut self, hook: impl CoreHook + 'static)
+    pub fn hook(&mut self, hook: impl CoreHook + 'static) -> u64
     {
-        self.core.write().unwrap().add_hook(hook);
+        self.core.write().unwrap().add_hook(hook)
     }
 
+    pub fn remove_hook(&mut self, hook: u64)
+    {
+        self.core.write().unwrap().remove_hook(hook);
+    }
+
+    /// Cancels the task, including runs that were already scheduled for the next frame.
     pub fn cancel(&mut self, task: u64)
     {
         self.tasks.retain(|e| e.id != task);
         self.oneshots.retain(|e| e.id != task);
+        self.core.write().unwrap().cancel_task(task);
+    }
+
+    /// See [`Core::sync`].
+    pub fn sync(&self) -> MainLoopSync
+    {
+        self.core.read().unwrap().sync()
     }
 }
 
@@ -128,7 +146,7 @@ impl LayerDispatch<LayerEvent> for Tasks
             {
                 if task.last_executed.elapsed() > task.repeats
                 {
-                    core.schedule_task(task.cb.clone());
+                    core.schedule_task(task.id, task.cb.clone());
                     task.last_executed = Instant::now();
                 }
             }
@@ -138,7 +156,7 @@ impl LayerDispatch<LayerEvent> for Tasks
             {
                 if task.last_executed.elapsed() > task.repeats
                 {
-                    core.schedule_task(task.cb);
+                    core.schedule_task(task.id, task.cb);
                 }
                 else
                 {
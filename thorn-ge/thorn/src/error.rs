@@ -1,3 +1,4 @@
+use semver::{Version, VersionReq};
 use thiserror::Error;
 
 
@@ -13,8 +14,8 @@ pub enum ThError
     #[error("Failed to load Plugin {0:?} because of: {1}")]
     PluginLoadFailed(String, String),
 
-    #[error("Failed to find a suitable plugin load order")]
-    PluginLoadOrder,
+    #[error("Failed to resolve plugin dependencies:\n{}", lines(.0))]
+    PluginResolve(Vec<ResolveError>),
 
     #[error("{0}")]
     Error(String),
@@ -25,3 +26,57 @@ pub enum ThError
     #[error("{0}")]
     VulkanError(#[from] ash::vk::Result),
 }
+
+
+#[derive(Debug, Clone, Error)]
+pub enum ResolveError
+{
+    #[error("Plugin {plugin} depends on {dep}, which is missing")]
+    Missing
+    {
+        plugin: String, dep: String
+    },
+
+    #[error("Plugin {plugin} requires {dep} {req}, but only {found} is present")]
+    Incompatible
+    {
+        plugin: String,
+        dep: String,
+        req: VersionReq,
+        found: Version,
+    },
+
+    #[error("Plugin {plugin} conflicts with {other} {version}")]
+    Conflict
+    {
+        plugin: String,
+        other: String,
+        version: Version,
+    },
+
+    #[error("Plugin {plugin} can not be loaded, it is waiting on {}", waiting_on.join(", "))]
+    Unresolved
+    {
+        plugin: String,
+        waiting_on: Vec<String>,
+    },
+
+    #[error("Plugin {plugin} declares an invalid {reason}")]
+    Invalid
+    {
+        plugin: String, reason: String
+    },
+
+    #[error("Plugins {} depend on each other", .0.join(" -> "))]
+    Cycle(Vec<String>),
+}
+
+
+fn lines(errors: &[ResolveError]) -> String
+{
+    errors
+        .iter()
+        .map(|e| format!("    {e}"))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
@@ -0,0 +1,155 @@
+use super::{LayerKey, LayerReg};
+use crate::utils::short_type_name;
+use std::collections::HashMap;
+use std::fmt::{self, Display, Write};
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LockState
+{
+    Free,
+    Read,
+
+    // A writer that is waiting for the lock may also show up as write,
+    // depending on the platform's RwLock implementation.
+    Write,
+    Poisoned,
+}
+
+
+/// A snapshot of a single registered layer.
+#[derive(Debug, Clone)]
+pub struct LayerInfo
+{
+    pub key: LayerKey,
+    pub type_name: &'static str,
+    pub owner: Option<String>,
+    pub lock: LockState,
+
+    // Includes the two references the registry holds itself.
+    pub strong_count: usize,
+    pub deps: Vec<LayerKey>,
+}
+
+
+impl LayerInfo
+{
+    pub fn label(&self) -> String
+    {
+        match &self.key.name
+        {
+            Some(name) => format!("{}@{name}", short_type_name(self.type_name)),
+            None => short_type_name(self.type_name),
+        }
+    }
+}
+
+
+impl Display for LayerInfo
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+        write!(
+            f,
+            "{} (owner: {}, lock: {:?}, refs: {}, deps: {})",
+            self.label(),
+            self.owner.as_deref().unwrap_or("<none>"),
+            self.lock,
+            self.strong_count,
+            self.deps.len(),
+        )
+    }
+}
+
+
+impl<E> LayerReg<E>
+{
+    /// Lists all registered layers, sorted by their label.
+    pub fn inspect(&self) -> Vec<LayerInfo>
+    {
+        let mut infos = self
+            .layers
+            .iter()
+            .map(|(key, layer)| {
+                LayerInfo {
+                    key: key.clone(),
+                    type_name: layer.type_name(),
+                    owner: layer.owner().map(str::to_string),
+                    lock: layer.lock_state(),
+                    strong_count: layer.strong_count(),
+                    deps: layer.deps().to_vec(),
+                }
+            })
+            .collect::<Vec<_>>();
+
+        infos.sort_by_key(|i| i.label());
+        infos
+    }
+
+    /// A human readable listing of all layers, one per line.
+    pub fn dump(&self) -> String
+    {
+        let mut out = String::new();
+
+        for info in self.inspect()
+        {
+            let _ = writeln!(out, "{info}");
+        }
+
+        out
+    }
+
+    /// Renders the layers and their dependency edges as a Graphviz DOT graph.
+    pub fn to_dot(&self) -> String
+    {
+        let infos = self.inspect();
+        let nodes = infos
+            .iter()
+            .enumerate()
+            .map(|(i, info)| (&info.key, i))
+            .collect::<HashMap<_, _>>();
+
+        let mut out = String::from("digraph layers {\n    node [shape=box];\n");
+
+        for (i, info) in infos.iter().enumerate()
+        {
+            let color = match info.lock
+            {
+                LockState::Free => "black",
+                LockState::Read => "blue",
+                LockState::Write => "red",
+                LockState::Poisoned => "purple",
+            };
+
+            let _ = writeln!(
+                out,
+                "    l{i} [label=\"{}\\nowner: {}\\nlock: {:?}, refs: {}\", color={color}];",
+                escape(&info.label()),
+                escape(info.owner.as_deref().unwrap_or("<none>")),
+                info.lock,
+                info.strong_count,
+            );
+        }
+
+        for (i, info) in infos.iter().enumerate()
+        {
+            for dep in &info.deps
+            {
+                // Deps without a registered layer cannot be drawn
+                if let Some(d) = nodes.get(dep)
+                {
+                    let _ = writeln!(out, "    l{i} -> l{d};");
+                }
+            }
+        }
+
+        out.push_str("}\n");
+        out
+    }
+}
+
+
+fn escape(s: &str) -> String
+{
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
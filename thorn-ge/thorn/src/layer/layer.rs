@@ -1,6 +1,40 @@
 use std::any::{Any, TypeId, type_name};
 use std::ops::Deref;
-use std::sync::{Arc, RwLock};
+use std::sync::{Arc, TryLockError};
+
+use super::{LayerLock, LockState};
+use crate::plugin::PluginInfo;
+
+
+/// Identifies a layer inside of a [`LayerReg`](super::LayerReg).
+/// Unnamed keys refer to the default instance of a layer type,
+/// named keys allow multiple instances of the same type to coexist.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct LayerKey
+{
+    pub id: TypeId,
+    pub name: Option<String>,
+}
+
+
+impl LayerKey
+{
+    pub fn of<T: 'static>() -> Self
+    {
+        Self {
+            id: TypeId::of::<T>(),
+            name: None,
+        }
+    }
+
+    pub fn named<T: 'static>(name: impl Into<String>) -> Self
+    {
+        Self {
+            id: TypeId::of::<T>(),
+            name: Some(name.into()),
+        }
+    }
+}
 
 
 pub trait LayerDispatch<E>
@@ -9,21 +43,21 @@ pub trait LayerDispatch<E>
 }
 
 
-pub struct Layer<T: Send + Sync>(Arc<RwLock<T>>);
+pub struct Layer<T: Send + Sync>(Arc<LayerLock<T>>);
 
 
 impl<T: Send + Sync> Layer<T>
 {
     pub fn new(layer: T) -> Self
     {
-        Self(Arc::new(RwLock::new(layer)))
+        Self(Arc::new(LayerLock::new(layer)))
     }
 }
 
 
 impl<T: Send + Sync> Deref for Layer<T>
 {
-    type Target = Arc<RwLock<T>>;
+    type Target = Arc<LayerLock<T>>;
 
     fn deref(&self) -> &Self::Target
     {
@@ -49,7 +83,7 @@ where
 
     fn try_from(value: &AnyLayer<E>) -> Result<Self, Self::Error>
     {
-        if let Some(layer) = value.layer.downcast_ref::<Arc<RwLock<T>>>()
+        if let Some(layer) = value.layer.downcast_ref::<Arc<LayerLock<T>>>()
         {
             return Ok(Layer(layer.clone()));
         }
@@ -62,7 +96,11 @@ where
 pub struct AnyLayer<E>
 {
     id: TypeId,
-    dispatch: Arc<RwLock<dyn LayerDispatch<E> + Send + Sync>>,
+    name: Option<String>,
+    type_name: &'static str,
+    owner: Option<String>,
+    deps: Vec<LayerKey>,
+    dispatch: Arc<LayerLock<dyn LayerDispatch<E> + Send + Sync>>,
     layer: Box<dyn Any + Send + Sync>,
 }
 
@@ -80,6 +118,73 @@ impl<E> AnyLayer<E>
     {
         self.id
     }
+
+    pub fn name(&self) -> Option<&str>
+    {
+        self.name.as_deref()
+    }
+
+    pub fn key(&self) -> LayerKey
+    {
+        LayerKey {
+            id: self.id,
+            name: self.name.clone(),
+        }
+    }
+
+    /// Turns this layer into a named instance of its type.
+    pub fn with_name(mut self, name: impl Into<String>) -> Self
+    {
+        self.name = Some(name.into());
+        self
+    }
+
+    /// Records which plugin created this layer and what it depends on.
+    pub fn with_owner(mut self, info: &PluginInfo) -> Self
+    {
+        self.owner = Some(info.name.clone());
+        self.deps = info.deps.iter().map(|d| d.key.clone()).collect();
+        self
+    }
+
+    pub fn type_name(&self) -> &'static str
+    {
+        self.type_name
+    }
+
+    pub fn owner(&self) -> Option<&str>
+    {
+        self.owner.as_deref()
+    }
+
+    pub fn deps(&self) -> &[LayerKey]
+    {
+        &self.deps
+    }
+
+    pub fn lock_state(&self) -> LockState
+    {
+        match self.dispatch.try_write()
+        {
+            Ok(_) => LockState::Free,
+            Err(TryLockError::Poisoned(_)) => LockState::Poisoned,
+
+            Err(TryLockError::WouldBlock) =>
+            {
+                match self.dispatch.try_read()
+                {
+                    Ok(_) => LockState::Read,
+                    Err(TryLockError::Poisoned(_)) => LockState::Poisoned,
+                    Err(TryLockError::WouldBlock) => LockState::Write,
+                }
+            }
+        }
+    }
+
+    pub fn strong_count(&self) -> usize
+    {
+        Arc::strong_count(&self.dispatch)
+    }
 }
 
 
@@ -102,6 +207,10 @@ where
             dispatch: value.0.clone(),
             layer: Box::new(value.0),
             id: TypeId::of::<T>(),
+            name: None,
+            type_name: type_name::<T>(),
+            owner: None,
+            deps: vec![],
         }
     }
 }
@@ -1,15 +1,28 @@
+mod inspect;
 #[allow(clippy::module_inception)]
 mod layer;
+#[cfg(all(feature = "debug-locks", debug_assertions))]
+mod tracked;
+pub use inspect::{LayerInfo, LockState};
 pub use layer::*;
 
 
+// Layers use an instrumented lock with the `debug-locks` feature in debug builds.
+#[cfg(not(all(feature = "debug-locks", debug_assertions)))]
+pub type LayerLock<T> = std::sync::RwLock<T>;
+#[cfg(all(feature = "debug-locks", debug_assertions))]
+pub type LayerLock<T> = tracked::TrackedRwLock<T>;
+#[cfg(all(feature = "debug-locks", debug_assertions))]
+pub use tracked::held_locks_report;
+
+
 use std::any::TypeId;
 use std::collections::HashMap;
 
 
 pub struct LayerReg<E>
 {
-    layers: HashMap<TypeId, AnyLayer<E>>,
+    layers: HashMap<LayerKey, AnyLayer<E>>,
 }
 
 
@@ -24,12 +37,14 @@ impl<E> LayerReg<E>
 
     pub fn insert_any(&mut self, layer: AnyLayer<E>) -> Option<AnyLayer<E>>
     {
-        if self.layers.contains_key(&layer.id())
+        let key = layer.key();
+
+        if self.layers.contains_key(&key)
         {
             return Some(layer);
         }
 
-        self.layers.insert(layer.id(), layer);
+        self.layers.insert(key, layer);
 
         None
     }
@@ -38,24 +53,29 @@ impl<E> LayerReg<E>
     where
         T: LayerDispatch<E> + Send + Sync + 'static,
     {
-        let key = TypeId::of::<T>();
-
-        if self.layers.contains_key(&key)
-        {
-            return Some(layer);
-        }
+        self.insert_keyed(LayerKey::of::<T>(), layer)
+    }
 
-        let layer = Layer::new(layer);
-        self.layers.insert(key, layer.into());
+    pub fn insert_named<T>(&mut self, name: impl Into<String>, layer: T) -> Option<T>
+    where
+        T: LayerDispatch<E> + Send + Sync + 'static,
+    {
+        self.insert_keyed(LayerKey::named::<T>(name), layer)
+    }
 
-        None
+    pub fn contains(&self, key: &LayerKey) -> bool
+    {
+        self.layers.contains_key(key)
     }
 
     pub fn get<T: Send + Sync + 'static>(&self) -> Option<Layer<T>>
     {
-        self.layers
-            .get(&TypeId::of::<T>())
-            .map(|l| Layer::try_from(l).unwrap())
+        self.get_keyed(&LayerKey::of::<T>())
+    }
+
+    pub fn get_named<T: Send + Sync + 'static>(&self, name: &str) -> Option<Layer<T>>
+    {
+        self.get_keyed(&LayerKey::named::<T>(name))
     }
 
     pub fn get_unchecked<T: Send + Sync + 'static>(&self) -> Layer<T>
@@ -63,13 +83,38 @@ impl<E> LayerReg<E>
         self.get().unwrap()
     }
 
-    pub fn remove<T: Send + Sync + 'static>(&mut self) -> Option<Layer<T>>
+    pub fn get_named_unchecked<T: Send + Sync + 'static>(&self, name: &str) -> Layer<T>
+    {
+        self.get_named(name).unwrap()
+    }
+
+    /// Returns every instance of `T`, the default instance as well as all named ones.
+    pub fn get_all<T: Send + Sync + 'static>(&self) -> Vec<(Option<&str>, Layer<T>)>
     {
         self.layers
-            .remove(&TypeId::of::<T>())
+            .values()
+            .filter(|l| l.id() == TypeId::of::<T>())
+            .map(|l| (l.name(), Layer::try_from(l).unwrap()))
+            .collect()
+    }
+
+    pub fn remove<T: Send + Sync + 'static>(&mut self) -> Option<Layer<T>>
+    {
+        self.remove_any(&LayerKey::of::<T>())
+            .map(|l| Layer::try_from(&l).unwrap())
+    }
+
+    pub fn remove_named<T: Send + Sync + 'static>(&mut self, name: &str) -> Option<Layer<T>>
+    {
+        self.remove_any(&LayerKey::named::<T>(name))
             .map(|l| Layer::try_from(&l).unwrap())
     }
 
+    pub fn remove_any(&mut self, key: &LayerKey) -> Option<AnyLayer<E>>
+    {
+        self.layers.remove(key)
+    }
+
     pub fn dispatch(&mut self, event: E)
     {
         for layer in self.layers.values_mut()
@@ -77,6 +122,31 @@ impl<E> LayerReg<E>
             layer.dispatch(&event);
         }
     }
+
+    fn insert_keyed<T>(&mut self, key: LayerKey, layer: T) -> Option<T>
+    where
+        T: LayerDispatch<E> + Send + Sync + 'static,
+    {
+        if self.layers.contains_key(&key)
+        {
+            return Some(layer);
+        }
+
+        let mut layer = AnyLayer::new(layer);
+        if let Some(name) = key.name.clone()
+        {
+            layer = layer.with_name(name);
+        }
+
+        self.layers.insert(key, layer);
+
+        None
+    }
+
+    fn get_keyed<T: Send + Sync + 'static>(&self, key: &LayerKey) -> Option<Layer<T>>
+    {
+        self.layers.get(key).map(|l| Layer::try_from(l).unwrap())
+    }
 }
 
 
@@ -101,6 +171,17 @@ macro_rules! reg_inspect {
             }
         }
     }};
+
+    ($reg:expr, $name:ident = $layer:ident @ $instance:expr => $f:expr) => {{
+        if let Some(layer) = $reg.get_named::<$layer>($instance)
+        {
+            #[allow(unused_mut)]
+            if let Ok(mut $name) = layer.write()
+            {
+                $f;
+            }
+        }
+    }};
 }
 
 
@@ -137,6 +218,12 @@ macro_rules! reg_read {
     ($reg:expr, $layer:ident) => {
         $reg.get_unchecked::<$layer>().read().unwrap()
     };
+
+    ($reg:expr, $layer:ident @ $instance:expr) => {
+        $reg.get_named_unchecked::<$layer>($instance)
+            .read()
+            .unwrap()
+    };
 }
 
 
@@ -145,4 +232,10 @@ macro_rules! reg_write {
     ($reg:expr, $layer:ident) => {
         $reg.get_unchecked::<$layer>().write().unwrap()
     };
+
+    ($reg:expr, $layer:ident @ $instance:expr) => {
+        $reg.get_named_unchecked::<$layer>($instance)
+            .write()
+            .unwrap()
+    };
 }
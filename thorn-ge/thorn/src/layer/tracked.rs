@@ -0,0 +1,429 @@
+// Instrumented RwLock used for layers when the `debug-locks` feature is enabled in debug builds.
+// Every acquisition is recorded per thread, so that lock order inversions between layers
+// and locks that are held or waited on for too long can be reported with the layer names involved.
+
+use std::any::type_name;
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
+use std::ops::{Deref, DerefMut};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{
+    LazyLock,
+    LockResult,
+    Mutex,
+    PoisonError,
+    RwLock,
+    RwLockReadGuard,
+    RwLockWriteGuard,
+    TryLockError,
+    TryLockResult,
+};
+use std::thread::{self, ThreadId};
+use std::time::{Duration, Instant};
+
+
+// Anything above this is worth a warning, a frame should never take that long.
+const LONG_HOLD: Duration = Duration::from_millis(250);
+const LONG_WAIT: Duration = Duration::from_millis(250);
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mode
+{
+    Read,
+    Write,
+}
+
+
+#[derive(Clone)]
+struct Held
+{
+    lock: usize,
+    name: &'static str,
+    mode: Mode,
+    since: Instant,
+}
+
+
+struct Waiting
+{
+    name: &'static str,
+    mode: Mode,
+    since: Instant,
+}
+
+
+#[derive(Default)]
+struct ThreadLocks
+{
+    name: String,
+    held: Vec<Held>,
+    waiting: Option<Waiting>,
+}
+
+
+#[derive(Default)]
+struct Tracker
+{
+    threads: HashMap<ThreadId, ThreadLocks>,
+
+    // (a, b) means that b was locked while a was held. Stores the thread name it was first seen on.
+    order: HashMap<(usize, usize), String>,
+    reported: HashSet<(usize, usize)>,
+}
+
+
+static TRACKER: LazyLock<Mutex<Tracker>> = LazyLock::new(Mutex::default);
+
+
+fn current_thread() -> (ThreadId, String)
+{
+    let t = thread::current();
+    let name = t
+        .name()
+        .map(str::to_string)
+        .unwrap_or_else(|| format!("{:?}", t.id()));
+
+    (t.id(), name)
+}
+
+
+fn tracker() -> std::sync::MutexGuard<'static, Tracker>
+{
+    // A panic while tracking should not take the lock tracking down with it
+    TRACKER.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+
+/// Records a blocking acquisition attempt and checks it against the known lock order.
+fn before_lock(lock: usize, name: &'static str, mode: Mode)
+{
+    let (id, thread_name) = current_thread();
+    let mut problems = vec![];
+
+    {
+        let mut tracker = tracker();
+        let Tracker {
+            threads,
+            order,
+            reported,
+        } = &mut *tracker;
+
+        let locks = threads.entry(id).or_default();
+        locks.name = thread_name.clone();
+
+        for held in &locks.held
+        {
+            if held.lock == lock
+            {
+                if held.mode == Mode::Write || mode == Mode::Write
+                {
+                    problems.push(format!(
+                        "Thread {thread_name:?} is about to deadlock on itself: {name} is locked for {:?} while already holding it for {:?}",
+                        mode, held.mode
+                    ));
+                }
+
+                continue;
+            }
+
+            order
+                .entry((held.lock, lock))
+                .or_insert_with(|| thread_name.clone());
+
+            match order.get(&(lock, held.lock))
+            {
+                Some(other) if reported.insert((held.lock.min(lock), held.lock.max(lock))) =>
+                {
+                    problems.push(format!(
+                        "Lock order inversion: thread {thread_name:?} locks {name} while holding {}, but thread {other:?} locked them the other way around",
+                        held.name
+                    ));
+                }
+
+                _ => (),
+            }
+        }
+
+        locks.waiting = Some(Waiting {
+            name,
+            mode,
+            since: Instant::now(),
+        });
+    }
+
+    for p in problems
+    {
+        log::error!("{p}");
+    }
+}
+
+
+fn after_lock(lock: usize, name: &'static str, mode: Mode)
+{
+    let (id, thread_name) = current_thread();
+    let mut waited = None;
+
+    {
+        let mut tracker = tracker();
+        let locks = tracker.threads.entry(id).or_default();
+        locks.name = thread_name;
+
+        if let Some(w) = locks.waiting.take()
+        {
+            waited = Some(w.since.elapsed());
+        }
+
+        locks.held.push(Held {
+            lock,
+            name,
+            mode,
+            since: Instant::now(),
+        });
+    }
+
+    if let Some(waited) = waited.filter(|w| *w > LONG_WAIT)
+    {
+        log::warn!("Waited {waited:?} to lock {name} for {mode:?}");
+    }
+}
+
+
+fn after_unlock(lock: usize)
+{
+    let id = thread::current().id();
+
+    let released = tracker().threads.get_mut(&id).and_then(|locks| {
+        let i = locks.held.iter().rposition(|h| h.lock == lock)?;
+        Some(locks.held.remove(i))
+    });
+
+    if let Some(held) = released.filter(|h| h.since.elapsed() > LONG_HOLD)
+    {
+        log::warn!(
+            "{} was held for {:?} ({:?})",
+            held.name,
+            held.since.elapsed(),
+            held.mode
+        );
+    }
+}
+
+
+/// Describes which layer locks every thread currently holds or waits for.
+pub fn held_locks_report() -> String
+{
+    let tracker = tracker();
+    let mut out = String::new();
+
+    for locks in tracker.threads.values()
+    {
+        if locks.held.is_empty() && locks.waiting.is_none()
+        {
+            continue;
+        }
+
+        let _ = writeln!(out, "Thread {:?}:", locks.name);
+
+        for held in &locks.held
+        {
+            let _ = writeln!(
+                out,
+                "    holds {} for {:?} since {:?}",
+                held.name,
+                held.mode,
+                held.since.elapsed()
+            );
+        }
+
+        if let Some(w) = &locks.waiting
+        {
+            let _ = writeln!(
+                out,
+                "    waits on {} for {:?} since {:?}",
+                w.name,
+                w.mode,
+                w.since.elapsed()
+            );
+        }
+    }
+
+    out
+}
+
+
+pub struct TrackedRwLock<T: ?Sized>
+{
+    id: usize,
+    name: &'static str,
+    lock: RwLock<T>,
+}
+
+
+impl<T> TrackedRwLock<T>
+{
+    pub fn new(value: T) -> Self
+    {
+        static ID: AtomicUsize = AtomicUsize::new(0);
+
+        Self {
+            id: ID.fetch_add(1, Ordering::Relaxed),
+            name: type_name::<T>(),
+            lock: RwLock::new(value),
+        }
+    }
+}
+
+
+impl<T: ?Sized> TrackedRwLock<T>
+{
+    pub fn read(&self) -> LockResult<TrackedReadGuard<'_, T>>
+    {
+        before_lock(self.id, self.name, Mode::Read);
+        let result = self.lock.read();
+        after_lock(self.id, self.name, Mode::Read);
+
+        match result
+        {
+            Ok(guard) => Ok(self.read_guard(guard)),
+            Err(e) => Err(PoisonError::new(self.read_guard(e.into_inner()))),
+        }
+    }
+
+    pub fn write(&self) -> LockResult<TrackedWriteGuard<'_, T>>
+    {
+        before_lock(self.id, self.name, Mode::Write);
+        let result = self.lock.write();
+        after_lock(self.id, self.name, Mode::Write);
+
+        match result
+        {
+            Ok(guard) => Ok(self.write_guard(guard)),
+            Err(e) => Err(PoisonError::new(self.write_guard(e.into_inner()))),
+        }
+    }
+
+    // Try locks can not deadlock, so they are only tracked while being held.
+    pub fn try_read(&self) -> TryLockResult<TrackedReadGuard<'_, T>>
+    {
+        match self.lock.try_read()
+        {
+            Ok(guard) =>
+            {
+                after_lock(self.id, self.name, Mode::Read);
+                Ok(self.read_guard(guard))
+            }
+
+            Err(TryLockError::Poisoned(e)) =>
+            {
+                after_lock(self.id, self.name, Mode::Read);
+                Err(TryLockError::Poisoned(PoisonError::new(
+                    self.read_guard(e.into_inner()),
+                )))
+            }
+
+            Err(TryLockError::WouldBlock) => Err(TryLockError::WouldBlock),
+        }
+    }
+
+    pub fn try_write(&self) -> TryLockResult<TrackedWriteGuard<'_, T>>
+    {
+        match self.lock.try_write()
+        {
+            Ok(guard) =>
+            {
+                after_lock(self.id, self.name, Mode::Write);
+                Ok(self.write_guard(guard))
+            }
+
+            Err(TryLockError::Poisoned(e)) =>
+            {
+                after_lock(self.id, self.name, Mode::Write);
+                Err(TryLockError::Poisoned(PoisonError::new(
+                    self.write_guard(e.into_inner()),
+                )))
+            }
+
+            Err(TryLockError::WouldBlock) => Err(TryLockError::WouldBlock),
+        }
+    }
+
+    fn read_guard<'a>(&self, guard: RwLockReadGuard<'a, T>) -> TrackedReadGuard<'a, T>
+    {
+        TrackedReadGuard {
+            guard,
+            lock: self.id,
+        }
+    }
+
+    fn write_guard<'a>(&self, guard: RwLockWriteGuard<'a, T>) -> TrackedWriteGuard<'a, T>
+    {
+        TrackedWriteGuard {
+            guard,
+            lock: self.id,
+        }
+    }
+}
+
+
+pub struct TrackedReadGuard<'a, T: ?Sized>
+{
+    guard: RwLockReadGuard<'a, T>,
+    lock: usize,
+}
+
+
+impl<T: ?Sized> Deref for TrackedReadGuard<'_, T>
+{
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target
+    {
+        &self.guard
+    }
+}
+
+
+impl<T: ?Sized> Drop for TrackedReadGuard<'_, T>
+{
+    fn drop(&mut self)
+    {
+        after_unlock(self.lock);
+    }
+}
+
+
+pub struct TrackedWriteGuard<'a, T: ?Sized>
+{
+    guard: RwLockWriteGuard<'a, T>,
+    lock: usize,
+}
+
+
+impl<T: ?Sized> Deref for TrackedWriteGuard<'_, T>
+{
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target
+    {
+        &self.guard
+    }
+}
+
+
+impl<T: ?Sized> DerefMut for TrackedWriteGuard<'_, T>
+{
+    fn deref_mut(&mut self) -> &mut Self::Target
+    {
+        &mut self.guard
+    }
+}
+
+
+impl<T: ?Sized> Drop for TrackedWriteGuard<'_, T>
+{
+    fn drop(&mut self)
+    {
+        after_unlock(self.lock);
+    }
+}
@@ -0,0 +1,36 @@
+use crate::error::{ThError, ThResult};
+use serde::de::DeserializeOwned;
+
+
+/// A plugin's section of the project manifest.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PluginConfig(toml::Table);
+
+
+impl PluginConfig
+{
+    pub fn new(table: toml::Table) -> Self
+    {
+        Self(table)
+    }
+
+    pub fn is_empty(&self) -> bool
+    {
+        self.0.is_empty()
+    }
+
+    /// Deserializes the whole section into the plugin's config type.
+    /// Use `#[serde(default)]` on it, so that an empty or missing section works.
+    pub fn get<T: DeserializeOwned>(&self) -> ThResult<T>
+    {
+        self.0
+            .clone()
+            .try_into()
+            .map_err(|e: toml::de::Error| ThError::Error(e.message().to_string()))
+    }
+
+    pub fn table(&self) -> &toml::Table
+    {
+        &self.0
+    }
+}
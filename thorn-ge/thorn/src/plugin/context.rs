@@ -0,0 +1,188 @@
+use crate::engine::core::{FrameInfo, MainLoopSync};
+use crate::prelude::*;
+use std::error::Error;
+use std::ops::Deref;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+
+// Revokers of main loop registrations return a sync to wait for.
+type Revoker = Box<dyn FnOnce() -> Option<MainLoopSync> + Send + Sync>;
+
+
+/// Everything a plugin registered with other layers, so it can all be undone when the plugin unloads.
+#[derive(Clone, Default)]
+pub struct PluginScope(Arc<Mutex<Vec<Revoker>>>);
+
+
+impl PluginScope
+{
+    pub fn new() -> Self
+    {
+        Self::default()
+    }
+
+    /// Records how to undo a registration.
+    pub fn track(&self, revoke: impl FnOnce() + Send + Sync + 'static)
+    {
+        self.0.lock().unwrap().push(Box::new(move || {
+            revoke();
+            None
+        }));
+    }
+
+    /// Records how to undo a registration with the main loop. Revoking waits for the sync.
+    pub fn track_main_loop(&self, revoke: impl FnOnce() -> MainLoopSync + Send + Sync + 'static)
+    {
+        self.0
+            .lock()
+            .unwrap()
+            .push(Box::new(move || Some(revoke())));
+    }
+
+    /// Undoes all tracked registrations, the most recent first, and waits until the main loop
+    /// no longer runs any of the plugin's hooks or tasks. Returns false if that could not be
+    /// confirmed, in which case the plugin's code has to stay loaded.
+    pub fn revoke_all(&self) -> bool
+    {
+        let revokers = std::mem::take(&mut *self.0.lock().unwrap());
+
+        let syncs = revokers
+            .into_iter()
+            .rev()
+            .filter_map(|revoke| revoke())
+            .collect::<Vec<_>>();
+
+        // Waits on all of them, they complete in the same frame
+        let mut confirmed = true;
+        for sync in syncs
+        {
+            confirmed &= sync.wait();
+        }
+
+        confirmed
+    }
+}
+
+
+/// Handed to a plugin while it is loaded and unloaded. Gives access to the layer registry
+/// and registers tasks, hooks, subscriptions and gobjects within the plugin's scope.
+pub struct PluginContext<'a, E>
+{
+    reg: &'a LayerReg<E>,
+    scope: PluginScope,
+}
+
+
+impl<'a, E> PluginContext<'a, E>
+{
+    pub fn new(reg: &'a LayerReg<E>, scope: PluginScope) -> Self
+    {
+        Self { reg, scope }
+    }
+
+    pub fn reg(&self) -> &'a LayerReg<E>
+    {
+        self.reg
+    }
+
+    pub fn scope(&self) -> &PluginScope
+    {
+        &self.scope
+    }
+
+    fn layer<T: Send + Sync + 'static>(&self) -> ThResult<Layer<T>>
+    {
+        self.reg.get().ok_or_else(|| {
+            ThError::Error(format!(
+                "The {} layer is not loaded",
+                std::any::type_name::<T>()
+            ))
+        })
+    }
+}
+
+
+impl<E> Deref for PluginContext<'_, E>
+{
+    type Target = LayerReg<E>;
+
+    fn deref(&self) -> &Self::Target
+    {
+        self.reg
+    }
+}
+
+
+impl PluginContext<'_, LayerEvent>
+{
+    /// Like [`Tasks::repeating`], but cancelled when the plugin unloads.
+    pub fn repeating<T>(&self, interval: Duration, task: T) -> ThResult<u64>
+    where
+        T: Fn(&FrameInfo) -> Result<(), Box<dyn Error>> + Send + Sync + 'static,
+    {
+        let tasks = self.layer::<Tasks>()?;
+        let id = tasks.write().unwrap().repeating(interval, task);
+        self.scope.track_main_loop(move || {
+            let mut tasks = tasks.write().unwrap();
+            tasks.cancel(id);
+            tasks.sync()
+        });
+        Ok(id)
+    }
+
+    /// Like [`Tasks::oneshot`], but cancelled when the plugin unloads before it ran.
+    pub fn oneshot<T>(&self, delay: Duration, task: T) -> ThResult<u64>
+    where
+        T: Fn(&FrameInfo) -> Result<(), Box<dyn Error>> + Send + Sync + 'static,
+    {
+        let tasks = self.layer::<Tasks>()?;
+        let id = tasks.write().unwrap().oneshot(delay, task);
+        self.scope.track_main_loop(move || {
+            let mut tasks = tasks.write().unwrap();
+            tasks.cancel(id);
+            tasks.sync()
+        });
+        Ok(id)
+    }
+
+    /// Like [`Tasks::hook`], but removed when the plugin unloads.
+    pub fn hook(&self, hook: impl CoreHook + 'static) -> ThResult<u64>
+    {
+        let tasks = self.layer::<Tasks>()?;
+        let id = tasks.write().unwrap().hook(hook);
+        self.scope.track_main_loop(move || {
+            let mut tasks = tasks.write().unwrap();
+            tasks.remove_hook(id);
+            tasks.sync()
+        });
+        Ok(id)
+    }
+
+    /// Subscribes to the default receiver of `Ev` until the plugin unloads.
+    pub fn subscribe<Ev>(&self, subscriber: impl EventSubscriber<Ev> + 'static) -> ThResult<u64>
+    where
+        Ev: Send + Sync + 'static,
+    {
+        let receiver = self.layer::<EventReceiver<Ev>>()?;
+        let id = receiver.write().unwrap().subscribe(subscriber);
+        self.scope
+            .track(move || receiver.write().unwrap().unsubscribe(id));
+        Ok(id)
+    }
+
+    /// Adds a gobject, which is removed again when the plugin unloads.
+    pub fn add_gobj(&self, gobj: impl Gobject + 'static) -> ThResult<u64>
+    {
+        let manager = self.layer::<GobjectManager>()?;
+        let tasks = self.layer::<Tasks>()?;
+        let id = manager.write().unwrap().add_gobj(gobj);
+
+        // Gobjects are ticked from a main loop task
+        self.scope.track_main_loop(move || {
+            manager.write().unwrap().remove_obj(id);
+            tasks.read().unwrap().sync()
+        });
+        Ok(id)
+    }
+}
@@ -0,0 +1,136 @@
+// Entry point shared between the engine runner and plugins that are built as shared libraries.
+// Rust has no stable ABI, so only the leading fields of the declaration are read before the
+// engine and compiler versions have been checked to match the runner's.
+
+use super::Plugin;
+use crate::event::LayerEvent;
+use std::ffi::{CStr, c_char};
+
+
+/// Bumped whenever the layout of [`PluginDeclaration`] or [`LoggerHandoff`] changes.
+pub const THORN_ABI_VERSION: u32 = 1;
+
+pub const THORN_VERSION: &CStr = c_str(concat!(env!("CARGO_PKG_VERSION"), "\0"));
+pub const THORN_RUSTC_VERSION: &CStr = c_str(concat!(env!("THORN_RUSTC_VERSION"), "\0"));
+
+/// Name of the symbol [`export_plugin!`](crate::export_plugin) exports the declaration as.
+pub const PLUGIN_DECLARATION_SYMBOL: &[u8] = b"THORN_PLUGIN_DECLARATION\0";
+
+
+pub type DynPlugin = Box<dyn Plugin<LayerEvent> + Send + Sync>;
+
+
+#[repr(C)]
+pub struct PluginDeclaration
+{
+    pub abi_version: u32,
+    pub engine_version: *const c_char,
+    pub rustc_version: *const c_char,
+
+    // Only safe to call once the versions above match the runner's.
+    pub create: unsafe extern "C" fn() -> *mut DynPlugin,
+    pub set_logger: unsafe extern "C" fn(*const LoggerHandoff),
+}
+
+
+// Only ever points at static, immutable data.
+unsafe impl Sync for PluginDeclaration {}
+
+
+impl PluginDeclaration
+{
+    /// # Safety
+    /// `engine_version` must point to a valid nul terminated string.
+    pub unsafe fn engine_version(&self) -> &CStr
+    {
+        unsafe { CStr::from_ptr(self.engine_version) }
+    }
+
+    /// # Safety
+    /// `rustc_version` must point to a valid nul terminated string.
+    pub unsafe fn rustc_version(&self) -> &CStr
+    {
+        unsafe { CStr::from_ptr(self.rustc_version) }
+    }
+}
+
+
+/// A shared library carries its own copy of the `log` crate, which has to be pointed at the runner's logger.
+#[repr(C)]
+pub struct LoggerHandoff
+{
+    pub logger: &'static dyn log::Log,
+    pub level: log::LevelFilter,
+}
+
+
+impl LoggerHandoff
+{
+    pub fn current() -> Self
+    {
+        Self {
+            logger: log::logger(),
+            level: log::max_level(),
+        }
+    }
+}
+
+
+/// Installs the runner's logger inside of a plugin library. Used by [`export_plugin!`](crate::export_plugin).
+///
+/// # Safety
+/// `handoff` must point to a valid [`LoggerHandoff`] created by the same engine and compiler version.
+pub unsafe extern "C" fn set_logger(handoff: *const LoggerHandoff)
+{
+    let Some(handoff) = (unsafe { handoff.as_ref() })
+    else
+    {
+        return;
+    };
+
+    // Fails if the plugin is linked statically and the logger is already set, which is fine.
+    if log::set_logger(handoff.logger).is_ok()
+    {
+        log::set_max_level(handoff.level);
+    }
+}
+
+
+const fn c_str(s: &str) -> &CStr
+{
+    match CStr::from_bytes_with_nul(s.as_bytes())
+    {
+        Ok(s) => s,
+        Err(_) => panic!("version strings must not contain nul bytes"),
+    }
+}
+
+
+/// Exports a plugin from a `cdylib`, so the runner can load it from its plugins directory.
+/// Takes an expression that constructs the plugin.
+///
+/// ```ignore
+/// thorn::export_plugin!(SamplePlugin);
+/// ```
+#[macro_export]
+macro_rules! export_plugin {
+    ($plugin:expr) => {
+        #[unsafe(no_mangle)]
+        pub static THORN_PLUGIN_DECLARATION: $crate::plugin::dynamic::PluginDeclaration =
+            $crate::plugin::dynamic::PluginDeclaration {
+                abi_version: $crate::plugin::dynamic::THORN_ABI_VERSION,
+                engine_version: $crate::plugin::dynamic::THORN_VERSION.as_ptr(),
+                rustc_version: $crate::plugin::dynamic::THORN_RUSTC_VERSION.as_ptr(),
+                create: {
+                    unsafe extern "C" fn create() -> *mut $crate::plugin::dynamic::DynPlugin
+                    {
+                        let plugin: $crate::plugin::dynamic::DynPlugin = Box::new($plugin);
+                        Box::into_raw(Box::new(plugin))
+                    }
+
+                    create
+                },
+                set_logger: $crate::plugin::dynamic::set_logger,
+            };
+    };
+}
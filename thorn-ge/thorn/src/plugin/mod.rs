@@ -1,14 +1,40 @@
+pub mod config;
+pub mod context;
+pub mod dynamic;
 pub mod plugin_info;
-pub use plugin_info::PluginInfo;
+pub use config::PluginConfig;
+pub use context::{PluginContext, PluginScope};
+pub use plugin_info::{Dependency, PluginInfo};
 
 
-use crate::layer::{AnyLayer, LayerReg};
+use crate::layer::AnyLayer;
 
 
 pub trait Plugin<E>
 {
-    fn load(&mut self, reg: &LayerReg<E>) -> Result<AnyLayer<E>, Box<dyn std::error::Error>>;
+    fn load(&mut self, ctx: &PluginContext<E>) -> Result<AnyLayer<E>, Box<dyn std::error::Error>>;
     fn info(&self) -> PluginInfo;
-    fn notify_loaded(&mut self, _reg: &LayerReg<E>) {}
-    fn notify_unloaded(&mut self, _reg: &LayerReg<E>) {}
+
+    /// Receives the plugin's section of the project manifest. Called before `load`.
+    fn configure(&mut self, _config: &PluginConfig) -> Result<(), Box<dyn std::error::Error>>
+    {
+        Ok(())
+    }
+
+    fn notify_loaded(&mut self, _ctx: &PluginContext<E>) {}
+
+    /// Called before the plugin is unloaded. Everything registered through the context is revoked
+    /// right after. Anything else holding on to the plugin's layer or code has to be released here
+    /// for the plugin to be reloadable.
+    fn notify_unloaded(&mut self, _ctx: &PluginContext<E>) {}
+
+    /// Serializes state that should survive a reload. Called before `notify_unloaded`.
+    fn save_state(&mut self, _ctx: &PluginContext<E>) -> Option<Vec<u8>>
+    {
+        None
+    }
+
+    /// Receives the state saved before a reload, possibly by an older build of the plugin.
+    /// Called after the layer was registered, but before `notify_loaded`.
+    fn restore_state(&mut self, _ctx: &PluginContext<E>, _state: Vec<u8>) {}
 }
@@ -1,12 +1,101 @@
-use std::any::{TypeId, type_name};
+use crate::layer::LayerKey;
+use crate::utils::short_type_name;
+use semver::{Version, VersionReq};
+use std::any::type_name;
+
+
+/// A dependency on the layer of another plugin, or on a capability provided by one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Dependency
+{
+    pub key: LayerKey,
+    pub type_name: &'static str,
+    pub req: VersionReq,
+
+    // Optional dependencies only affect the load order, if they are present.
+    pub optional: bool,
+
+    // Why the requirement passed to `version` is invalid, reported by the resolver.
+    pub invalid_req: Option<String>,
+}
+
+
+impl Dependency
+{
+    pub fn on<T: 'static>() -> Self
+    {
+        Self {
+            key: LayerKey::of::<T>(),
+            type_name: type_name::<T>(),
+            req: VersionReq::STAR,
+            optional: false,
+            invalid_req: None,
+        }
+    }
+
+    pub fn on_named<T: 'static>(name: impl Into<String>) -> Self
+    {
+        Self {
+            key: LayerKey::named::<T>(name),
+            ..Self::on::<T>()
+        }
+    }
+
+    /// Restricts the dependency to versions matching `req`, like `"^1.2"` or `">=0.3, <0.5"`.
+    /// An invalid requirement keeps the plugin from being loaded.
+    pub fn version(mut self, req: &str) -> Self
+    {
+        match VersionReq::parse(req)
+        {
+            Ok(parsed) =>
+            {
+                self.req = parsed;
+                self.invalid_req = None;
+            }
+
+            Err(e) =>
+            {
+                self.invalid_req = Some(format!("requirement {req:?} on {}: {e}", self.label()))
+            }
+        }
+
+        self
+    }
+
+    pub fn optional(mut self) -> Self
+    {
+        self.optional = true;
+        self
+    }
+
+    /// The dependency's short type name, followed by its instance name if it has one.
+    pub fn label(&self) -> String
+    {
+        match &self.key.name
+        {
+            Some(name) => format!("{}@{name}", short_type_name(self.type_name)),
+            None => short_type_name(self.type_name),
+        }
+    }
+}
+
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct PluginInfo
 {
     pub name: String,
-    pub version: String,
-    pub identity: TypeId,
-    pub deps: Vec<TypeId>,
+    pub version: Version,
+    pub identity: LayerKey,
+    pub deps: Vec<Dependency>,
+
+    // Plugins that can not be loaded alongside this one, limited to the versions matching `req`.
+    pub conflicts: Vec<Dependency>,
+
+    // Virtual capabilities, which dependencies can name instead of a concrete plugin.
+    pub provides: Vec<LayerKey>,
+
+    // Why the version passed to `version` is invalid, reported by the resolver.
+    pub invalid_version: Option<String>,
 }
 
 
@@ -14,17 +103,14 @@ impl PluginInfo
 {
     pub fn build<T: 'static>() -> PluginInfo
     {
-        let name = type_name::<T>()
-            .split("<")
-            .filter_map(|p| p.split("::").last())
-            .collect::<Vec<_>>()
-            .join("<");
-
         PluginInfo {
-            name,
-            version: std::env!("CARGO_PKG_VERSION").into(),
-            identity: TypeId::of::<T>(),
+            name: short_type_name(type_name::<T>()),
+            version: Version::parse(std::env!("CARGO_PKG_VERSION")).unwrap(),
+            identity: LayerKey::of::<T>(),
             deps: Vec::new(),
+            conflicts: Vec::new(),
+            provides: Vec::new(),
+            invalid_version: None,
         }
     }
 
@@ -34,15 +120,100 @@ impl PluginInfo
         self
     }
 
-    pub fn version(mut self, version: impl Into<String>) -> Self
+    /// Sets the plugin's semver version. An invalid version keeps the plugin from being loaded.
+    pub fn version(mut self, version: &str) -> Self
     {
-        self.version = version.into();
+        match Version::parse(version)
+        {
+            Ok(parsed) =>
+            {
+                self.version = parsed;
+                self.invalid_version = None;
+            }
+
+            Err(e) => self.invalid_version = Some(format!("version {version:?}: {e}")),
+        }
+
+        self
+    }
+
+    /// Why the plugin's version or any of its requirements are invalid.
+    pub fn invalid(&self) -> impl Iterator<Item = &str>
+    {
+        let reqs = self.deps.iter().chain(&self.conflicts);
+
+        self.invalid_version
+            .as_deref()
+            .into_iter()
+            .chain(reqs.filter_map(|d| d.invalid_req.as_deref()))
+    }
+
+    /// Marks the plugin as providing a named instance of its layer.
+    pub fn instance(mut self, name: impl Into<String>) -> Self
+    {
+        self.identity.name = Some(name.into());
         self
     }
 
-    pub fn dep<T: 'static>(mut self) -> Self
+    pub fn dep<T: 'static>(self) -> Self
+    {
+        self.depends(Dependency::on::<T>())
+    }
+
+    pub fn dep_named<T: 'static>(self, name: impl Into<String>) -> Self
+    {
+        self.depends(Dependency::on_named::<T>(name))
+    }
+
+    /// Loads after `T` if it is present, without requiring it.
+    pub fn optional_dep<T: 'static>(self) -> Self
     {
-        self.deps.push(TypeId::of::<T>());
+        self.depends(Dependency::on::<T>().optional())
+    }
+
+    pub fn depends(mut self, dep: Dependency) -> Self
+    {
+        self.deps.push(dep);
         self
     }
+
+    pub fn conflicts<T: 'static>(self) -> Self
+    {
+        self.conflicts_with(Dependency::on::<T>())
+    }
+
+    pub fn conflicts_with(mut self, conflict: Dependency) -> Self
+    {
+        self.conflicts.push(conflict);
+        self
+    }
+
+    /// Lets dependencies on `T` be satisfied by this plugin.
+    pub fn provides<T: 'static>(mut self) -> Self
+    {
+        self.provides.push(LayerKey::of::<T>());
+        self
+    }
+
+    /// The plugin's name, followed by its instance name if it has one.
+    /// Used to refer to the plugin in the project manifest.
+    pub fn label(&self) -> String
+    {
+        match self.instance_name()
+        {
+            Some(instance) => format!("{}@{instance}", self.name),
+            None => self.name.clone(),
+        }
+    }
+
+    /// The plugin's identity followed by all capabilities it provides.
+    pub fn provided(&self) -> impl Iterator<Item = &LayerKey>
+    {
+        std::iter::once(&self.identity).chain(&self.provides)
+    }
+
+    pub fn instance_name(&self) -> Option<&str>
+    {
+        self.identity.name.as_deref()
+    }
 }
@@ -1,7 +1,7 @@
 pub use crate::engine::event::{EngineEvent, EventSubscriber};
 pub use crate::engine::prelude::*;
-pub use crate::error::{ThError, ThResult};
+pub use crate::error::{ResolveError, ThError, ThResult};
 pub use crate::event::LayerEvent;
-pub use crate::layer::{AnyLayer, Layer, LayerDispatch, LayerReg};
-pub use crate::plugin::{Plugin, PluginInfo};
+pub use crate::layer::{AnyLayer, Layer, LayerDispatch, LayerKey, LayerReg};
+pub use crate::plugin::{Dependency, Plugin, PluginConfig, PluginContext, PluginInfo, PluginScope};
 pub use crate::{either, if_do, reg_read, reg_write};
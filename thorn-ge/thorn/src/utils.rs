@@ -3,6 +3,16 @@ use std::sync::{Arc, LazyLock, Mutex};
 use std::thread::{self, JoinHandle};
 
 
+/// Strips the module paths from a type name. `a::B<c::D>` becomes `B<D>`
+pub fn short_type_name(name: &str) -> String
+{
+    name.split("<")
+        .filter_map(|p| p.split("::").last())
+        .collect::<Vec<_>>()
+        .join("<")
+}
+
+
 /// Show a non blocking message box
 pub fn show_msg_box(msg: impl Into<String>)
 {